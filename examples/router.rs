@@ -1,6 +1,6 @@
 use grout::{
 	hyper::{Body, Method, Server},
-	path, HttpRouter, PathSegment, Request, Response, ResponseBuilder, Router,
+	path, Request, Response, ResponseBuilder, Router,
 };
 
 async fn handler(params: Vec<String>, _req: Request) -> Response {
@@ -23,7 +23,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 		.register(Method::POST, path![foo / _ / bar / _ / baz], handler)
 		.register(Method::GET, path![_], other_handler);
 
-	let router = HttpRouter::from(builder);
+	let router = builder.build()?;
 
 	let server = Server::bind(&addr).serve(router);
 	println!("Listening on http://{}", addr);
@@ -0,0 +1,302 @@
+//! Building routers from a TOML description of their routes, so the routing of a gateway can
+//! change without recompiling it.
+//!
+//! Each route maps a method and a path to the name of a handler in a set of
+//! [Handlers](struct.Handlers.html). Paths use the notation of the [path!](../macro.path.html)
+//! macro, with `_` for a dynamic segment, `{name}` for a named one, `_?` for an optional one and
+//! `**` for the rest of the path. Routes can also have a `name` for
+//! [url_for](../struct.Router.html#method.url_for), a `description` and `tags`, which are
+//! registered as their [metadata](../struct.RouteMeta.html).
+//!
+//! ```
+//! # use grout::{config::{Handlers, RouteConfig}, hyper::{Body, Method}, test::TestClient, Request, Response, ResponseBuilder, Router};
+//! async fn list_users(_params: Vec<String>, _req: Request) -> Response {
+//! 	Ok(ResponseBuilder::default().body(Body::from("users"))?)
+//! }
+//!
+//! async fn show_user(params: Vec<String>, _req: Request) -> Response {
+//! 	Ok(ResponseBuilder::default().body(Body::from(params[0].clone()))?)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let handlers = Handlers::default()
+//! 	.add("list_users", list_users)
+//! 	.add("show_user", show_user);
+//!
+//! let config = RouteConfig::from_toml(
+//! 	r#"
+//! 	[[routes]]
+//! 	method = "GET"
+//! 	path = "/users"
+//! 	handler = "list_users"
+//!
+//! 	[[routes]]
+//! 	method = "GET"
+//! 	path = "/users/{id}"
+//! 	handler = "show_user"
+//! 	name = "user_detail"
+//! 	tags = ["users"]
+//! 	"#,
+//! )?;
+//!
+//! let router = Router::<Method, _, _>::default().configure(&config, &handlers)?.build()?;
+//! assert_eq!(router.url_for("user_detail", &["42"])?, "/users/42");
+//!
+//! let client = TestClient::new(router);
+//! assert_eq!(client.get("/users/42").await?.body(), "42");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{DynRoute, Params, Path, PathSegment, RouteMeta, Router, SharedRoute};
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	error::Error,
+	fmt::{self, Debug, Display, Formatter},
+	future::Future,
+	hash::Hash,
+	str::FromStr,
+	sync::Arc,
+};
+use toml_edit::{Document, Item, Table};
+
+/// The route handlers configured routes can use, by name.
+pub struct Handlers<Req, Res> {
+	handlers: HashMap<String, SharedRoute<Req, Res>>,
+}
+
+impl<Req, Res> Default for Handlers<Req, Res> {
+	fn default() -> Self {
+		Self {
+			handlers: HashMap::new(),
+		}
+	}
+}
+
+impl<Req: 'static, Res: 'static> Handlers<Req, Res> {
+	/// Add a route handler under a name, replacing any handler added under it before.
+	pub fn add<F, T>(self, name: impl Into<String>, route: F) -> Self
+	where
+		F: Fn(Vec<String>, Req) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Res> + Send,
+	{
+		self.add_boxed(name, crate::boxed(route))
+	}
+
+	/// Add an already boxed route handler under a name, like [add](#method.add).
+	pub fn add_boxed(mut self, name: impl Into<String>, route: DynRoute<Req, Res>) -> Self {
+		self.handlers.insert(name.into(), Arc::new(route));
+		self
+	}
+}
+
+impl<Req, Res> Debug for Handlers<Req, Res> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let mut names: Vec<_> = self.handlers.keys().collect();
+		names.sort();
+		f.debug_struct("Handlers").field("names", &names).finish()
+	}
+}
+
+/// The routes described by a configuration file, registered with
+/// [configure](../struct.Router.html#method.configure).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RouteConfig {
+	pub routes: Vec<RouteEntry>,
+}
+
+/// A route of a [RouteConfig](struct.RouteConfig.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RouteEntry {
+	/// The prefix of the route, parsed with `FromStr`, such as the method for HTTP routers.
+	pub method: String,
+	pub path: String,
+	/// The name of the handler in the [Handlers](struct.Handlers.html) of the router.
+	pub handler: String,
+	pub name: Option<String>,
+	pub description: Option<String>,
+	pub tags: Vec<String>,
+}
+
+impl RouteConfig {
+	/// Read the routes from a TOML document with a `routes` array of tables, whose tables have
+	/// `method`, `path` and `handler` strings, and optionally `name` and `description` strings
+	/// and an array of `tags`.
+	pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+		let document: Document<String> = text
+			.parse()
+			.map_err(|e: toml_edit::TomlError| ConfigError::Syntax(e.message().to_owned()))?;
+		let routes = match document.get("routes") {
+			None => return Ok(Self::default()),
+			Some(routes) => routes.as_array_of_tables().ok_or(ConfigError::Field {
+				route: None,
+				field: "routes",
+			})?,
+		};
+
+		let routes = routes
+			.iter()
+			.enumerate()
+			.map(|(index, table)| RouteEntry::from_table(index, table))
+			.collect::<Result<_, _>>()?;
+		Ok(Self { routes })
+	}
+}
+
+impl RouteEntry {
+	fn from_table(index: usize, table: &Table) -> Result<Self, ConfigError> {
+		let invalid = |field| ConfigError::Field {
+			route: Some(index),
+			field,
+		};
+		let string = |field| match table.get(field) {
+			None => Ok(None),
+			Some(item) => item
+				.as_str()
+				.map(|value| Some(value.to_owned()))
+				.ok_or_else(|| invalid(field)),
+		};
+		let required = |field| string(field)?.ok_or_else(|| invalid(field));
+		let tags = match table.get("tags").map(Item::as_array) {
+			None => vec![],
+			Some(None) => return Err(invalid("tags")),
+			Some(Some(tags)) => tags
+				.iter()
+				.map(|tag| tag.as_str().map(str::to_owned))
+				.collect::<Option<_>>()
+				.ok_or_else(|| invalid("tags"))?,
+		};
+
+		Ok(Self {
+			method: required("method")?,
+			path: required("path")?,
+			handler: required("handler")?,
+			name: string("name")?,
+			description: string("description")?,
+			tags,
+		})
+	}
+
+	fn meta(&self) -> RouteMeta {
+		RouteMeta {
+			name: self.name.clone(),
+			description: self.description.clone(),
+			tags: self.tags.clone(),
+			..Default::default()
+		}
+	}
+}
+
+/// A configuration that couldn't be read or registered. Routes are numbered from zero in the
+/// order they are listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+	/// The configuration isn't valid TOML.
+	Syntax(String),
+	/// A field is missing or has the wrong type, in a route or at the top level.
+	Field {
+		route: Option<usize>,
+		field: &'static str,
+	},
+	/// The method of a route couldn't be parsed as a prefix.
+	Method { route: usize, method: String },
+	/// The path of a route isn't in the notation of the `path!` macro.
+	Path { route: usize, path: String },
+	/// A route uses a handler that isn't in the handlers of the router.
+	UnknownHandler { route: usize, handler: String },
+}
+
+impl Display for ConfigError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Syntax(message) => write!(f, "invalid TOML: {}", message),
+			Self::Field {
+				route: Some(route),
+				field,
+			} => write!(f, "route {} has a missing or invalid {:?}", route, field),
+			Self::Field { route: None, field } => write!(f, "missing or invalid {:?}", field),
+			Self::Method { route, method } => {
+				write!(f, "route {} has an invalid method {:?}", route, method)
+			}
+			Self::Path { route, path } => {
+				write!(f, "route {} has an invalid path {:?}", route, path)
+			}
+			Self::UnknownHandler { route, handler } => {
+				write!(f, "route {} uses an unknown handler {:?}", route, handler)
+			}
+		}
+	}
+}
+
+impl Error for ConfigError {}
+
+impl<'a, Prefix, Req, Res> Router<'a, Prefix, Req, Res>
+where
+	Req: 'static,
+	Res: 'static,
+	Prefix: Eq + Hash + FromStr,
+{
+	/// Register the routes of a [configuration](config/index.html), with handlers looked up by
+	/// name. The router owns the paths it reads, so the configuration can be dropped once the
+	/// routes are registered. Problems with the routes themselves, such as duplicates, are
+	/// reported by [validate](#method.validate) as usual.
+	pub fn configure(
+		mut self,
+		config: &RouteConfig,
+		handlers: &Handlers<Req, Res>,
+	) -> Result<Self, ConfigError> {
+		for (index, entry) in config.routes.iter().enumerate() {
+			let prefix = entry.method.parse().map_err(|_| ConfigError::Method {
+				route: index,
+				method: entry.method.clone(),
+			})?;
+			let path = parse_path(&entry.path).ok_or_else(|| ConfigError::Path {
+				route: index,
+				path: entry.path.clone(),
+			})?;
+			let route = handlers.handlers.get(&entry.handler).ok_or_else(|| {
+				ConfigError::UnknownHandler {
+					route: index,
+					handler: entry.handler.clone(),
+				}
+			})?;
+
+			let route = Arc::clone(route);
+			self = self.register_with(prefix, path, entry.meta(), move |params, req| {
+				route(Params::from(params), req)
+			});
+		}
+		Ok(self)
+	}
+}
+
+/// Parse a path in the notation of the [path!](../macro.path.html) macro, such as `/users/{id}`,
+/// into owned segments. Constrained segments can't be written outside of code, so they are
+/// rejected.
+fn parse_path(path: &str) -> Option<Path<'static>> {
+	let path = path.strip_prefix('/').unwrap_or(path);
+	if path.is_empty() {
+		return Some(vec![]);
+	}
+
+	path.split('/')
+		.map(|segment| match segment {
+			"" => None,
+			"_" => Some(PathSegment::Dynamic),
+			"_?" => Some(PathSegment::Optional),
+			"**" => Some(PathSegment::CatchAll),
+			segment => match segment.strip_prefix('{') {
+				Some(name) => name
+					.strip_suffix('}')
+					.filter(|name| !name.is_empty() && !name.contains(['{', '}']))
+					.map(|name| PathSegment::Named(Cow::Owned(name.to_owned()))),
+				None => Some(PathSegment::Static(Cow::Owned(segment.to_owned())))
+					.filter(|_| !segment.contains(['{', '}', '(', ')'])),
+			},
+		})
+		.collect()
+}
@@ -0,0 +1,147 @@
+//! Reading request bodies into typed values.
+
+use crate::{Error, Request};
+use hyper::{header::CONTENT_TYPE, StatusCode};
+#[cfg(any(feature = "form", feature = "json"))]
+use {
+	super::limit::limit_body, crate::PayloadTooLarge, hyper::body::Bytes,
+	serde::de::DeserializeOwned,
+};
+
+#[cfg(feature = "multipart")]
+use super::multipart::disposition_param;
+#[cfg(feature = "multipart")]
+pub use super::multipart::{Field, Multipart};
+#[cfg(feature = "form")]
+use super::urlencoded;
+
+/// The default maximum size of the bodies read whole by the extractors in this module.
+pub const DEFAULT_LIMIT: u64 = 1024 * 1024;
+
+/// Read a JSON request body into a value, with a size limit of
+/// [DEFAULT_LIMIT](constant.DEFAULT_LIMIT.html).
+///
+/// Requests without a JSON `Content-Type` fail with `415 Unsupported Media Type`, bodies over the
+/// limit fail with `413 Payload Too Large` and bodies that don't deserialize fail with
+/// `400 Bad Request`.
+///
+/// ```
+/// # use grout::{body, hyper::{Body, StatusCode}, Json, Request, Response};
+/// #[derive(serde::Deserialize, serde::Serialize)]
+/// struct User {
+/// 	name: String,
+/// }
+///
+/// async fn create_user(_params: Vec<String>, req: Request) -> Response {
+/// 	let user: User = body::json(req).await?;
+/// 	Json(user).into_response()
+/// }
+/// ```
+#[cfg(feature = "json")]
+pub async fn json<T: DeserializeOwned>(req: Request) -> Result<T, Error> {
+	json_with_limit(req, DEFAULT_LIMIT).await
+}
+
+/// Read a JSON request body into a value, like [json](fn.json.html) with a different size limit.
+#[cfg(feature = "json")]
+pub async fn json_with_limit<T: DeserializeOwned>(req: Request, limit: u64) -> Result<T, Error> {
+	require_content_type(&req, |mime| {
+		mime == "application/json" || mime.ends_with("+json")
+	})?;
+	let body = read(req, limit).await?;
+	serde_json::from_slice(&body).map_err(|e| Error::new(StatusCode::BAD_REQUEST, e))
+}
+
+/// Read an `application/x-www-form-urlencoded` request body, as sent by HTML forms, into a value,
+/// with a size limit of [DEFAULT_LIMIT](constant.DEFAULT_LIMIT.html).
+///
+/// Values are deserialized from their text, so fields can be strings, numbers, booleans, unit
+/// enum variants and options of those, and fields given more than once deserialize into
+/// sequences. Requests without a form
+/// `Content-Type` fail with `415 Unsupported Media Type`, bodies over the limit fail with
+/// `413 Payload Too Large` and bodies that don't deserialize fail with `400 Bad Request`.
+///
+/// ```
+/// # use grout::{body, hyper::Body, Request, Response, ResponseBuilder};
+/// #[derive(serde::Deserialize)]
+/// struct Login {
+/// 	username: String,
+/// 	remember: Option<bool>,
+/// }
+///
+/// async fn login(_params: Vec<String>, req: Request) -> Response {
+/// 	let login: Login = body::form(req).await?;
+/// 	Ok(ResponseBuilder::default().body(Body::from(login.username))?)
+/// }
+/// ```
+#[cfg(feature = "form")]
+pub async fn form<T: DeserializeOwned>(req: Request) -> Result<T, Error> {
+	form_with_limit(req, DEFAULT_LIMIT).await
+}
+
+/// Read a form request body into a value, like [form](fn.form.html) with a different size limit.
+#[cfg(feature = "form")]
+pub async fn form_with_limit<T: DeserializeOwned>(req: Request, limit: u64) -> Result<T, Error> {
+	require_content_type(&req, |mime| mime == "application/x-www-form-urlencoded")?;
+	let body = read(req, limit).await?;
+	urlencoded::from_bytes(&body).map_err(|e| Error::new(StatusCode::BAD_REQUEST, e))
+}
+
+/// Read a `multipart/form-data` request body, as sent by HTML forms with file inputs, field by
+/// field as it arrives. Requests without a multipart `Content-Type` fail with
+/// `415 Unsupported Media Type`, and malformed bodies fail with `400 Bad Request` once the
+/// malformed part is reached.
+///
+/// ```
+/// # use grout::{body, hyper::Body, Request, Response, ResponseBuilder};
+/// async fn upload(_params: Vec<String>, req: Request) -> Response {
+/// 	let mut multipart = body::multipart(req)?.field_limit(10 * 1024 * 1024);
+/// 	let mut uploaded = 0;
+/// 	while let Some(mut field) = multipart.next_field().await? {
+/// 		if field.file_name().is_none() {
+/// 			continue;
+/// 		}
+/// 		while let Some(chunk) = field.chunk().await? {
+/// 			uploaded += chunk.len();
+/// 		}
+/// 	}
+///
+/// 	Ok(ResponseBuilder::default().body(Body::from(format!("{} bytes", uploaded)))?)
+/// }
+/// ```
+#[cfg(feature = "multipart")]
+pub fn multipart(req: Request) -> Result<Multipart, Error> {
+	require_content_type(&req, |mime| mime == "multipart/form-data")?;
+	let boundary = req
+		.headers()
+		.get(CONTENT_TYPE)
+		.and_then(|content_type| content_type.to_str().ok())
+		.and_then(|content_type| disposition_param(content_type, "boundary"))
+		.filter(|boundary| !boundary.is_empty() && boundary.len() <= 70)
+		.ok_or_else(|| Error::new(StatusCode::BAD_REQUEST, "missing multipart boundary"))?;
+	Ok(Multipart::new(req.into_body(), &boundary))
+}
+
+/// Fail with `415 Unsupported Media Type` unless the media type of the request is accepted.
+fn require_content_type(req: &Request, accept: impl Fn(&str) -> bool) -> Result<(), Error> {
+	let mime = req
+		.headers()
+		.get(CONTENT_TYPE)
+		.and_then(|content_type| content_type.to_str().ok())
+		.and_then(|content_type| content_type.split(';').next())
+		.map(|mime| mime.trim().to_ascii_lowercase());
+
+	match mime {
+		Some(mime) if accept(&mime) => Ok(()),
+		_ => Err(Error::new(
+			StatusCode::UNSUPPORTED_MEDIA_TYPE,
+			"unsupported content type",
+		)),
+	}
+}
+
+#[cfg(any(feature = "form", feature = "json"))]
+async fn read(req: Request, limit: u64) -> Result<Bytes, Error> {
+	let req = limit_body(req, limit).ok_or(PayloadTooLarge { limit })?;
+	Ok(hyper::body::to_bytes(req.into_body()).await?)
+}
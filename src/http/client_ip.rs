@@ -0,0 +1,191 @@
+use crate::Request;
+use hyper::header::{HeaderName, FORWARDED};
+use std::{
+	error::Error,
+	fmt::{self, Display, Formatter},
+	net::{IpAddr, SocketAddr},
+	str::FromStr,
+};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// A range of IP addresses in CIDR notation, such as `10.0.0.0/8` or `fd00::/8`, used to list the
+/// [trusted proxies](struct.HttpRouter.html#method.trusted_proxies) of a router. An address
+/// without a prefix length is a range of just that address.
+///
+/// ```
+/// # use grout::Cidr;
+/// let private: Cidr = "10.0.0.0/8".parse().unwrap();
+/// assert!(private.contains("10.1.2.3".parse().unwrap()));
+/// assert!(!private.contains("192.0.2.1".parse().unwrap()));
+/// assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+	addr: IpAddr,
+	prefix: u8,
+}
+
+impl Cidr {
+	pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, InvalidCidr> {
+		let bits = match addr {
+			IpAddr::V4(_) => 32,
+			IpAddr::V6(_) => 128,
+		};
+		if prefix > bits {
+			return Err(InvalidCidr);
+		}
+
+		Ok(Self { addr, prefix })
+	}
+
+	/// Whether an address is in the range. IPv4 addresses mapped to IPv6, as dual-stack sockets
+	/// report IPv4 peers, are in the IPv4 ranges they map.
+	pub fn contains(&self, addr: IpAddr) -> bool {
+		match (self.addr, canonical(addr)) {
+			(IpAddr::V4(range), IpAddr::V4(addr)) => {
+				prefix_matches(&range.octets(), &addr.octets(), self.prefix)
+			}
+			(IpAddr::V6(range), IpAddr::V6(addr)) => {
+				prefix_matches(&range.octets(), &addr.octets(), self.prefix)
+			}
+			_ => false,
+		}
+	}
+}
+
+impl FromStr for Cidr {
+	type Err = InvalidCidr;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (addr, prefix) = match s.split_once('/') {
+			Some((addr, prefix)) => {
+				let addr = canonical(addr.parse().map_err(|_| InvalidCidr)?);
+				(addr, Some(prefix.parse().map_err(|_| InvalidCidr)?))
+			}
+			None => (canonical(s.parse().map_err(|_| InvalidCidr)?), None),
+		};
+
+		let bits = if addr.is_ipv4() { 32 } else { 128 };
+		Self::new(addr, prefix.unwrap_or(bits))
+	}
+}
+
+impl Display for Cidr {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}/{}", self.addr, self.prefix)
+	}
+}
+
+/// A [Cidr](struct.Cidr.html) that couldn't be parsed or has a prefix length longer than its
+/// address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidCidr;
+
+impl Display for InvalidCidr {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("invalid CIDR range")
+	}
+}
+
+impl Error for InvalidCidr {}
+
+/// The address of the client a request came from, as resolved through
+/// [trusted proxies](struct.HttpRouter.html#method.trusted_proxies). Read it with
+/// [client_ip](trait.RequestExt.html#tymethod.client_ip).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+	/// Resolve the client address of a request from a peer. The forwarding headers are only read
+	/// when the peer is a trusted proxy, and are then walked from the closest hop back, stopping
+	/// at the first address that isn't a trusted proxy itself, so clients can't spoof their
+	/// address by sending the headers. `Forwarded` takes precedence over `X-Forwarded-For`.
+	pub(super) fn resolve(req: &Request, peer: IpAddr, trusted: &[Cidr]) -> Self {
+		let is_trusted = |addr: IpAddr| trusted.iter().any(|range| range.contains(addr));
+		let mut client = canonical(peer);
+		if !is_trusted(client) {
+			return Self(client);
+		}
+
+		let hops = match forwarded_for(req) {
+			Some(hops) => hops,
+			None => list(req, HeaderName::from_static(X_FORWARDED_FOR)),
+		};
+		for hop in hops.iter().rev() {
+			match parse_node(hop) {
+				Some(addr) => client = addr,
+				None => break,
+			}
+			if !is_trusted(client) {
+				break;
+			}
+		}
+		Self(client)
+	}
+}
+
+/// The `for` parameters of the `Forwarded` headers of a request (RFC 7239), or `None` if there
+/// are none.
+fn forwarded_for(req: &Request) -> Option<Vec<String>> {
+	let hops: Vec<String> = list(req, FORWARDED)
+		.iter()
+		.filter_map(|element| {
+			element.split(';').find_map(|pair| {
+				let (key, value) = pair.split_once('=')?;
+				Some(value.trim().to_owned()).filter(|_| key.trim().eq_ignore_ascii_case("for"))
+			})
+		})
+		.collect();
+	Some(hops).filter(|hops| !hops.is_empty())
+}
+
+/// The comma separated elements of every header with a name, in order.
+fn list(req: &Request, name: HeaderName) -> Vec<String> {
+	req.headers()
+		.get_all(name)
+		.iter()
+		.filter_map(|header| header.to_str().ok())
+		.flat_map(|header| header.split(','))
+		.map(|element| element.trim().to_owned())
+		.filter(|element| !element.is_empty())
+		.collect()
+}
+
+/// The address of a node of a forwarding header, such as `192.0.2.60`, `"[2001:db8::17]:4711"`
+/// or `192.0.2.60:8080`. Obfuscated and `unknown` nodes have none.
+fn parse_node(node: &str) -> Option<IpAddr> {
+	let node = node.trim_matches('"');
+	let host = match node.strip_prefix('[') {
+		Some(rest) => &rest[..rest.find(']')?],
+		None => node,
+	};
+	let addr = host
+		.parse()
+		.ok()
+		.or_else(|| Some(host.parse::<SocketAddr>().ok()?.ip()))?;
+	Some(canonical(addr))
+}
+
+fn canonical(addr: IpAddr) -> IpAddr {
+	match addr {
+		IpAddr::V6(v6) => match v6.octets() {
+			[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => IpAddr::from([a, b, c, d]),
+			_ => addr,
+		},
+		addr => addr,
+	}
+}
+
+fn prefix_matches(range: &[u8], addr: &[u8], prefix: u8) -> bool {
+	let whole = usize::from(prefix / 8);
+	let rest = prefix % 8;
+	if range[..whole] != addr[..whole] {
+		return false;
+	}
+
+	rest == 0 || {
+		let mask = 0xff_u8 << (8 - rest);
+		range[whole] & mask == addr[whole] & mask
+	}
+}
@@ -0,0 +1,284 @@
+use super::deflate::Encoder;
+use futures_core::Stream;
+use hyper::{
+	body::{self, Bytes, HttpBody},
+	header::{
+		HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE,
+		CONTENT_TYPE, VARY,
+	},
+	Body, StatusCode,
+};
+use std::{
+	error::Error as StdError,
+	future::Future,
+	pin::Pin,
+	task::{Context, Poll},
+};
+use tokio::task::{spawn_blocking, JoinHandle};
+
+/// The default size below which responses aren't compressed.
+pub const DEFAULT_MIN_COMPRESSION_SIZE: u64 = 1024;
+
+/// The size from which bodies and chunks are compressed on the blocking thread pool instead of
+/// the task serving the response.
+const BLOCKING_SIZE: usize = 64 * 1024;
+
+/// Response compression settings for an HTTP router, attached with
+/// [compression](struct.HttpRouter.html#method.compression).
+///
+/// Responses are compressed with `gzip` or `deflate`, whichever the `Accept-Encoding` header of
+/// the request prefers. Only responses with a textual `Content-Type` (`text/*` except server-sent
+/// events, JSON, JavaScript, XML and WebAssembly) are compressed, unless their length is known to
+/// be below the minimum size. Responses that already have a `Content-Encoding`, and responses
+/// carrying the [Uncompressed](struct.Uncompressed.html) extension, are left alone too. `br`
+/// isn't supported.
+///
+/// Fully buffered bodies are compressed at once, and sent uncompressed if that doesn't make them
+/// smaller. Streamed bodies, such as files, are compressed chunk by chunk as they are sent,
+/// without a `Content-Length`. Compression takes tens of milliseconds per MiB, so bodies and
+/// chunks of 64 KiB or more are compressed on the blocking thread pool rather than holding up the
+/// runtime.
+///
+/// ```
+/// # use grout::Compression;
+/// let compression = Compression::default().min_size(256);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compression {
+	min_size: u64,
+}
+
+impl Default for Compression {
+	fn default() -> Self {
+		Self {
+			min_size: DEFAULT_MIN_COMPRESSION_SIZE,
+		}
+	}
+}
+
+/// A response extension that opts a response out of [compression](struct.Compression.html).
+///
+/// ```
+/// # use grout::{hyper::Body, Request, Response, ResponseBuilder, Uncompressed};
+/// async fn already_small(_params: Vec<String>, _req: Request) -> Response {
+/// 	let mut res = ResponseBuilder::default().body(Body::from("{}"))?;
+/// 	res.extensions_mut().insert(Uncompressed);
+/// 	Ok(res)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Uncompressed;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+	Gzip,
+	Deflate,
+}
+
+impl Encoding {
+	fn name(self) -> &'static str {
+		match self {
+			Self::Gzip => "gzip",
+			Self::Deflate => "deflate",
+		}
+	}
+
+	fn encoder(self) -> Encoder {
+		match self {
+			Self::Gzip => Encoder::gzip(),
+			Self::Deflate => Encoder::zlib(),
+		}
+	}
+}
+
+impl Compression {
+	/// Set the size in bytes below which responses aren't compressed.
+	pub fn min_size(mut self, min_size: u64) -> Self {
+		self.min_size = min_size;
+		self
+	}
+
+	/// Compress a response for a request with the given headers, if it should be.
+	pub(crate) async fn apply(
+		&self,
+		req_headers: &HeaderMap,
+		mut res: hyper::Response<Body>,
+	) -> hyper::Response<Body> {
+		let exact = HttpBody::size_hint(res.body()).exact();
+		let len = exact.or_else(|| {
+			res.headers()
+				.get(CONTENT_LENGTH)
+				.and_then(|len| len.to_str().ok())
+				.and_then(|len| len.parse().ok())
+		});
+		if len.is_some_and(|len| len < self.min_size) || !is_compressible(&res) {
+			return res;
+		}
+
+		res.headers_mut()
+			.append(VARY, HeaderValue::from_static("accept-encoding"));
+		let encoding = match negotiate(req_headers) {
+			Some(encoding) => encoding,
+			None => return res,
+		};
+
+		let (mut parts, body) = res.into_parts();
+		parts
+			.headers
+			.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+		if exact.is_none() {
+			parts.headers.remove(CONTENT_LENGTH);
+			let body = Body::wrap_stream(Compressed {
+				body,
+				encoder: Some(encoding.encoder()),
+				encoding: None,
+			});
+			return hyper::Response::from_parts(parts, body);
+		}
+
+		let data = match body::to_bytes(body).await {
+			Ok(data) => data,
+			Err(_) => return hyper::Response::from_parts(parts, Body::empty()),
+		};
+		let (data, encoded) = if data.len() < BLOCKING_SIZE {
+			let encoded = encoding.encoder().finish(&data);
+			(data, encoded)
+		} else {
+			match spawn_blocking(move || {
+				let encoded = encoding.encoder().finish(&data);
+				(data, encoded)
+			})
+			.await
+			{
+				Ok(encoded) => encoded,
+				Err(_) => return hyper::Response::from_parts(parts, Body::empty()),
+			}
+		};
+		if encoded.len() >= data.len() {
+			parts.headers.remove(CONTENT_ENCODING);
+			return hyper::Response::from_parts(parts, data.into());
+		}
+
+		parts.headers.insert(CONTENT_LENGTH, encoded.len().into());
+		hyper::Response::from_parts(parts, encoded.into())
+	}
+}
+
+/// A streamed response body, compressed chunk by chunk.
+struct Compressed {
+	body: Body,
+	/// The encoder, unless it is compressing a chunk on the blocking thread pool or the stream
+	/// has ended.
+	encoder: Option<Encoder>,
+	encoding: Option<JoinHandle<(Encoder, Vec<u8>)>>,
+}
+
+impl Stream for Compressed {
+	type Item = Result<Bytes, Box<dyn StdError + Send + Sync>>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+		loop {
+			if let Some(encoding) = &mut this.encoding {
+				let (encoder, encoded) = match Pin::new(encoding).poll(cx) {
+					Poll::Ready(Ok(encoded)) => encoded,
+					Poll::Ready(Err(error)) => return Poll::Ready(Some(Err(error.into()))),
+					Poll::Pending => return Poll::Pending,
+				};
+				this.encoding = None;
+				this.encoder = Some(encoder);
+				if !encoded.is_empty() {
+					return Poll::Ready(Some(Ok(encoded.into())));
+				}
+			}
+
+			let mut encoder = match this.encoder.take() {
+				Some(encoder) => encoder,
+				None => return Poll::Ready(None),
+			};
+			match Pin::new(&mut this.body).poll_data(cx) {
+				Poll::Ready(Some(Ok(chunk))) if chunk.len() >= BLOCKING_SIZE => {
+					this.encoding = Some(spawn_blocking(move || {
+						let encoded = encoder.write(&chunk);
+						(encoder, encoded)
+					}));
+				}
+				Poll::Ready(Some(Ok(chunk))) => {
+					let encoded = encoder.write(&chunk);
+					this.encoder = Some(encoder);
+					if !encoded.is_empty() {
+						return Poll::Ready(Some(Ok(encoded.into())));
+					}
+				}
+				Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error.into()))),
+				Poll::Ready(None) => return Poll::Ready(Some(Ok(encoder.finish(&[]).into()))),
+				Poll::Pending => {
+					this.encoder = Some(encoder);
+					return Poll::Pending;
+				}
+			}
+		}
+	}
+}
+
+fn is_compressible(res: &hyper::Response<Body>) -> bool {
+	let status = res.status();
+	if status == StatusCode::NO_CONTENT
+		|| status == StatusCode::NOT_MODIFIED
+		|| status == StatusCode::PARTIAL_CONTENT
+		|| res.extensions().get::<Uncompressed>().is_some()
+		|| res.headers().contains_key(CONTENT_ENCODING)
+		|| res.headers().contains_key(CONTENT_RANGE)
+	{
+		return false;
+	}
+
+	let content_type = match res.headers().get(CONTENT_TYPE).map(HeaderValue::to_str) {
+		Some(Ok(content_type)) => content_type.to_ascii_lowercase(),
+		_ => return false,
+	};
+	let mime = content_type.split(';').next().unwrap_or("").trim();
+	(mime.starts_with("text/") && mime != "text/event-stream")
+		|| mime.ends_with("/json")
+		|| mime.ends_with("+json")
+		|| mime.ends_with("/javascript")
+		|| mime.ends_with("/xml")
+		|| mime.ends_with("+xml")
+		|| mime == "application/wasm"
+}
+
+/// The encoding the client prefers from `Accept-Encoding`, preferring `gzip` between equals.
+fn negotiate(headers: &HeaderMap) -> Option<Encoding> {
+	let mut gzip = None;
+	let mut deflate = None;
+	let mut any = None;
+	let accepted = headers
+		.get_all(ACCEPT_ENCODING)
+		.iter()
+		.filter_map(|accept| accept.to_str().ok())
+		.flat_map(|accept| accept.split(','));
+	for coding in accepted {
+		let mut params = coding.split(';');
+		let name = params.next().unwrap_or("").trim().to_ascii_lowercase();
+		let quality = params
+			.filter_map(|param| param.trim().strip_prefix("q="))
+			.find_map(|quality| quality.trim().parse::<f32>().ok())
+			.unwrap_or(1.0);
+		match name.as_str() {
+			"gzip" | "x-gzip" => gzip = Some(quality),
+			"deflate" => deflate = Some(quality),
+			"*" => any = Some(quality),
+			_ => {}
+		}
+	}
+
+	let gzip = gzip.or(any).unwrap_or(0.0);
+	let deflate = deflate.or(any).unwrap_or(0.0);
+	if gzip > 0.0 && gzip >= deflate {
+		Some(Encoding::Gzip)
+	} else if deflate > 0.0 {
+		Some(Encoding::Deflate)
+	} else {
+		None
+	}
+}
@@ -0,0 +1,270 @@
+//! Reading request cookies and building `Set-Cookie` headers.
+//!
+//! ```
+//! # use grout::{cookies::{Cookie, SameSite}, hyper::{header::SET_COOKIE, Body}, Request, RequestExt, Response, ResponseBuilder};
+//! # use std::time::Duration;
+//! async fn greet(_params: Vec<String>, req: Request) -> Response {
+//! 	let visits: u32 = req.cookies().get("visits").and_then(|v| v.parse().ok()).unwrap_or(0);
+//! 	let cookie = Cookie::new("visits", (visits + 1).to_string())
+//! 		.path("/")
+//! 		.max_age(Duration::from_secs(60 * 60 * 24 * 365))
+//! 		.same_site(SameSite::Lax)
+//! 		.http_only(true);
+//!
+//! 	Ok(ResponseBuilder::default()
+//! 		.header(SET_COOKIE, cookie.header_value()?)
+//! 		.body(Body::from(format!("visit number {}", visits + 1)))?)
+//! }
+//! ```
+
+use httpdate::fmt_http_date;
+use hyper::header::{HeaderMap, HeaderValue, COOKIE};
+use std::{
+	error::Error as StdError,
+	fmt::{self, Display, Formatter},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The cookies sent with a request, read with
+/// [cookies](../trait.RequestExt.html#tymethod.cookies).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieJar {
+	cookies: Vec<(String, String)>,
+}
+
+impl CookieJar {
+	/// Parse the cookies from the `Cookie` headers of a request. Malformed cookies are skipped.
+	///
+	/// ```
+	/// # use grout::{cookies::CookieJar, hyper::{header::COOKIE, HeaderMap}};
+	/// let mut headers = HeaderMap::new();
+	/// headers.insert(COOKIE, "theme=dark; session=\"abc123\"; broken".parse().unwrap());
+	///
+	/// let jar = CookieJar::from_headers(&headers);
+	/// assert_eq!(jar.get("theme"), Some("dark"));
+	/// assert_eq!(jar.get("session"), Some("abc123"));
+	/// assert_eq!(jar.iter().count(), 2);
+	/// ```
+	pub fn from_headers(headers: &HeaderMap) -> Self {
+		let cookies = headers
+			.get_all(COOKIE)
+			.iter()
+			.filter_map(|header| header.to_str().ok())
+			.flat_map(|header| header.split(';'))
+			.filter_map(|cookie| {
+				let (name, value) = cookie.split_once('=')?;
+				let name = name.trim();
+				let value = value.trim();
+				let value = value
+					.strip_prefix('"')
+					.and_then(|value| value.strip_suffix('"'))
+					.unwrap_or(value);
+				Some((name.to_owned(), value.to_owned())).filter(|_| !name.is_empty())
+			})
+			.collect();
+
+		Self { cookies }
+	}
+
+	/// The value of a cookie. If a cookie was sent more than once, which happens when cookies with
+	/// the same name are set for different paths, this is the first one, which the browser sends
+	/// for the most specific path.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.cookies
+			.iter()
+			.find(|(cookie, _)| cookie == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// Every cookie, by name and value.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.cookies
+			.iter()
+			.map(|(name, value)| (name.as_str(), value.as_str()))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.cookies.is_empty()
+	}
+}
+
+/// The `SameSite` attribute of a cookie, which controls whether it is sent with cross-site
+/// requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+	Strict,
+	Lax,
+	/// Sent with every request, which browsers only accept for `Secure` cookies.
+	None,
+}
+
+impl Display for SameSite {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Strict => "Strict",
+			Self::Lax => "Lax",
+			Self::None => "None",
+		})
+	}
+}
+
+/// A cookie to set with a `Set-Cookie` header. Its `Display` implementation writes the header
+/// value, and [header_value](#method.header_value) checks it can be sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+	name: String,
+	value: String,
+	path: Option<String>,
+	domain: Option<String>,
+	max_age: Option<Duration>,
+	expires: Option<SystemTime>,
+	same_site: Option<SameSite>,
+	secure: bool,
+	http_only: bool,
+}
+
+impl Cookie {
+	pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			value: value.into(),
+			path: None,
+			domain: None,
+			max_age: None,
+			expires: None,
+			same_site: None,
+			secure: false,
+			http_only: false,
+		}
+	}
+
+	/// A cookie that removes the cookie with the same name, path and domain from the browser.
+	///
+	/// ```
+	/// # use grout::cookies::Cookie;
+	/// let removal = Cookie::removal("session").path("/");
+	/// assert_eq!(
+	/// 	removal.to_string(),
+	/// 	"session=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+	/// );
+	/// ```
+	pub fn removal(name: impl Into<String>) -> Self {
+		Self::new(name, "")
+			.max_age(Duration::from_secs(0))
+			.expires(UNIX_EPOCH)
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn value(&self) -> &str {
+		&self.value
+	}
+
+	/// Only send the cookie for paths under this one.
+	pub fn path(mut self, path: impl Into<String>) -> Self {
+		self.path = Some(path.into());
+		self
+	}
+
+	/// Send the cookie to this domain and its subdomains, instead of only the host that set it.
+	pub fn domain(mut self, domain: impl Into<String>) -> Self {
+		self.domain = Some(domain.into());
+		self
+	}
+
+	/// Keep the cookie for this long. Without this or [expires](#method.expires), the cookie is
+	/// removed when the browser closes.
+	pub fn max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = Some(max_age);
+		self
+	}
+
+	/// Keep the cookie until this time. [max_age](#method.max_age) takes precedence in browsers
+	/// that support it.
+	pub fn expires(mut self, expires: SystemTime) -> Self {
+		self.expires = Some(expires);
+		self
+	}
+
+	pub fn same_site(mut self, same_site: SameSite) -> Self {
+		self.same_site = Some(same_site);
+		self
+	}
+
+	/// Only send the cookie over HTTPS.
+	pub fn secure(mut self, secure: bool) -> Self {
+		self.secure = secure;
+		self
+	}
+
+	/// Hide the cookie from scripts.
+	pub fn http_only(mut self, http_only: bool) -> Self {
+		self.http_only = http_only;
+		self
+	}
+
+	/// The value of a `Set-Cookie` header for the cookie. This fails if the name isn't a token or
+	/// the value, path or domain have characters that can't appear in a cookie.
+	pub fn header_value(&self) -> Result<HeaderValue, InvalidCookie> {
+		let is_token = |c: char| c.is_ascii_graphic() && !"()<>@,;:\\\"/[]?={}".contains(c);
+		let is_octet = |c: char| c.is_ascii_graphic() && !"\",;\\".contains(c);
+		let is_attribute = |value: &Option<String>| {
+			value.iter().all(|value| {
+				value.chars().all(|c| c.is_ascii_graphic() || c == ' ') && !value.contains(';')
+			})
+		};
+
+		if self.name.is_empty()
+			|| !self.name.chars().all(is_token)
+			|| !self.value.chars().all(is_octet)
+			|| !is_attribute(&self.path)
+			|| !is_attribute(&self.domain)
+		{
+			return Err(InvalidCookie);
+		}
+
+		HeaderValue::from_str(&self.to_string()).map_err(|_| InvalidCookie)
+	}
+}
+
+impl Display for Cookie {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}={}", self.name, self.value)?;
+		if let Some(path) = &self.path {
+			write!(f, "; Path={}", path)?;
+		}
+		if let Some(domain) = &self.domain {
+			write!(f, "; Domain={}", domain)?;
+		}
+		if let Some(max_age) = self.max_age {
+			write!(f, "; Max-Age={}", max_age.as_secs())?;
+		}
+		if let Some(expires) = self.expires {
+			write!(f, "; Expires={}", fmt_http_date(expires))?;
+		}
+		if let Some(same_site) = self.same_site {
+			write!(f, "; SameSite={}", same_site)?;
+		}
+		if self.secure {
+			f.write_str("; Secure")?;
+		}
+		if self.http_only {
+			f.write_str("; HttpOnly")?;
+		}
+		Ok(())
+	}
+}
+
+/// A [Cookie](struct.Cookie.html) that can't be sent in a header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidCookie;
+
+impl Display for InvalidCookie {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("invalid cookie")
+	}
+}
+
+impl StdError for InvalidCookie {}
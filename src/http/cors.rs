@@ -0,0 +1,207 @@
+use crate::Request;
+use hyper::{
+	header::{
+		HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+		ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS,
+		ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD,
+		ORIGIN, VARY,
+	},
+	http::response::Builder,
+	Body, Method, StatusCode,
+};
+use std::time::Duration;
+
+/// Cross-origin resource sharing settings for an HTTP router, attached with
+/// [cors](struct.HttpRouter.html#method.cors).
+///
+/// Preflight requests from allowed origins are answered before routing, and the responses to
+/// other requests from allowed origins get the `Access-Control-*` headers. Requests from other
+/// origins are routed as usual without any of the headers, so browsers reject them. No origin is
+/// allowed by default. Unless every origin is allowed, every response gets `Vary: origin`, since
+/// the headers depend on the origin, so caches don't serve one origin's response to another.
+///
+/// ```
+/// # use grout::{hyper::Method, Cors};
+/// # use std::time::Duration;
+/// let cors = Cors::default()
+/// 	.allow_origin("https://example.com")
+/// 	.allow_methods(vec![Method::GET, Method::POST])
+/// 	.allow_headers(vec!["content-type"])
+/// 	.max_age(Duration::from_secs(600));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Cors {
+	any_origin: bool,
+	origins: Vec<String>,
+	methods: Option<Vec<Method>>,
+	headers: Option<Vec<String>>,
+	expose_headers: Vec<String>,
+	max_age: Option<Duration>,
+	credentials: bool,
+}
+
+impl Cors {
+	/// Allow requests from an origin, such as `https://example.com`.
+	pub fn allow_origin(mut self, origin: &str) -> Self {
+		self.origins.push(origin.to_ascii_lowercase());
+		self
+	}
+
+	/// Allow requests from every origin, which are answered with `Access-Control-Allow-Origin: *`.
+	/// This can't be combined with [credentials](#method.allow_credentials).
+	pub fn allow_any_origin(mut self) -> Self {
+		self.any_origin = true;
+		self
+	}
+
+	/// Set the methods preflight requests may ask for. By default, any requested method is
+	/// allowed.
+	pub fn allow_methods(mut self, methods: Vec<Method>) -> Self {
+		self.methods = Some(methods);
+		self
+	}
+
+	/// Set the request headers preflight requests may ask for. By default, any requested headers
+	/// are allowed.
+	pub fn allow_headers(mut self, headers: Vec<&str>) -> Self {
+		self.headers = Some(headers.into_iter().map(str::to_ascii_lowercase).collect());
+		self
+	}
+
+	/// Set the response headers scripts may read besides the safelisted ones.
+	pub fn expose_headers(mut self, headers: Vec<&str>) -> Self {
+		self.expose_headers = headers.into_iter().map(str::to_owned).collect();
+		self
+	}
+
+	/// Set how long browsers may cache the result of a preflight request.
+	pub fn max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = Some(max_age);
+		self
+	}
+
+	/// Allow requests with credentials such as cookies from the allowed origins. Allowing them from
+	/// [any origin](#method.allow_any_origin) would let every site make requests on behalf of the
+	/// users of this one, so [cors](struct.HttpRouter.html#method.cors) rejects that combination.
+	pub fn allow_credentials(mut self, credentials: bool) -> Self {
+		self.credentials = credentials;
+		self
+	}
+
+	/// Whether credentials are allowed from every origin, which is rejected.
+	pub(crate) fn allows_credentials_from_any_origin(&self) -> bool {
+		self.any_origin && self.credentials
+	}
+
+	fn allows_origin(&self, origin: &HeaderValue) -> bool {
+		self.any_origin
+			|| origin.to_str().is_ok_and(|origin| {
+				self.origins
+					.iter()
+					.any(|allowed| allowed.eq_ignore_ascii_case(origin))
+			})
+	}
+
+	/// The origin of a request if it is allowed.
+	pub(crate) fn allowed_origin(&self, req: &Request) -> Option<HeaderValue> {
+		req.headers()
+			.get(ORIGIN)
+			.filter(|origin| self.allows_origin(origin))
+			.cloned()
+	}
+
+	/// Answer a preflight request, if it is one from an allowed origin asking for allowed methods
+	/// and headers.
+	pub(crate) fn preflight(&self, req: &Request) -> Option<hyper::Response<Body>> {
+		if req.method() != Method::OPTIONS {
+			return None;
+		}
+
+		let origin = self.allowed_origin(req)?;
+		let headers = req.headers();
+		let method = headers.get(ACCESS_CONTROL_REQUEST_METHOD)?;
+		if let Some(methods) = &self.methods {
+			if !methods.iter().any(|allowed| allowed.as_str() == method) {
+				return None;
+			}
+		}
+
+		let requested = headers.get(ACCESS_CONTROL_REQUEST_HEADERS);
+		if let (Some(allowed), Some(requested)) = (&self.headers, requested) {
+			let all_allowed = requested
+				.to_str()
+				.ok()?
+				.split(',')
+				.map(str::trim)
+				.filter(|header| !header.is_empty())
+				.all(|header| allowed.iter().any(|h| h.eq_ignore_ascii_case(header)));
+			if !all_allowed {
+				return None;
+			}
+		}
+
+		let mut builder = Builder::default().status(StatusCode::NO_CONTENT);
+		let response_headers = builder.headers_mut()?;
+		self.insert_origin(response_headers, origin);
+		response_headers.insert(
+			ACCESS_CONTROL_ALLOW_METHODS,
+			match &self.methods {
+				Some(methods) => join(methods.iter().map(Method::as_str)),
+				None => method.clone(),
+			},
+		);
+		let allow_headers = match &self.headers {
+			Some(headers) => Some(join(headers.iter().map(String::as_str))),
+			None => requested.cloned(),
+		};
+		if let Some(allow_headers) = allow_headers {
+			response_headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allow_headers);
+		}
+		if let Some(max_age) = self.max_age {
+			response_headers.insert(ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+		}
+
+		builder.body(Body::empty()).ok()
+	}
+
+	/// Add the headers for an actual request to its response: `Vary: origin` unless every origin
+	/// is allowed, and the `Access-Control-*` headers if the request is from an allowed origin.
+	/// Preflight responses only get `Vary: origin`, since they already have the rest.
+	pub(crate) fn apply(&self, origin: Option<HeaderValue>, res: &mut hyper::Response<Body>) {
+		let headers = res.headers_mut();
+		if !self.any_origin {
+			headers.append(VARY, HeaderValue::from_static("origin"));
+		}
+		let origin = match origin {
+			Some(origin) => origin,
+			None => return,
+		};
+		self.insert_origin(headers, origin);
+		if !self.expose_headers.is_empty() {
+			headers.insert(
+				ACCESS_CONTROL_EXPOSE_HEADERS,
+				join(self.expose_headers.iter().map(String::as_str)),
+			);
+		}
+	}
+
+	fn insert_origin(&self, headers: &mut HeaderMap, origin: HeaderValue) {
+		if self.any_origin {
+			headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+		} else {
+			headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+		}
+
+		if self.credentials {
+			headers.insert(
+				ACCESS_CONTROL_ALLOW_CREDENTIALS,
+				HeaderValue::from_static("true"),
+			);
+		}
+	}
+}
+
+fn join<'a>(values: impl Iterator<Item = &'a str>) -> HeaderValue {
+	let joined = values.collect::<Vec<_>>().join(", ");
+	HeaderValue::from_str(&joined).unwrap_or_else(|_| HeaderValue::from_static(""))
+}
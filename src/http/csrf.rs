@@ -0,0 +1,263 @@
+//! Protection against cross-site request forgery.
+//!
+//! The [Csrf](struct.Csrf.html) middleware gives every client a random token and rejects
+//! requests with unsafe methods, such as `POST` and `DELETE`, with `403 Forbidden` unless they
+//! send the token back. Other sites can make a browser send requests but can't read the token,
+//! so their requests are rejected.
+//!
+//! The token is sent back in a header, for scripts, or in a form field of
+//! `application/x-www-form-urlencoded` bodies, for plain HTML forms. Handlers read it with
+//! [csrf_token](../trait.RequestExt.html#tymethod.csrf_token) to put it into pages.
+//!
+//! ```
+//! # use grout::{csrf::Csrf, hyper::{header::{COOKIE, SET_COOKIE}, service::Service, Body, Method, StatusCode}, path, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+//! async fn form(_params: Vec<String>, req: Request) -> Response {
+//! 	let token = req.csrf_token().unwrap();
+//! 	let page = format!(
+//! 		r#"<form method="post"><input type="hidden" name="csrf_token" value="{}"><button>Go</button></form>"#,
+//! 		token,
+//! 	);
+//! 	Ok(ResponseBuilder::default().body(Body::from(page))?)
+//! }
+//! # async fn submit(_params: Vec<String>, _req: Request) -> Response {
+//! # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+//! # }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let mut router = Router::default()
+//! 	.register(Method::GET, path![], form)
+//! 	.register(Method::POST, path![], submit)
+//! 	.build()?
+//! 	.middleware(Csrf::default().middleware());
+//!
+//! let mut service = router.call(()).await?;
+//! let res = service.call(Request::new(Body::empty())).await?;
+//! let cookie = res.headers()[SET_COOKIE].to_str()?.split(';').next().unwrap().to_owned();
+//! let token = cookie.trim_start_matches("csrf_token=").to_owned();
+//!
+//! let forged = hyper::Request::post("/").header(COOKIE, &cookie).body(Body::empty())?;
+//! assert_eq!(service.call(forged).await?.status(), StatusCode::FORBIDDEN);
+//!
+//! let req = hyper::Request::post("/")
+//! 	.header(COOKIE, &cookie)
+//! 	.header("x-csrf-token", token)
+//! 	.body(Body::empty())?;
+//! assert_eq!(service.call(req).await?.status(), StatusCode::OK);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+	cookies::{Cookie, CookieJar, SameSite},
+	limit, query, token, Next, ResponseFuture,
+};
+use crate::{Error, Request};
+use hyper::{
+	body::to_bytes,
+	header::{HeaderName, CONTENT_TYPE, SET_COOKIE},
+	Body, Method, StatusCode,
+};
+use ring::constant_time::verify_slices_are_equal;
+use std::sync::Arc;
+
+/// The default name of the token cookie and form field.
+pub const DEFAULT_FIELD_NAME: &str = "csrf_token";
+
+/// The default name of the token header.
+pub const DEFAULT_HEADER_NAME: &str = "x-csrf-token";
+
+/// The largest form body read to find the token, in bytes. Larger forms must send the token in
+/// the header instead.
+pub const MAX_FORM_SIZE: u64 = 1024 * 1024;
+
+/// The session key the token is kept under with [session](struct.Csrf.html#method.session).
+#[cfg(feature = "session")]
+pub const SESSION_KEY: &str = "csrf_token";
+
+/// The CSRF token of a request, inserted into its extensions by the middleware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Storage {
+	Cookie,
+	#[cfg(feature = "session")]
+	Session,
+}
+
+/// CSRF middleware settings, turned into middleware with [middleware](#method.middleware).
+///
+/// By default the token is kept in a cookie and compared with the one sent back, known as a
+/// double-submit cookie. The cookie is `SameSite=Lax` with a `Path` of `/`, and isn't
+/// `HttpOnly`, so scripts can read it to set the header. With the `session` feature, the token
+/// can be kept in the [session](#method.session) instead.
+#[derive(Debug, Clone)]
+pub struct Csrf {
+	storage: Storage,
+	field_name: String,
+	header_name: HeaderName,
+	secure: bool,
+}
+
+impl Default for Csrf {
+	fn default() -> Self {
+		Self {
+			storage: Storage::Cookie,
+			field_name: DEFAULT_FIELD_NAME.to_owned(),
+			header_name: HeaderName::from_static(DEFAULT_HEADER_NAME),
+			secure: false,
+		}
+	}
+}
+
+impl Csrf {
+	/// Keep the token in the [session](../session/index.html) rather than a cookie, known as a
+	/// synchronizer token. The session middleware must be registered before this middleware.
+	#[cfg(feature = "session")]
+	pub fn session(mut self) -> Self {
+		self.storage = Storage::Session;
+		self
+	}
+
+	/// Set the name of the form field and cookie the token is read from.
+	pub fn field_name(mut self, name: impl Into<String>) -> Self {
+		self.field_name = name.into();
+		self
+	}
+
+	pub fn header_name(mut self, name: HeaderName) -> Self {
+		self.header_name = name;
+		self
+	}
+
+	/// Set whether the token cookie is only sent over HTTPS.
+	pub fn secure(mut self, secure: bool) -> Self {
+		self.secure = secure;
+		self
+	}
+
+	/// The middleware, to register with [middleware](../struct.HttpRouter.html#method.middleware).
+	pub fn middleware(
+		self,
+	) -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static {
+		let csrf = Arc::new(self);
+		move |req, next| {
+			let csrf = Arc::clone(&csrf);
+			Box::pin(async move { csrf.handle(req, next).await })
+		}
+	}
+
+	async fn handle(&self, req: Request, next: Next) -> crate::Response {
+		let (token, issued) = match self.stored_token(&req)? {
+			Some(token) => (token, false),
+			None => (token::random()?, true),
+		};
+
+		let mut req = if is_safe(req.method()) {
+			req
+		} else {
+			self.check(req, &token).await?
+		};
+
+		#[cfg(feature = "session")]
+		if let (Storage::Session, true) = (self.storage, issued) {
+			if let Some(session) = req.extensions().get::<super::session::Session>() {
+				session.insert(SESSION_KEY, token.as_str());
+			}
+		}
+
+		req.extensions_mut().insert(CsrfToken(token.clone()));
+		let mut response = next.run(req).await?;
+		if issued && self.storage == Storage::Cookie {
+			let cookie = Cookie::new(self.field_name.as_str(), token)
+				.path("/")
+				.same_site(SameSite::Lax)
+				.secure(self.secure);
+			response
+				.headers_mut()
+				.append(SET_COOKIE, cookie.header_value()?);
+		}
+		Ok(response)
+	}
+
+	fn stored_token(&self, req: &Request) -> Result<Option<String>, Error> {
+		match self.storage {
+			Storage::Cookie => Ok(CookieJar::from_headers(req.headers())
+				.get(&self.field_name)
+				.filter(|token| !token.is_empty())
+				.map(str::to_owned)),
+			#[cfg(feature = "session")]
+			Storage::Session => req
+				.extensions()
+				.get::<super::session::Session>()
+				.map(|session| session.get(SESSION_KEY))
+				.ok_or_else(|| {
+					Error::new(
+						StatusCode::INTERNAL_SERVER_ERROR,
+						"CSRF tokens are kept in the session, but there is no session middleware",
+					)
+				}),
+		}
+	}
+
+	/// Pass the request on if it sends the token back, reading form bodies to find it.
+	async fn check(&self, req: Request, token: &str) -> Result<Request, Error> {
+		if let Some(sent) = req.headers().get(&self.header_name) {
+			return if matches(sent.as_bytes(), token) {
+				Ok(req)
+			} else {
+				Err(forbidden())
+			};
+		}
+
+		let is_form = req
+			.headers()
+			.get(CONTENT_TYPE)
+			.and_then(|value| value.to_str().ok())
+			.and_then(|value| value.split(';').next())
+			.is_some_and(|mime| {
+				mime.trim()
+					.eq_ignore_ascii_case("application/x-www-form-urlencoded")
+			});
+		if !is_form {
+			return Err(forbidden());
+		}
+
+		let req = limit::limit_body(req, MAX_FORM_SIZE).ok_or_else(forbidden)?;
+		let (parts, body) = req.into_parts();
+		let body = to_bytes(body).await?;
+		let sent = query::decode_pairs(&body)
+			.map_err(|e| Error::new(StatusCode::BAD_REQUEST, e))?
+			.into_iter()
+			.find(|(name, _)| *name == self.field_name);
+		match sent {
+			Some((_, sent)) if matches(sent.as_bytes(), token) => {
+				Ok(Request::from_parts(parts, Body::from(body)))
+			}
+			_ => Err(forbidden()),
+		}
+	}
+}
+
+fn is_safe(method: &Method) -> bool {
+	matches!(
+		*method,
+		Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+	)
+}
+
+/// Compare tokens in constant time, so their contents can't be guessed from response times.
+fn matches(sent: &[u8], token: &str) -> bool {
+	verify_slices_are_equal(sent, token.as_bytes()).is_ok()
+}
+
+fn forbidden() -> Error {
+	Error::new(StatusCode::FORBIDDEN, "missing or invalid CSRF token")
+}
@@ -0,0 +1,65 @@
+use std::{
+	fmt::{self, Debug, Formatter},
+	ops::Deref,
+	sync::Arc,
+};
+
+/// Application state shared with every handler, registered with
+/// [with_state](struct.HttpRouter.html#method.with_state) and read from a request with
+/// [data](trait.RequestExt.html#tymethod.data). Cloning the handle shares the state.
+///
+/// ```
+/// # use grout::{hyper::{Body, Method}, path, Data, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+/// struct Greeting(String);
+///
+/// async fn greet(req: Request) -> Response {
+/// 	let greeting = req.data::<Greeting>().unwrap();
+/// 	Ok(ResponseBuilder::default().body(Body::from(greeting.0.clone()))?)
+/// }
+///
+/// let router = Router::default()
+/// 	.handle(Method::GET, path![greet], greet)
+/// 	.build()
+/// 	.unwrap()
+/// 	.with_state(Greeting("hello".into()));
+/// ```
+pub struct Data<T: ?Sized>(Arc<T>);
+
+impl<T> Data<T> {
+	pub fn new(state: T) -> Self {
+		Self(Arc::new(state))
+	}
+}
+
+impl<T: ?Sized> Data<T> {
+	/// The shared state as an `Arc`, for passing it to code that doesn't know about handles.
+	pub fn into_inner(self) -> Arc<T> {
+		self.0
+	}
+}
+
+impl<T: ?Sized> Clone for Data<T> {
+	fn clone(&self) -> Self {
+		Self(Arc::clone(&self.0))
+	}
+}
+
+impl<T: ?Sized> Deref for Data<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		&self.0
+	}
+}
+
+impl<T: ?Sized> From<Arc<T>> for Data<T> {
+	fn from(state: Arc<T>) -> Self {
+		Self(state)
+	}
+}
+
+impl<T: ?Sized + Debug> Debug for Data<T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
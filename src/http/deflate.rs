@@ -0,0 +1,304 @@
+//! A small DEFLATE encoder for response compression: LZ77 matching over a 32 KiB window, encoded
+//! with the fixed Huffman codes in a block per chunk. It compresses less than a full
+//! implementation, but text responses still shrink severalfold.
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+/// The base lengths of the length codes starting at 257, and their extra bits.
+const LENGTH_BASE: [u16; 29] = [
+	3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+	163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+	0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// The base distances of the distance codes, and their extra bits.
+const DISTANCE_BASE: [u16; 30] = [
+	1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+	2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+	0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+	13,
+];
+
+/// A DEFLATE stream in the gzip or zlib format, compressed as it is written. Every chunk is
+/// compressed into its own block, which can refer back to the earlier chunks in the window, and
+/// is flushed to a byte boundary so the client can decompress what it has received so far.
+pub(crate) struct Encoder {
+	bits: BitWriter,
+	format: Format,
+	/// The last window of data written, which matches can refer back to, followed by the chunk
+	/// being compressed.
+	window: Vec<u8>,
+	/// The position of the first byte of the window in the whole stream.
+	base: usize,
+	/// The most recent position of every hash of three bytes, and for every position in the
+	/// window the previous position with the same hash, as positions in the whole stream.
+	head: Vec<usize>,
+	prev: Vec<usize>,
+	checksum: u32,
+	len: u32,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+	Gzip,
+	Zlib,
+}
+
+impl Encoder {
+	/// A stream in the gzip format, for the `gzip` content coding.
+	pub(crate) fn gzip() -> Self {
+		Self::new(
+			Format::Gzip,
+			&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff],
+			crc32(0, &[]),
+		)
+	}
+
+	/// A stream in the zlib format, for the `deflate` content coding.
+	pub(crate) fn zlib() -> Self {
+		Self::new(Format::Zlib, &[0x78, 0x01], adler32(1, &[]))
+	}
+
+	fn new(format: Format, header: &[u8], checksum: u32) -> Self {
+		Self {
+			bits: BitWriter {
+				out: header.to_vec(),
+				buf: 0,
+				len: 0,
+			},
+			format,
+			window: Vec::new(),
+			base: 0,
+			head: vec![usize::MAX; 1 << HASH_BITS],
+			prev: vec![usize::MAX; WINDOW_SIZE],
+			checksum,
+			len: 0,
+		}
+	}
+
+	/// Compress a chunk, returning the compressed bytes that are ready to be sent.
+	pub(crate) fn write(&mut self, data: &[u8]) -> Vec<u8> {
+		if !data.is_empty() {
+			self.block(data, false);
+			// An empty stored block, which aligns the stream to a byte boundary.
+			self.bits.write(0, 3);
+			self.bits.flush();
+			self.bits.out.extend_from_slice(&[0, 0, 0xff, 0xff]);
+		}
+		std::mem::take(&mut self.bits.out)
+	}
+
+	/// Compress the last chunk and end the stream, returning the rest of the compressed bytes.
+	pub(crate) fn finish(mut self, data: &[u8]) -> Vec<u8> {
+		self.block(data, true);
+		self.bits.flush();
+		let mut out = self.bits.out;
+		match self.format {
+			Format::Gzip => {
+				out.extend_from_slice(&self.checksum.to_le_bytes());
+				out.extend_from_slice(&self.len.to_le_bytes());
+			}
+			Format::Zlib => out.extend_from_slice(&self.checksum.to_be_bytes()),
+		}
+		out
+	}
+
+	/// Compress a chunk into a block with the fixed Huffman codes.
+	fn block(&mut self, data: &[u8], last: bool) {
+		self.checksum = match self.format {
+			Format::Gzip => crc32(self.checksum, data),
+			Format::Zlib => adler32(self.checksum, data),
+		};
+		self.len = self.len.wrapping_add(data.len() as u32);
+
+		// Keep only the last window of earlier chunks.
+		if self.window.len() > WINDOW_SIZE {
+			let dropped = self.window.len() - WINDOW_SIZE;
+			self.window.drain(..dropped);
+			self.base += dropped;
+		}
+		let mut pos = self.window.len();
+		self.window.extend_from_slice(data);
+
+		let bits = &mut self.bits;
+		bits.write(u32::from(last), 1);
+		bits.write(1, 2);
+		let window = &self.window;
+		while pos < window.len() {
+			let (length, distance) = longest_match(window, pos, self.base, &self.head, &self.prev);
+			let end = if length >= MIN_MATCH {
+				bits.length(length);
+				bits.distance(distance);
+				pos + length
+			} else {
+				bits.literal(window[pos]);
+				pos + 1
+			};
+
+			while pos < end {
+				if pos + MIN_MATCH <= window.len() {
+					let hash = hash(&window[pos..]);
+					let at = self.base + pos;
+					self.prev[at % WINDOW_SIZE] = self.head[hash];
+					self.head[hash] = at;
+				}
+				pos += 1;
+			}
+		}
+		bits.symbol(256);
+	}
+}
+
+fn hash(data: &[u8]) -> usize {
+	let value = u32::from(data[0]) << 16 | u32::from(data[1]) << 8 | u32::from(data[2]);
+	(value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+/// The longest earlier occurrence of the data at a position within the window, as its length and
+/// distance back. `base` is the position of the window in the whole stream, which the positions
+/// in `head` and `prev` are relative to.
+fn longest_match(
+	data: &[u8],
+	pos: usize,
+	base: usize,
+	head: &[usize],
+	prev: &[usize],
+) -> (usize, usize) {
+	if pos + MIN_MATCH > data.len() {
+		return (0, 0);
+	}
+
+	let at = base + pos;
+	let max = (data.len() - pos).min(MAX_MATCH);
+	let mut best = (0, 0);
+	let mut candidate = head[hash(&data[pos..])];
+	for _ in 0..MAX_CHAIN {
+		if candidate == usize::MAX || candidate < base || at - candidate > WINDOW_SIZE {
+			break;
+		}
+
+		let length = data[candidate - base..]
+			.iter()
+			.zip(&data[pos..pos + max])
+			.take_while(|(a, b)| a == b)
+			.count();
+		if length > best.0 {
+			best = (length, at - candidate);
+			if length == max {
+				break;
+			}
+		}
+
+		let next = prev[candidate % WINDOW_SIZE];
+		if next == usize::MAX || next >= candidate {
+			break;
+		}
+		candidate = next;
+	}
+
+	best
+}
+
+struct BitWriter {
+	out: Vec<u8>,
+	buf: u64,
+	len: u32,
+}
+
+impl BitWriter {
+	/// Write the low bits of a value, least significant first.
+	fn write(&mut self, value: u32, bits: u32) {
+		self.buf |= u64::from(value) << self.len;
+		self.len += bits;
+		while self.len >= 8 {
+			self.out.push(self.buf as u8);
+			self.buf >>= 8;
+			self.len -= 8;
+		}
+	}
+
+	/// Write a Huffman code, which is packed most significant bit first.
+	fn code(&mut self, code: u32, bits: u32) {
+		self.write(code.reverse_bits() >> (32 - bits), bits);
+	}
+
+	/// Write a literal/length symbol with its fixed code.
+	fn symbol(&mut self, symbol: u32) {
+		match symbol {
+			0..=143 => self.code(0x30 + symbol, 8),
+			144..=255 => self.code(0x190 + symbol - 144, 9),
+			256..=279 => self.code(symbol - 256, 7),
+			_ => self.code(0xc0 + symbol - 280, 8),
+		}
+	}
+
+	fn literal(&mut self, byte: u8) {
+		self.symbol(u32::from(byte));
+	}
+
+	fn length(&mut self, length: usize) {
+		let index = LENGTH_BASE
+			.iter()
+			.rposition(|&base| usize::from(base) <= length)
+			.unwrap();
+		self.symbol(257 + index as u32);
+		self.write(
+			(length - usize::from(LENGTH_BASE[index])) as u32,
+			u32::from(LENGTH_EXTRA[index]),
+		);
+	}
+
+	fn distance(&mut self, distance: usize) {
+		let index = DISTANCE_BASE
+			.iter()
+			.rposition(|&base| usize::from(base) <= distance)
+			.unwrap();
+		self.code(index as u32, 5);
+		self.write(
+			(distance - usize::from(DISTANCE_BASE[index])) as u32,
+			u32::from(DISTANCE_EXTRA[index]),
+		);
+	}
+
+	fn flush(&mut self) {
+		if self.len > 0 {
+			self.out.push(self.buf as u8);
+		}
+		self.buf = 0;
+		self.len = 0;
+	}
+}
+
+/// Continue a CRC-32 with more data.
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+	let mut crc = !crc;
+	for &byte in data {
+		crc ^= u32::from(byte);
+		for _ in 0..8 {
+			crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+		}
+	}
+	!crc
+}
+
+/// Continue an Adler-32 with more data.
+fn adler32(adler: u32, data: &[u8]) -> u32 {
+	let (mut a, mut b) = (adler & 0xffff, adler >> 16);
+	for chunk in data.chunks(5552) {
+		for &byte in chunk {
+			a += u32::from(byte);
+			b += a;
+		}
+		a %= 65521;
+		b %= 65521;
+	}
+	b << 16 | a
+}
@@ -0,0 +1,209 @@
+use crate::{ParamError, PayloadTooLarge};
+use hyper::StatusCode;
+use std::{
+	borrow::Cow,
+	error::Error as StdError,
+	fmt::{self, Debug, Display, Formatter},
+	io,
+	net::AddrParseError,
+	num::{ParseFloatError, ParseIntError},
+	str::{ParseBoolError, Utf8Error},
+	string::FromUtf8Error,
+};
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// An error returned from a route handler, carrying the status of the response that should be
+/// sent for it and optionally the body to send.
+///
+/// Any error type converts into this with `?`. Errors from parsing request data (integers,
+/// floats, booleans, addresses, UTF-8 and [parameters](enum.ParamError.html)) default to
+/// `400 Bad Request`, I/O errors default to the status of their kind (`404 Not Found`,
+/// `403 Forbidden`, `400 Bad Request` for invalid input or data, `504 Gateway Timeout`) and
+/// everything else defaults to `500 Internal Server Error`; use [new](#method.new) or
+/// [with_status](#method.with_status) to choose a status explicitly.
+///
+/// The default [error handlers](struct.HttpRouter.html#method.internal_error_handler) respond
+/// with the [client message](#method.client_message) of the error, so the text of server errors,
+/// which may describe internals such as database queries, isn't sent to clients unless it is
+/// set as the body with [with_body](#method.with_body).
+///
+/// ```
+/// # use grout::{hyper::{Body, StatusCode}, Error, Request, Response, ResponseBuilder};
+/// async fn handler(params: Vec<String>, _req: Request) -> Response {
+/// 	let id: u64 = params[0].parse()?; // 400 if the parameter isn't a number
+/// 	if id == 0 {
+/// 		return Err(Error::new(StatusCode::NOT_FOUND, "no such user"));
+/// 	}
+///
+/// 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// }
+///
+/// let error = Error::new(StatusCode::INTERNAL_SERVER_ERROR, "connection to 10.0.0.5 refused");
+/// assert_eq!(error.client_message(), "Internal Server Error");
+///
+/// let error = Error::new(StatusCode::CONFLICT, "email taken").with_body("that email is in use");
+/// assert_eq!(error.to_string(), "email taken");
+/// assert_eq!(error.client_message(), "that email is in use");
+/// ```
+///
+/// Error handlers can map domain errors to their statuses by downcasting the underlying error:
+///
+/// ```
+/// # use grout::{hyper::{Body, StatusCode}, Error, RequestParts, ResponseBuilder};
+/// #[derive(Debug)]
+/// enum AccountError {
+/// 	Suspended,
+/// 	Exists,
+/// }
+/// # impl std::fmt::Display for AccountError {
+/// # 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// # 		write!(f, "{:?}", self)
+/// # 	}
+/// # }
+/// # impl std::error::Error for AccountError {}
+///
+/// fn error_handler(e: Error, _parts: &RequestParts) -> hyper::Response<Body> {
+/// 	let e = match e.downcast_ref::<AccountError>() {
+/// 		Some(AccountError::Suspended) => e.with_status(StatusCode::FORBIDDEN),
+/// 		Some(AccountError::Exists) => e.with_status(StatusCode::CONFLICT),
+/// 		None => e,
+/// 	};
+/// 	ResponseBuilder::default()
+/// 		.status(e.status())
+/// 		.body(e.client_message().into_owned().into())
+/// 		.unwrap()
+/// }
+/// ```
+///
+/// Like `anyhow::Error`, this type deliberately does not implement `std::error::Error` so that
+/// the blanket conversion is possible.
+pub struct Error {
+	status: StatusCode,
+	inner: BoxError,
+	body: Option<String>,
+}
+
+impl Error {
+	pub fn new(status: StatusCode, error: impl Into<BoxError>) -> Self {
+		Self {
+			status,
+			inner: error.into(),
+			body: None,
+		}
+	}
+
+	/// The same error with a different status.
+	pub fn with_status(mut self, status: StatusCode) -> Self {
+		self.status = status;
+		self
+	}
+
+	/// The same error with a body to send to the client, instead of its client message.
+	pub fn with_body(mut self, body: impl Into<String>) -> Self {
+		self.body = Some(body.into());
+		self
+	}
+
+	/// The status of the response this error should produce.
+	pub fn status(&self) -> StatusCode {
+		self.status
+	}
+
+	/// The body set with [with_body](#method.with_body), if any.
+	pub fn body(&self) -> Option<&str> {
+		self.body.as_deref()
+	}
+
+	/// The text that is safe to send to the client: the body if one is set, the error itself
+	/// for client errors, and the reason phrase of the status for everything else.
+	pub fn client_message(&self) -> Cow<'_, str> {
+		match &self.body {
+			Some(body) => Cow::Borrowed(body),
+			None if self.status.is_client_error() => Cow::Owned(self.inner.to_string()),
+			None => Cow::Borrowed(self.status.canonical_reason().unwrap_or("Error")),
+		}
+	}
+
+	/// The underlying error.
+	pub fn inner(&self) -> &(dyn StdError + Send + Sync + 'static) {
+		&*self.inner
+	}
+
+	/// The underlying error, if it has a type.
+	pub fn downcast_ref<E: StdError + 'static>(&self) -> Option<&E> {
+		self.inner.downcast_ref()
+	}
+
+	pub fn into_inner(self) -> BoxError {
+		self.inner
+	}
+}
+
+fn is_parse_error(error: &(dyn StdError + 'static)) -> bool {
+	error.is::<ParseIntError>()
+		|| error.is::<ParseFloatError>()
+		|| error.is::<ParseBoolError>()
+		|| error.is::<AddrParseError>()
+		|| error.is::<Utf8Error>()
+		|| error.is::<FromUtf8Error>()
+		|| error.is::<ParamError>()
+}
+
+/// The status of an I/O error, by its kind.
+fn io_status(error: &(dyn StdError + 'static)) -> Option<StatusCode> {
+	let status = match error.downcast_ref::<io::Error>()?.kind() {
+		io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+		io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+		io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => StatusCode::BAD_REQUEST,
+		io::ErrorKind::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+		_ => return None,
+	};
+	Some(status)
+}
+
+/// Whether an error is caused by a request body exceeding its limit, which reaches handlers
+/// wrapped in the error of hyper.
+fn is_payload_too_large(error: &(dyn StdError + 'static)) -> bool {
+	let mut error = Some(error);
+	while let Some(cause) = error {
+		if cause.is::<PayloadTooLarge>() {
+			return true;
+		}
+		error = cause.source();
+	}
+	false
+}
+
+impl<E> From<E> for Error
+where
+	E: StdError + Send + Sync + 'static,
+{
+	fn from(error: E) -> Self {
+		let status = if is_payload_too_large(&error) {
+			StatusCode::PAYLOAD_TOO_LARGE
+		} else if is_parse_error(&error) {
+			StatusCode::BAD_REQUEST
+		} else {
+			io_status(&error).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+		};
+
+		Self::new(status, error)
+	}
+}
+
+impl Debug for Error {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Error")
+			.field("status", &self.status)
+			.field("inner", &self.inner)
+			.field("body", &self.body)
+			.finish()
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		Display::fmt(&self.inner, f)
+	}
+}
@@ -0,0 +1,189 @@
+use super::{IntoResponse, RequestExt, ResponseFuture};
+use crate::{Data, Error, Params, Query, Request};
+use hyper::{HeaderMap, Method, StatusCode, Uri};
+use std::{
+	future::{ready, Future},
+	pin::Pin,
+};
+
+/// A boxed future resolving to a value extracted from a request.
+pub type ExtractFuture<T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send>>;
+
+/// A value a handler can take as an argument, extracted from the request it handles. Handlers
+/// taking extractors are registered with [route](struct.Router.html#method.route) or adapted
+/// with [extract](fn.extract.html).
+///
+/// Extraction starts before the future is returned, with the request borrowed, so values that
+/// need the body, such as [Json](struct.Json.html), take it then and read it in the future. The
+/// arguments of a handler are extracted in order, so a [Request](type.Request.html) argument,
+/// which takes what is left of the request, goes last. A failed extraction responds with its
+/// error instead of calling the handler.
+///
+/// | Extractor | Value |
+/// |-|-|
+/// | [Params](struct.Params.html), `Vec<String>` | The values of the dynamic segments |
+/// | [Query](struct.Query.html) | The decoded query, failing with `400 Bad Request` |
+/// | [Data](struct.Data.html) | The state of its type, failing with `500 Internal Server Error` if there is none |
+/// | [Json](struct.Json.html) | The JSON body, failing like [body::json](body/fn.json.html) |
+/// | `HeaderMap`, `Method`, `Uri` | The parts of the request |
+/// | [Request](type.Request.html) | The rest of the request |
+/// | `Option` of an extractor | The value, or `None` if its extraction failed |
+pub trait FromRequest: Sized + Send + 'static {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self>;
+}
+
+fn extracted<T: Send + 'static>(result: Result<T, Error>) -> ExtractFuture<T> {
+	Box::pin(ready(result))
+}
+
+impl FromRequest for Params {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		extracted(Ok(req.params().clone()))
+	}
+}
+
+impl FromRequest for Vec<String> {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		extracted(Ok(req.params().to_vec()))
+	}
+}
+
+impl FromRequest for Query {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		extracted(req.query())
+	}
+}
+
+impl<T: Send + Sync + 'static> FromRequest for Data<T> {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		extracted(req.data::<T>().cloned().ok_or_else(|| {
+			Error::new(
+				StatusCode::INTERNAL_SERVER_ERROR,
+				format!("missing state of type {}", std::any::type_name::<T>()),
+			)
+		}))
+	}
+}
+
+impl FromRequest for HeaderMap {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		extracted(Ok(req.headers().clone()))
+	}
+}
+
+impl FromRequest for Method {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		extracted(Ok(req.method().clone()))
+	}
+}
+
+impl FromRequest for Uri {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		extracted(Ok(req.uri().clone()))
+	}
+}
+
+impl FromRequest for Request {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		extracted(Ok(std::mem::take(req)))
+	}
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::de::DeserializeOwned + Send + 'static> FromRequest for crate::Json<T> {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		let mut body = Request::new(std::mem::take(req.body_mut()));
+		*body.headers_mut() = req.headers().clone();
+		Box::pin(async move { super::body::json(body).await.map(crate::Json) })
+	}
+}
+
+impl<T: FromRequest> FromRequest for Option<T> {
+	fn from_request(req: &mut Request) -> ExtractFuture<Self> {
+		let fut = T::from_request(req);
+		Box::pin(async move { Ok(fut.await.ok()) })
+	}
+}
+
+/// A handler taking [extractors](trait.FromRequest.html) as its arguments and responding with
+/// any [IntoResponse](trait.IntoResponse.html) value. It is implemented for functions and
+/// closures taking up to eight extractors, which must be `Clone`, as functions and closures
+/// capturing `Arc`s are.
+pub trait Handler<Args>: Send + Sync + 'static {
+	fn call(&self, req: Request) -> ResponseFuture;
+}
+
+macro_rules! handler {
+	($($arg:ident),*) => {
+		impl<F, T, $($arg,)*> Handler<($($arg,)*)> for F
+		where
+			F: Fn($($arg),*) -> T + Clone + Send + Sync + 'static,
+			T: Future + Send + 'static,
+			T::Output: IntoResponse,
+			$($arg: FromRequest,)*
+		{
+			#[allow(non_snake_case, unused_mut, unused_variables)]
+			fn call(&self, mut req: Request) -> ResponseFuture {
+				$(let $arg = $arg::from_request(&mut req);)*
+				let handler = self.clone();
+				Box::pin(async move {
+					$(let $arg = $arg.await?;)*
+					handler($($arg),*).await.into_response()
+				})
+			}
+		}
+	};
+}
+
+handler!();
+handler!(A);
+handler!(A, B);
+handler!(A, B, C);
+handler!(A, B, C, D);
+handler!(A, B, C, D, E);
+handler!(A, B, C, D, E, G);
+handler!(A, B, C, D, E, G, H);
+handler!(A, B, C, D, E, G, H, I);
+
+/// Adapt a [Handler](trait.Handler.html) taking extractors into a route handler, which can be
+/// registered and [wrapped](trait.HandlerExt.html) like any other.
+///
+/// ```
+/// # use grout::{extract, hyper::{Method, StatusCode}, path, test::TestClient, Data, Params, PathSegment, Query, Router};
+/// struct Greeting(String);
+///
+/// async fn greet(params: Params, query: Query, greeting: Data<Greeting>) -> String {
+/// 	let name = params.named("name").unwrap_or_default();
+/// 	format!("{}, {}{}", greeting.0, name, query.get("punctuation").unwrap_or("."))
+/// }
+///
+/// async fn teapot(method: Method) -> (StatusCode, String) {
+/// 	(StatusCode::IM_A_TEAPOT, format!("no {}", method))
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let router = Router::default()
+/// 	.register(Method::GET, path![greet / {name}], extract(greet))
+/// 	.route(Method::POST, path![coffee], teapot)
+/// 	.build()?
+/// 	.with_state(Greeting("Hello".into()));
+/// let client = TestClient::new(router);
+///
+/// assert_eq!(client.get("/greet/Ferris?punctuation=!").await?.body(), "Hello, Ferris!");
+///
+/// let res = client.send(hyper::Request::post("/coffee").body(Default::default())?).await?;
+/// assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+/// assert_eq!(res.body(), "no POST");
+/// # Ok(())
+/// # }
+/// ```
+pub fn extract<H, Args>(
+	handler: H,
+) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static
+where
+	H: Handler<Args>,
+	Args: 'static,
+{
+	move |_params, req| handler.call(req)
+}
@@ -0,0 +1,369 @@
+//! Serving files from a directory.
+
+use super::{is_under, ResponseFuture};
+use crate::{router::percent_decode, Error, Request};
+use futures_core::Stream;
+use httpdate::{fmt_http_date, parse_http_date};
+use hyper::{
+	body::Bytes,
+	header::{
+		HeaderMap, ACCEPT, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+		IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+	},
+	http::response::Builder,
+	Body, Method, StatusCode,
+};
+use std::{
+	io::{self, ErrorKind, SeekFrom},
+	path::{Path, PathBuf},
+	pin::Pin,
+	sync::Arc,
+	task::{Context, Poll},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{fs::File, io::AsyncRead};
+
+/// The name of the file served for requests for a directory.
+pub const INDEX_FILE: &str = "index.html";
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Create a handler that serves the files under a directory, to be registered on a route ending
+/// in a catch-all segment. The remainder of the path matched by the catch-all is the path of the
+/// file under the root; requests for a directory are served its
+/// [index file](constant.INDEX_FILE.html).
+///
+/// Paths that would escape the root, such as ones with `..` segments, are answered with
+/// `404 Not Found`, as are missing files. The `Content-Type` of each file is guessed from its
+/// extension, falling back to `application/octet-stream`.
+///
+/// Files are sent with `Last-Modified` and `ETag` headers, and requests with `If-None-Match` or
+/// `If-Modified-Since` for a file that hasn't changed are answered with `304 Not Modified`.
+/// Single byte ranges requested with `Range` are answered with `206 Partial Content`, subject to
+/// `If-Range`, so media can be seeked.
+///
+/// ```
+/// # use grout::{fs::serve_dir, hyper::Method, path, PathSegment, Router};
+/// let router = Router::default().register(Method::GET, path![assets / **], serve_dir("./public"));
+/// # let _ = router.build();
+/// ```
+pub fn serve_dir(
+	root: impl Into<PathBuf>,
+) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static {
+	let root: Arc<Path> = root.into().into();
+	move |params, req| {
+		let root = Arc::clone(&root);
+		Box::pin(async move {
+			let path = params
+				.last()
+				.and_then(|path| resolve(&root, path))
+				.ok_or_else(not_found)?;
+			serve_file(path, req.headers()).await
+		})
+	}
+}
+
+/// Serves the index file of a single-page app for unmatched requests from browsers, set with
+/// [spa_fallback](../struct.HttpRouter.html#method.spa_fallback).
+#[derive(Debug, Clone)]
+pub(crate) struct SpaFallback {
+	index: Arc<Path>,
+	api_prefixes: Vec<String>,
+}
+
+impl SpaFallback {
+	pub(crate) fn new(index: PathBuf, api_prefixes: &[&str]) -> Self {
+		Self {
+			index: index.into(),
+			api_prefixes: api_prefixes
+				.iter()
+				.map(|prefix| prefix.trim_end_matches('/').to_owned())
+				.collect(),
+		}
+	}
+
+	/// Whether the index file should be served for an unmatched request: it must be a `GET` or
+	/// `HEAD` that accepts HTML, for a path outside of every API prefix.
+	pub(crate) fn accepts(&self, req: &Request) -> bool {
+		let path = req.uri().path();
+		let is_api = self
+			.api_prefixes
+			.iter()
+			.any(|prefix| is_under(path, prefix));
+		let accepts_html = req
+			.headers()
+			.get_all(ACCEPT)
+			.iter()
+			.filter_map(|accept| accept.to_str().ok())
+			.any(|accept| accept.contains("text/html"));
+
+		(req.method() == Method::GET || req.method() == Method::HEAD) && accepts_html && !is_api
+	}
+
+	pub(crate) fn serve(&self, req: Request) -> ResponseFuture {
+		let index = self.index.to_path_buf();
+		Box::pin(async move { serve_file(index, req.headers()).await })
+	}
+}
+
+fn not_found() -> Error {
+	Error::new(StatusCode::NOT_FOUND, "file not found")
+}
+
+/// The path of a file under the root, or `None` if it would be outside of the root.
+fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+	let mut resolved = root.to_path_buf();
+	for segment in path.split('/') {
+		let segment = percent_decode(segment)?;
+		match segment.as_str() {
+			"" | "." => {}
+			".." => return None,
+			_ if segment.contains(&['/', '\\', '\0', ':'][..]) => return None,
+			_ => resolved.push(segment),
+		}
+	}
+
+	Some(resolved)
+}
+
+async fn serve_file(mut path: PathBuf, headers: &HeaderMap) -> crate::Response {
+	let mut metadata = tokio::fs::metadata(&path).await.map_err(io_error)?;
+	if metadata.is_dir() {
+		path.push(INDEX_FILE);
+		metadata = tokio::fs::metadata(&path).await.map_err(io_error)?;
+	}
+	if !metadata.is_file() {
+		return Err(not_found());
+	}
+
+	let len = metadata.len();
+	let modified = metadata.modified().ok().map(truncate_to_seconds);
+	let etag = entity_tag(len, modified);
+	let mut builder = Builder::default()
+		.header(ACCEPT_RANGES, "bytes")
+		.header(ETAG, etag.as_str());
+	if let Some(modified) = modified {
+		builder = builder.header(LAST_MODIFIED, fmt_http_date(modified));
+	}
+
+	if is_fresh(headers, &etag, modified) {
+		return Ok(builder
+			.status(StatusCode::NOT_MODIFIED)
+			.body(Body::empty())?);
+	}
+
+	let range = headers
+		.get(RANGE)
+		.filter(|_| if_range_matches(headers, modified))
+		.and_then(|range| range.to_str().ok())
+		.and_then(|range| parse_range(range, len));
+	let (start, end) = match range {
+		None => (0, len),
+		Some(Ok(range)) => range,
+		Some(Err(Unsatisfiable)) => {
+			return Ok(builder
+				.status(StatusCode::RANGE_NOT_SATISFIABLE)
+				.header(CONTENT_RANGE, format!("bytes */{}", len))
+				.body(Body::empty())?);
+		}
+	};
+	if range.is_some() {
+		builder = builder.status(StatusCode::PARTIAL_CONTENT).header(
+			CONTENT_RANGE,
+			format!("bytes {}-{}/{}", start, end - 1, len),
+		);
+	}
+
+	let mut file = File::open(&path).await.map_err(io_error)?;
+	if start > 0 {
+		file.seek(SeekFrom::Start(start)).await?;
+	}
+	Ok(builder
+		.header(CONTENT_TYPE, content_type(&path))
+		.header(CONTENT_LENGTH, end - start)
+		.body(Body::wrap_stream(FileStream::new(file, end - start)))?)
+}
+
+/// Drop the sub-second part of a modification time, which HTTP dates can't represent, so that
+/// comparisons against dates sent back by clients are exact.
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+	let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+	UNIX_EPOCH + Duration::from_secs(since_epoch.as_secs())
+}
+
+/// A weak entity tag for a file, derived from its length and modification time.
+fn entity_tag(len: u64, modified: Option<SystemTime>) -> String {
+	let modified = modified
+		.and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+		.map_or(0, |since_epoch| since_epoch.as_secs());
+	format!("W/\"{:x}-{:x}\"", len, modified)
+}
+
+/// Whether an entity tag matches a list of them from `If-None-Match` or `If-Range`, comparing
+/// weakly.
+fn etag_matches(list: &str, etag: &str) -> bool {
+	let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+	list.trim() == "*" || list.split(',').any(|tag| opaque(tag) == opaque(etag))
+}
+
+/// Whether the copy of the file the client has cached is still current, in which case it is
+/// answered with `304 Not Modified`. `If-Modified-Since` is only consulted without
+/// `If-None-Match`.
+fn is_fresh(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+	if let Some(if_none_match) = headers.get(IF_NONE_MATCH) {
+		return if_none_match
+			.to_str()
+			.is_ok_and(|list| etag_matches(list, etag));
+	}
+
+	let since = headers
+		.get(IF_MODIFIED_SINCE)
+		.and_then(|since| since.to_str().ok())
+		.and_then(|since| parse_http_date(since).ok());
+	match (since, modified) {
+		(Some(since), Some(modified)) => modified <= since,
+		_ => false,
+	}
+}
+
+/// Whether a `Range` header should be honored given the `If-Range` header, which makes it
+/// conditional on the file being unchanged.
+fn if_range_matches(headers: &HeaderMap, modified: Option<SystemTime>) -> bool {
+	let if_range = match headers.get(IF_RANGE).map(|if_range| if_range.to_str()) {
+		None => return true,
+		Some(Ok(if_range)) => if_range.trim(),
+		Some(Err(_)) => return false,
+	};
+
+	// Weak tags can't be used for ranges, and every tag this handler creates is weak.
+	if if_range.starts_with('"') || if_range.starts_with("W/") {
+		return false;
+	}
+
+	match (parse_http_date(if_range), modified) {
+		(Ok(date), Some(modified)) => modified == date,
+		_ => false,
+	}
+}
+
+/// A `Range` header that can't be satisfied for the length of the file.
+struct Unsatisfiable;
+
+/// Parse a `Range` header into the start and exclusive end of the bytes to send. Only single
+/// byte ranges are supported; `None` is returned for anything else, which is answered with the
+/// whole file.
+fn parse_range(range: &str, len: u64) -> Option<Result<(u64, u64), Unsatisfiable>> {
+	let range = range.trim().strip_prefix("bytes=")?;
+	if range.contains(',') {
+		return None;
+	}
+
+	let (start, end) = range.split_once('-')?;
+	let (start, end) = (start.trim(), end.trim());
+	let range = if start.is_empty() {
+		let suffix: u64 = end.parse().ok()?;
+		(len.saturating_sub(suffix), len)
+	} else {
+		let start: u64 = start.parse().ok()?;
+		let end = if end.is_empty() {
+			len
+		} else {
+			let end: u64 = end.parse().ok()?;
+			if end < start {
+				return None;
+			}
+			end.saturating_add(1).min(len)
+		};
+		(start, end)
+	};
+
+	Some(if range.0 < range.1 {
+		Ok(range)
+	} else {
+		Err(Unsatisfiable)
+	})
+}
+
+fn io_error(error: io::Error) -> Error {
+	match error.kind() {
+		ErrorKind::NotFound | ErrorKind::PermissionDenied => not_found(),
+		_ => error.into(),
+	}
+}
+
+/// Guess the `Content-Type` of a file from its extension.
+pub fn content_type(path: &Path) -> &'static str {
+	let extension = path
+		.extension()
+		.and_then(|extension| extension.to_str())
+		.map(str::to_ascii_lowercase);
+
+	match extension.as_deref() {
+		Some("html") | Some("htm") => "text/html; charset=utf-8",
+		Some("css") => "text/css; charset=utf-8",
+		Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+		Some("json") | Some("map") => "application/json",
+		Some("txt") => "text/plain; charset=utf-8",
+		Some("csv") => "text/csv; charset=utf-8",
+		Some("xml") => "application/xml",
+		Some("wasm") => "application/wasm",
+		Some("pdf") => "application/pdf",
+		Some("zip") => "application/zip",
+		Some("png") => "image/png",
+		Some("jpg") | Some("jpeg") => "image/jpeg",
+		Some("gif") => "image/gif",
+		Some("svg") => "image/svg+xml",
+		Some("ico") => "image/x-icon",
+		Some("webp") => "image/webp",
+		Some("avif") => "image/avif",
+		Some("woff") => "font/woff",
+		Some("woff2") => "font/woff2",
+		Some("ttf") => "font/ttf",
+		Some("otf") => "font/otf",
+		Some("mp3") => "audio/mpeg",
+		Some("ogg") => "audio/ogg",
+		Some("wav") => "audio/wav",
+		Some("mp4") => "video/mp4",
+		Some("webm") => "video/webm",
+		_ => "application/octet-stream",
+	}
+}
+
+/// The contents of a file as a stream of chunks, ending after a number of bytes.
+struct FileStream {
+	file: File,
+	buf: Box<[u8]>,
+	remaining: u64,
+}
+
+impl FileStream {
+	fn new(file: File, len: u64) -> Self {
+		Self {
+			file,
+			buf: vec![0; CHUNK_SIZE].into_boxed_slice(),
+			remaining: len,
+		}
+	}
+}
+
+impl Stream for FileStream {
+	type Item = io::Result<Bytes>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+		if this.remaining == 0 {
+			return Poll::Ready(None);
+		}
+
+		let len = (this.buf.len() as u64).min(this.remaining) as usize;
+		match Pin::new(&mut this.file).poll_read(cx, &mut this.buf[..len]) {
+			Poll::Ready(Ok(0)) => Poll::Ready(None),
+			Poll::Ready(Ok(read)) => {
+				this.remaining -= read as u64;
+				Poll::Ready(Some(Ok(Bytes::copy_from_slice(&this.buf[..read]))))
+			}
+			Poll::Ready(Err(error)) => Poll::Ready(Some(Err(error))),
+			Poll::Pending => Poll::Pending,
+		}
+	}
+}
@@ -0,0 +1,165 @@
+//! Liveness and readiness endpoints backed by check functions.
+//!
+//! [with_health_checks](../struct.Router.html#method.with_health_checks) registers `/healthz`,
+//! which runs the liveness checks, and `/readyz`, which runs the liveness and readiness checks.
+//! Both respond with `200 OK` if every check passes and `503 Service Unavailable` otherwise,
+//! with a JSON summary of the checks:
+//!
+//! ```json
+//! {"checks":{"cache":{"error":"connection refused","status":"error"},"database":{"status":"ok"}},"status":"error"}
+//! ```
+//!
+//! ```
+//! # use grout::{health::HealthChecks, hyper::{Method, StatusCode}, test::TestClient, Error, Router};
+//! # use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let warm = Arc::new(AtomicBool::new(false));
+//! let checks = HealthChecks::default()
+//! 	.liveness("process", || async { Ok(()) })
+//! 	.readiness("cache", {
+//! 		let warm = Arc::clone(&warm);
+//! 		move || {
+//! 			let warm = warm.load(Ordering::SeqCst);
+//! 			async move {
+//! 				if warm {
+//! 					Ok(())
+//! 				} else {
+//! 					Err(Error::new(StatusCode::SERVICE_UNAVAILABLE, "cache is cold"))
+//! 				}
+//! 			}
+//! 		}
+//! 	});
+//!
+//! let router = Router::default().with_health_checks(checks).build()?;
+//! let client = TestClient::new(router);
+//! assert_eq!(client.get("/healthz").await?.status(), StatusCode::OK);
+//! assert_eq!(client.get("/readyz").await?.status(), StatusCode::SERVICE_UNAVAILABLE);
+//!
+//! warm.store(true, Ordering::SeqCst);
+//! let res = client.get("/readyz").await?;
+//! assert_eq!(res.status(), StatusCode::OK);
+//! assert_eq!(res.body(), r#"{"checks":{"cache":{"status":"ok"},"process":{"status":"ok"}},"status":"ok"}"#);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{InnerHttpRouter, ResponseFuture};
+use crate::{Error, Json, PathSegment, Request};
+use hyper::{Method, StatusCode};
+use serde_json::{json, Map};
+use std::{
+	fmt::{self, Debug, Formatter},
+	future::Future,
+	pin::Pin,
+	sync::Arc,
+};
+
+/// The path of the liveness endpoint.
+pub const HEALTH_PATH: &str = "healthz";
+
+/// The path of the readiness endpoint.
+pub const READY_PATH: &str = "readyz";
+
+type CheckFuture = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+type Check = Arc<dyn Fn() -> CheckFuture + Send + Sync>;
+
+/// The check functions behind the health endpoints, by name. Checks run one after another for
+/// every request to an endpoint, so they should be quick, such as pinging a database.
+#[derive(Clone, Default)]
+pub struct HealthChecks {
+	liveness: Vec<(String, Check)>,
+	readiness: Vec<(String, Check)>,
+}
+
+impl HealthChecks {
+	/// Add a check of whether the server is working at all, which fails when it should be
+	/// restarted. Liveness checks back both endpoints.
+	pub fn liveness<F, T>(mut self, name: impl Into<String>, check: F) -> Self
+	where
+		F: Fn() -> T + Send + Sync + 'static,
+		T: Future<Output = Result<(), Error>> + Send + 'static,
+	{
+		self.liveness.push((name.into(), boxed(check)));
+		self
+	}
+
+	/// Add a check of whether the server can handle requests, which fails while it should be
+	/// taken out of load balancing, such as while a dependency is down.
+	pub fn readiness<F, T>(mut self, name: impl Into<String>, check: F) -> Self
+	where
+		F: Fn() -> T + Send + Sync + 'static,
+		T: Future<Output = Result<(), Error>> + Send + 'static,
+	{
+		self.readiness.push((name.into(), boxed(check)));
+		self
+	}
+}
+
+impl Debug for HealthChecks {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let names = |checks: &[(String, Check)]| -> Vec<String> {
+			checks.iter().map(|(name, _)| name.clone()).collect()
+		};
+		f.debug_struct("HealthChecks")
+			.field("liveness", &names(&self.liveness))
+			.field("readiness", &names(&self.readiness))
+			.finish()
+	}
+}
+
+fn boxed<F, T>(check: F) -> Check
+where
+	F: Fn() -> T + Send + Sync + 'static,
+	T: Future<Output = Result<(), Error>> + Send + 'static,
+{
+	Arc::new(move || Box::pin(check()))
+}
+
+impl<'a> InnerHttpRouter<'a, Method> {
+	/// Register `GET /healthz` and `GET /readyz` endpoints backed by [health checks](health/index.html).
+	pub fn with_health_checks(self, checks: HealthChecks) -> Self {
+		let liveness = Arc::new(checks.liveness);
+		let readiness: Arc<Vec<_>> =
+			Arc::new(liveness.iter().cloned().chain(checks.readiness).collect());
+		self.register(
+			Method::GET,
+			vec![PathSegment::Static(HEALTH_PATH.into())],
+			endpoint(liveness),
+		)
+		.register(
+			Method::GET,
+			vec![PathSegment::Static(READY_PATH.into())],
+			endpoint(readiness),
+		)
+	}
+}
+
+fn endpoint(
+	checks: Arc<Vec<(String, Check)>>,
+) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static {
+	move |_params, _req| {
+		let checks = Arc::clone(&checks);
+		Box::pin(async move {
+			let mut healthy = true;
+			let mut summary = Map::new();
+			for (name, check) in checks.iter() {
+				let result = match check().await {
+					Ok(()) => json!({ "status": "ok" }),
+					Err(e) => {
+						healthy = false;
+						json!({ "status": "error", "error": e.to_string() })
+					}
+				};
+				summary.insert(name.clone(), result);
+			}
+
+			let (status, text) = if healthy {
+				(StatusCode::OK, "ok")
+			} else {
+				(StatusCode::SERVICE_UNAVAILABLE, "error")
+			};
+			Json(json!({ "status": text, "checks": summary })).with_status(status)
+		})
+	}
+}
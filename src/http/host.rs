@@ -0,0 +1,192 @@
+use super::{request_host, status_response, Connection, HttpRouter, RequestPrefix, RouteHandler};
+use crate::Request;
+use hyper::{service::Service, Body, Method, StatusCode};
+use std::{
+	collections::HashMap,
+	convert::Infallible,
+	future::{ready, Future, Ready},
+	net::SocketAddr,
+	pin::Pin,
+	sync::Arc,
+	task::{Context, Poll},
+};
+
+/// Selects among routers by the host a request was addressed to, so several domains can be
+/// served from one server. Pass it to hyper as the service, like an
+/// [HttpRouter](struct.HttpRouter.html).
+///
+/// Hosts are matched case insensitively and without a port. A host starting with `*.` matches
+/// any subdomain of the rest, at any depth, but not the rest itself; exact hosts take
+/// precedence over wildcards, and longer wildcards over shorter ones. Requests for other hosts go
+/// to the [fallback](#method.fallback) router, or are answered with `404 Not Found`.
+///
+/// ```
+/// # use grout::{hyper::{header::HOST, service::Service, Body, Method, StatusCode}, path, HostRouter, PathSegment, Request, Response, ResponseBuilder, Router};
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let site = Router::default().register(Method::GET, path![], handler).build()?;
+/// let tenants = Router::default().register(Method::GET, path![dashboard], handler).build()?;
+/// let mut hosts = HostRouter::default()
+/// 	.host("example.com", site.clone())
+/// 	.host("*.example.com", tenants)
+/// 	.fallback(site);
+///
+/// let mut service = hosts.call(()).await?;
+/// let req = hyper::Request::get("/dashboard")
+/// 	.header(HOST, "acme.example.com:8080")
+/// 	.body(Body::empty())?;
+/// assert_eq!(service.call(req).await?.status(), StatusCode::OK);
+///
+/// let req = hyper::Request::get("/dashboard")
+/// 	.header(HOST, "example.com")
+/// 	.body(Body::empty())?;
+/// assert_eq!(service.call(req).await?.status(), StatusCode::NOT_FOUND);
+/// # Ok(())
+/// # }
+/// ```
+pub struct HostRouter<P = Method> {
+	hosts: Arc<Hosts<P>>,
+}
+
+struct Hosts<P> {
+	exact: HashMap<String, RouteHandler<'static, P>>,
+	/// Routers for the subdomains of a host, keyed by the host without the `*.`.
+	wildcard: HashMap<String, RouteHandler<'static, P>>,
+	fallback: Option<RouteHandler<'static, P>>,
+}
+
+impl<P> Default for HostRouter<P> {
+	fn default() -> Self {
+		Self {
+			hosts: Arc::new(Hosts {
+				exact: HashMap::new(),
+				wildcard: HashMap::new(),
+				fallback: None,
+			}),
+		}
+	}
+}
+
+impl<P> Clone for HostRouter<P> {
+	fn clone(&self) -> Self {
+		Self {
+			hosts: Arc::clone(&self.hosts),
+		}
+	}
+}
+
+impl<P> Clone for Hosts<P> {
+	fn clone(&self) -> Self {
+		Self {
+			exact: self.exact.clone(),
+			wildcard: self.wildcard.clone(),
+			fallback: self.fallback.clone(),
+		}
+	}
+}
+
+impl<P> HostRouter<P> {
+	fn hosts(&mut self) -> &mut Hosts<P> {
+		Arc::make_mut(&mut self.hosts)
+	}
+
+	/// Route requests for a host, or for its subdomains if it starts with `*.`, to a router.
+	/// Adding a host again replaces its router.
+	pub fn host(mut self, host: &str, router: HttpRouter<P>) -> Self {
+		let host = normalize(host);
+		let handler = router.handler();
+		let hosts = self.hosts();
+		match host.strip_prefix("*.") {
+			Some(domain) => hosts.wildcard.insert(domain.to_owned(), handler),
+			None => hosts.exact.insert(host, handler),
+		};
+		self
+	}
+
+	/// Route requests for hosts without a router of their own, and requests without a host, to a
+	/// router.
+	pub fn fallback(mut self, router: HttpRouter<P>) -> Self {
+		self.hosts().fallback = Some(router.handler());
+		self
+	}
+}
+
+impl<P> Hosts<P> {
+	fn find(&self, host: Option<&str>) -> Option<&RouteHandler<'static, P>> {
+		let host = match host {
+			Some(host) => normalize(host),
+			None => return self.fallback.as_ref(),
+		};
+		if let Some(handler) = self.exact.get(&host) {
+			return Some(handler);
+		}
+
+		let mut domain = host.as_str();
+		while let Some((_, parent)) = domain.split_once('.') {
+			if let Some(handler) = self.wildcard.get(parent) {
+				return Some(handler);
+			}
+			domain = parent;
+		}
+		self.fallback.as_ref()
+	}
+}
+
+fn normalize(host: &str) -> String {
+	host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+impl<T: Connection, P> Service<T> for HostRouter<P> {
+	type Response = HostHandler<P>;
+	type Error = Infallible;
+	type Future = Ready<Result<Self::Response, Self::Error>>;
+
+	fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, connection: T) -> Self::Future {
+		ready(Ok(HostHandler {
+			hosts: Arc::clone(&self.hosts),
+			remote_addr: connection.remote_addr(),
+		}))
+	}
+}
+
+/// Handles the requests of a single connection for a [HostRouter](struct.HostRouter.html).
+pub struct HostHandler<P = Method> {
+	hosts: Arc<Hosts<P>>,
+	remote_addr: Option<SocketAddr>,
+}
+
+impl<P> Clone for HostHandler<P> {
+	fn clone(&self) -> Self {
+		Self {
+			hosts: Arc::clone(&self.hosts),
+			remote_addr: self.remote_addr,
+		}
+	}
+}
+
+impl<P: RequestPrefix> Service<Request> for HostHandler<P> {
+	type Response = hyper::Response<Body>;
+	type Error = Infallible;
+	type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+	fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, req: Request) -> Self::Future {
+		match self.hosts.find(request_host(&req)) {
+			Some(handler) => match self.remote_addr {
+				Some(addr) => handler.clone().with_remote_addr(addr).call(req),
+				None => handler.clone().call(req),
+			},
+			None => Box::pin(ready(Ok(status_response(StatusCode::NOT_FOUND)))),
+		}
+	}
+}
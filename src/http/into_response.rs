@@ -0,0 +1,115 @@
+use super::ResponseFuture;
+use crate::{Error, Request, Response};
+use hyper::{header::CONTENT_TYPE, http::response::Builder, Body, StatusCode};
+use std::{future::Future, sync::Arc};
+
+/// A value a handler can respond with, converted into a [Response](type.Response.html). Wrap
+/// handlers returning one with [responder](fn.responder.html) to register them.
+///
+/// Strings respond with `200 OK` and a `text/plain` body, status codes with an empty body, and a
+/// status code paired with another value overrides the status of its response. A `Result`
+/// responds with its value or fails with its error, and [Json](struct.Json.html) responds with
+/// a JSON body.
+pub trait IntoResponse {
+	fn into_response(self) -> Response;
+}
+
+impl IntoResponse for hyper::Response<Body> {
+	fn into_response(self) -> Response {
+		Ok(self)
+	}
+}
+
+impl IntoResponse for Body {
+	fn into_response(self) -> Response {
+		Ok(hyper::Response::new(self))
+	}
+}
+
+impl IntoResponse for &'static str {
+	fn into_response(self) -> Response {
+		text(Body::from(self))
+	}
+}
+
+impl IntoResponse for String {
+	fn into_response(self) -> Response {
+		text(Body::from(self))
+	}
+}
+
+impl IntoResponse for StatusCode {
+	fn into_response(self) -> Response {
+		Ok(Builder::default().status(self).body(Body::empty())?)
+	}
+}
+
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+	fn into_response(self) -> Response {
+		let (status, value) = self;
+		let mut response = value.into_response()?;
+		*response.status_mut() = status;
+		Ok(response)
+	}
+}
+
+impl<T, E> IntoResponse for Result<T, E>
+where
+	T: IntoResponse,
+	E: Into<Error>,
+{
+	fn into_response(self) -> Response {
+		self.map_err(Into::into)?.into_response()
+	}
+}
+
+fn text(body: Body) -> Response {
+	Ok(Builder::default()
+		.header(CONTENT_TYPE, "text/plain; charset=utf-8")
+		.body(body)?)
+}
+
+/// Adapt a handler that responds with any [IntoResponse](trait.IntoResponse.html) value into a
+/// route handler, which can be registered and [wrapped](trait.HandlerExt.html) like any other.
+///
+/// ```
+/// # use grout::{hyper::{Method, StatusCode}, path, responder, test::TestClient, Error, PathSegment, Request, Router};
+/// async fn hello(_params: Vec<String>, _req: Request) -> &'static str {
+/// 	"hello"
+/// }
+///
+/// async fn user(params: Vec<String>, _req: Request) -> Result<(StatusCode, String), Error> {
+/// 	let id: u32 = params[0].parse()?;
+/// 	Ok((StatusCode::ACCEPTED, format!("user {}", id)))
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let router = Router::default()
+/// 	.register(Method::GET, path![hello], responder(hello))
+/// 	.register(Method::GET, path![users / _], responder(user))
+/// 	.build()?;
+/// let client = TestClient::new(router);
+///
+/// assert_eq!(client.get("/hello").await?.body(), "hello");
+/// let res = client.get("/users/42").await?;
+/// assert_eq!(res.status(), StatusCode::ACCEPTED);
+/// assert_eq!(res.body(), "user 42");
+/// assert_eq!(client.get("/users/me").await?.status(), StatusCode::BAD_REQUEST);
+/// # Ok(())
+/// # }
+/// ```
+pub fn responder<F, T>(
+	handler: F,
+) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static
+where
+	F: Fn(Vec<String>, Request) -> T + Send + Sync + 'static,
+	T: Future + Send + 'static,
+	T::Output: IntoResponse,
+{
+	let handler = Arc::new(handler);
+	move |params, req| {
+		let fut = handler(params, req);
+		Box::pin(async move { fut.await.into_response() })
+	}
+}
@@ -0,0 +1,90 @@
+use crate::{Error, Request, RequestParts};
+use hyper::{header::CONTENT_TYPE, http::response::Builder, Body, StatusCode};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// A value to respond with as JSON, setting `Content-Type: application/json`. Values are read
+/// from JSON request bodies with [body::json](body/fn.json.html), or by taking a `Json` argument
+/// in a [handler taking extractors](trait.Handler.html).
+///
+/// ```
+/// # use grout::{hyper::StatusCode, Json, Request, Response};
+/// # use serde_json::{json, Value};
+/// async fn status(_params: Vec<String>, _req: Request) -> Response {
+/// 	Json(json!({ "ok": true })).into_response()
+/// }
+///
+/// async fn created(_params: Vec<String>, _req: Request) -> Response {
+/// 	Json(json!({ "id": 42 })).with_status(StatusCode::CREATED)
+/// }
+///
+/// async fn echo(Json(body): Json<Value>) -> Json<Value> {
+/// 	Json(json!({ "echo": body }))
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> Json<T> {
+	/// Serialize the value into a `200 OK` response.
+	pub fn into_response(self) -> crate::Response {
+		self.with_status(StatusCode::OK)
+	}
+
+	/// Serialize the value into a response with a status.
+	pub fn with_status(self, status: StatusCode) -> crate::Response {
+		let body = serde_json::to_vec(&self.0)?;
+		Ok(Builder::default()
+			.status(status)
+			.header(CONTENT_TYPE, "application/json")
+			.body(body.into())?)
+	}
+}
+
+impl<T: Serialize> super::IntoResponse for Json<T> {
+	fn into_response(self) -> crate::Response {
+		self.with_status(StatusCode::OK)
+	}
+}
+
+fn json_response(status: StatusCode, body: Value) -> hyper::Response<Body> {
+	Builder::default()
+		.status(status)
+		.header(CONTENT_TYPE, "application/json")
+		.body(body.to_string().into())
+		.unwrap()
+}
+
+/// The machine-readable code of an error status: `internal` for server errors and the snake-cased
+/// reason phrase (e.g. `not_found`) for everything else.
+fn error_code(status: StatusCode) -> String {
+	if status.is_server_error() {
+		return "internal".to_owned();
+	}
+
+	status
+		.canonical_reason()
+		.unwrap_or("error")
+		.to_ascii_lowercase()
+		.replace(' ', "_")
+}
+
+/// An [ErrorHandler](type.ErrorHandler.html) that responds with a JSON body like
+/// `{"error":"internal","message":"..."}`, with the [client message](struct.Error.html#method.client_message)
+/// of the error.
+pub fn json_error_handler(e: Error, _parts: &RequestParts) -> hyper::Response<Body> {
+	let status = e.status();
+	json_response(
+		status,
+		json!({ "error": error_code(status), "message": e.client_message() }),
+	)
+}
+
+/// A [NotFoundHandler](type.NotFoundHandler.html) that responds with a JSON body like
+/// `{"error":"not_found","path":"/x"}`.
+pub fn json_not_found_handler(req: Request) -> hyper::Response<Body> {
+	json_response(
+		StatusCode::NOT_FOUND,
+		json!({ "error": "not_found", "path": req.uri().path() }),
+	)
+}
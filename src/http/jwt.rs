@@ -0,0 +1,303 @@
+//! Verifying JSON Web Tokens sent as bearer tokens.
+//!
+//! The [Jwt](struct.Jwt.html) middleware verifies the signature and times of the token of every
+//! request, along with its audience and issuer when they are configured, and stores its
+//! [Claims](struct.Claims.html) in the request extensions. Requests without a valid token are
+//! answered with `401 Unauthorized` and a `Bearer` challenge.
+//!
+//! ```
+//! # use grout::{hyper::{header::AUTHORIZATION, service::Service, Body, Method, StatusCode}, jwt::Jwt, path, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+//! async fn whoami(_params: Vec<String>, req: Request) -> Response {
+//! 	let subject = req.claims().and_then(|claims| claims.subject()).unwrap_or_default();
+//! 	Ok(ResponseBuilder::default().body(Body::from(subject.to_owned()))?)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let jwt = Jwt::hs256(b"secret").audience("api").issuer("https://auth.example.com");
+//! let mut router = Router::default()
+//! 	.register(Method::GET, path![me], whoami)
+//! 	.build()?
+//! 	.middleware(jwt.middleware());
+//!
+//! let token = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.\
+//! 	eyJzdWIiOiJhbGljZSIsImF1ZCI6ImFwaSIsImlzcyI6Imh0dHBzOi8vYXV0aC5leGFtcGxlLmNvbSIsImV4cCI6NDEwMjQ0NDgwMH0.\
+//! 	m-vBH6ykwOMao1dSXIbtO3kofE9Va2txLLA6vp9IhSk";
+//! let mut service = router.call(()).await?;
+//! let req = hyper::Request::get("/me")
+//! 	.header(AUTHORIZATION, format!("Bearer {}", token))
+//! 	.body(Body::empty())?;
+//! let res = service.call(req).await?;
+//! assert_eq!(hyper::body::to_bytes(res.into_body()).await?, "alice");
+//!
+//! let req = hyper::Request::get("/me").body(Body::empty())?;
+//! assert_eq!(service.call(req).await?.status(), StatusCode::UNAUTHORIZED);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+	auth::{credentials, unauthorized},
+	Next, ResponseFuture,
+};
+use crate::Request;
+use hyper::header::HeaderValue;
+use ring::{
+	hmac,
+	signature::{self, RsaPublicKeyComponents, UnparsedPublicKey},
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::{
+	error::Error as StdError,
+	fmt::{self, Debug, Display, Formatter},
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The default allowance for clock differences when checking times, in seconds.
+pub const DEFAULT_LEEWAY: Duration = Duration::from_secs(60);
+
+/// The claims of a verified token, read with [claims](../trait.RequestExt.html#tymethod.claims).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claims(Map<String, Value>);
+
+impl Claims {
+	pub fn get(&self, claim: &str) -> Option<&Value> {
+		self.0.get(claim)
+	}
+
+	/// The `sub` claim, which identifies who the token was issued to.
+	pub fn subject(&self) -> Option<&str> {
+		self.get("sub").and_then(Value::as_str)
+	}
+
+	/// Deserialize the claims into a type.
+	pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+		T::deserialize(Value::Object(self.0.clone()))
+	}
+
+	pub fn into_inner(self) -> Map<String, Value> {
+		self.0
+	}
+}
+
+/// Why a token was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JwtError {
+	/// The token isn't three base64url parts with a JSON header and claims.
+	Malformed,
+	/// The token is signed with an algorithm other than the one of the key.
+	Algorithm,
+	Signature,
+	/// The `exp` claim is missing or has passed.
+	Expired,
+	/// The `nbf` claim hasn't been reached.
+	NotYetValid,
+	/// The `aud` claim doesn't include the expected audience.
+	Audience,
+	/// The `iss` claim isn't the expected issuer.
+	Issuer,
+}
+
+impl Display for JwtError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Malformed => "malformed token",
+			Self::Algorithm => "unexpected token algorithm",
+			Self::Signature => "invalid token signature",
+			Self::Expired => "token has expired",
+			Self::NotYetValid => "token is not valid yet",
+			Self::Audience => "unexpected token audience",
+			Self::Issuer => "unexpected token issuer",
+		})
+	}
+}
+
+impl StdError for JwtError {}
+
+enum Key {
+	Hs256(hmac::Key),
+	Rs256(UnparsedPublicKey<Vec<u8>>),
+	Rs256Components(RsaPublicKeyComponents<Vec<u8>>),
+}
+
+impl Key {
+	fn algorithm(&self) -> &'static str {
+		match self {
+			Self::Hs256(_) => "HS256",
+			Self::Rs256(_) | Self::Rs256Components(_) => "RS256",
+		}
+	}
+
+	fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+		match self {
+			Self::Hs256(key) => hmac::verify(key, message, signature).is_ok(),
+			Self::Rs256(key) => key.verify(message, signature).is_ok(),
+			Self::Rs256Components(key) => key
+				.verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+				.is_ok(),
+		}
+	}
+}
+
+/// JWT verification settings, turned into middleware with [middleware](#method.middleware).
+///
+/// Tokens must be signed with the algorithm of the key, so a token can't choose a weaker one, and
+/// must have an `exp` claim. The audience and issuer are only checked when they are set.
+pub struct Jwt {
+	key: Key,
+	audience: Option<String>,
+	issuer: Option<String>,
+	leeway: Duration,
+}
+
+impl Jwt {
+	fn new(key: Key) -> Self {
+		Self {
+			key,
+			audience: None,
+			issuer: None,
+			leeway: DEFAULT_LEEWAY,
+		}
+	}
+
+	/// Verify tokens signed with HMAC-SHA256 using a shared secret.
+	pub fn hs256(secret: &[u8]) -> Self {
+		Self::new(Key::Hs256(hmac::Key::new(hmac::HMAC_SHA256, secret)))
+	}
+
+	/// Verify tokens signed with RSA PKCS#1 v1.5 and SHA-256, using a DER-encoded PKCS#1
+	/// `RSAPublicKey` of 2048 to 8192 bits.
+	pub fn rs256(public_key: &[u8]) -> Self {
+		Self::new(Key::Rs256(UnparsedPublicKey::new(
+			&signature::RSA_PKCS1_2048_8192_SHA256,
+			public_key.to_vec(),
+		)))
+	}
+
+	/// Verify RS256 tokens using the big-endian modulus and exponent of a public key, such as the
+	/// decoded `n` and `e` of a JSON Web Key.
+	pub fn rs256_components(modulus: &[u8], exponent: &[u8]) -> Self {
+		Self::new(Key::Rs256Components(RsaPublicKeyComponents {
+			n: modulus.to_vec(),
+			e: exponent.to_vec(),
+		}))
+	}
+
+	/// Require the `aud` claim to be or include an audience.
+	pub fn audience(mut self, audience: impl Into<String>) -> Self {
+		self.audience = Some(audience.into());
+		self
+	}
+
+	/// Require the `iss` claim to be an issuer.
+	pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+		self.issuer = Some(issuer.into());
+		self
+	}
+
+	/// Set the allowance for clock differences when checking `exp` and `nbf`.
+	pub fn leeway(mut self, leeway: Duration) -> Self {
+		self.leeway = leeway;
+		self
+	}
+
+	/// Verify a token, returning its claims.
+	pub fn verify(&self, token: &str) -> Result<Claims, JwtError> {
+		let mut parts = token.split('.');
+		let (header, claims, signature) =
+			match (parts.next(), parts.next(), parts.next(), parts.next()) {
+				(Some(header), Some(claims), Some(signature), None) => (header, claims, signature),
+				_ => return Err(JwtError::Malformed),
+			};
+
+		let message = &token[..header.len() + claims.len() + 1];
+		let header = decode_json(header)?;
+		if header.get("alg").and_then(Value::as_str) != Some(self.key.algorithm()) {
+			return Err(JwtError::Algorithm);
+		}
+
+		let signature = decode(signature)?;
+		if !self.key.verify(message.as_bytes(), &signature) {
+			return Err(JwtError::Signature);
+		}
+
+		let claims = decode_json(claims)?;
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs_f64();
+		let leeway = self.leeway.as_secs_f64();
+		match claims.get("exp").and_then(Value::as_f64) {
+			Some(exp) if exp + leeway > now => {}
+			_ => return Err(JwtError::Expired),
+		}
+		if let Some(nbf) = claims.get("nbf") {
+			match nbf.as_f64() {
+				Some(nbf) if nbf - leeway <= now => {}
+				_ => return Err(JwtError::NotYetValid),
+			}
+		}
+
+		if let Some(audience) = &self.audience {
+			let matches = match claims.get("aud") {
+				Some(Value::String(aud)) => aud == audience,
+				Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+				_ => false,
+			};
+			if !matches {
+				return Err(JwtError::Audience);
+			}
+		}
+
+		if let Some(issuer) = &self.issuer {
+			if claims.get("iss").and_then(Value::as_str) != Some(issuer) {
+				return Err(JwtError::Issuer);
+			}
+		}
+
+		Ok(Claims(claims))
+	}
+
+	/// The middleware, to register with [middleware](../struct.HttpRouter.html#method.middleware)
+	/// or attach to routes with [with](../trait.HandlerExt.html#tymethod.with).
+	pub fn middleware(
+		self,
+	) -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static {
+		let jwt = Arc::new(self);
+		move |mut req, next| {
+			let claims = match credentials(&req, "Bearer") {
+				None => return unauthorized(HeaderValue::from_static("Bearer")),
+				Some(token) => jwt.verify(token),
+			};
+			match claims {
+				Ok(claims) => {
+					req.extensions_mut().insert(claims);
+					next.run(req)
+				}
+				Err(_) => unauthorized(HeaderValue::from_static(r#"Bearer error="invalid_token""#)),
+			}
+		}
+	}
+}
+
+impl Debug for Jwt {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Jwt")
+			.field("algorithm", &self.key.algorithm())
+			.field("audience", &self.audience)
+			.field("issuer", &self.issuer)
+			.field("leeway", &self.leeway)
+			.finish()
+	}
+}
+
+fn decode(part: &str) -> Result<Vec<u8>, JwtError> {
+	base64::decode_config(part, base64::URL_SAFE_NO_PAD).map_err(|_| JwtError::Malformed)
+}
+
+fn decode_json(part: &str) -> Result<Map<String, Value>, JwtError> {
+	serde_json::from_slice(&decode(part)?).map_err(|_| JwtError::Malformed)
+}
@@ -0,0 +1,214 @@
+use super::{status_response, Next, ResponseFuture};
+#[cfg(feature = "timeout")]
+use crate::Error;
+use crate::Request;
+use futures_core::Stream;
+use hyper::{
+	body::{Bytes, HttpBody},
+	header::CONTENT_LENGTH,
+	Body, StatusCode,
+};
+#[cfg(feature = "timeout")]
+use std::time::Duration;
+use std::{
+	error::Error as StdError,
+	fmt::{self, Display, Formatter},
+	pin::Pin,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	task::{Context, Poll},
+};
+
+/// The error reading a request body fails with once it exceeds the size limit. Converting it
+/// into an [Error](struct.Error.html), even wrapped in the error of hyper, gives
+/// `413 Payload Too Large`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadTooLarge {
+	pub limit: u64,
+}
+
+impl Display for PayloadTooLarge {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "request body is larger than {} bytes", self.limit)
+	}
+}
+
+impl StdError for PayloadTooLarge {}
+
+/// Middleware that limits the size of request bodies for the routes it is attached to with
+/// [with](trait.HandlerExt.html#tymethod.with), like
+/// [max_body_size](struct.HttpRouter.html#method.max_body_size) does for the whole router. A
+/// route limit can only lower the limit of the router, since the router limit applies first.
+///
+/// ```
+/// # use grout::{body_limit, hyper::Method, path, HandlerExt, PathSegment, Request, Response};
+/// # async fn upload(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+/// let router = grout::Router::default()
+/// 	.register(Method::POST, path![avatar], upload.with(body_limit(64 * 1024)));
+/// # let _ = router.build();
+/// ```
+pub fn body_limit(
+	max: u64,
+) -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static {
+	move |req, next| match limit_body(req, max) {
+		Some(req) => next.run(req),
+		None => Box::pin(async { Ok(status_response(StatusCode::PAYLOAD_TOO_LARGE)) }),
+	}
+}
+
+/// The error a handler fails with when it doesn't respond within its
+/// [timeout](fn.timeout.html).
+#[cfg(feature = "timeout")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut {
+	pub timeout: Duration,
+}
+
+#[cfg(feature = "timeout")]
+impl Display for TimedOut {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "handler did not respond within {:?}", self.timeout)
+	}
+}
+
+#[cfg(feature = "timeout")]
+impl StdError for TimedOut {}
+
+/// Middleware that cancels the handler of a route if it doesn't respond in time, attached with
+/// [with](trait.HandlerExt.html#tymethod.with). The request then fails with a
+/// [TimedOut](struct.TimedOut.html) error with the status `504 Gateway Timeout`, which the
+/// [error handler](struct.HttpRouter.html#method.internal_error_handler) turns into a response.
+///
+/// ```
+/// # use grout::{hyper::{service::Service, Body, Method, StatusCode}, path, timeout, HandlerExt, PathSegment, Request, Response, Router};
+/// # use std::time::Duration;
+/// async fn report(_params: Vec<String>, _req: Request) -> Response {
+/// 	tokio::time::delay_for(Duration::from_secs(60)).await;
+/// 	unreachable!()
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let mut router = Router::default()
+/// 	.register(Method::GET, path![report], report.with(timeout(Duration::from_millis(10))))
+/// 	.build()?;
+///
+/// let mut service = router.call(()).await?;
+/// let res = service.call(hyper::Request::get("/report").body(Body::empty())?).await?;
+/// assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "timeout")]
+pub fn timeout(
+	timeout: Duration,
+) -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static {
+	move |req, next| {
+		let response = next.run(req);
+		Box::pin(async move {
+			match tokio::time::timeout(timeout, response).await {
+				Ok(response) => response,
+				Err(_) => Err(Error::new(
+					StatusCode::GATEWAY_TIMEOUT,
+					TimedOut { timeout },
+				)),
+			}
+		})
+	}
+}
+
+/// Limit the body of a request to a size, or `None` if it declares a larger `Content-Length`,
+/// which should be answered right away with `413 Payload Too Large`. Other bodies fail with
+/// [PayloadTooLarge](struct.PayloadTooLarge.html) once they are read past the limit.
+pub(super) fn limit_body(req: Request, max: u64) -> Option<Request> {
+	let declared = req
+		.headers()
+		.get(CONTENT_LENGTH)
+		.and_then(|len| len.to_str().ok())
+		.and_then(|len| len.parse::<u64>().ok());
+	if declared.is_some_and(|len| len > max) {
+		return None;
+	}
+
+	if HttpBody::size_hint(req.body())
+		.upper()
+		.is_some_and(|len| len <= max)
+	{
+		return Some(req);
+	}
+
+	Some(req.map(|body| {
+		Body::wrap_stream(Limited {
+			body,
+			limit: max,
+			read: 0,
+		})
+	}))
+}
+
+/// A request body that fails once more than its limit has been read.
+struct Limited {
+	body: Body,
+	limit: u64,
+	read: u64,
+}
+
+impl Stream for Limited {
+	type Item = Result<Bytes, Box<dyn StdError + Send + Sync>>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+		match Pin::new(&mut this.body).poll_data(cx) {
+			Poll::Ready(Some(Ok(chunk))) => {
+				this.read += chunk.len() as u64;
+				if this.read > this.limit {
+					let error = PayloadTooLarge { limit: this.limit };
+					Poll::Ready(Some(Err(error.into())))
+				} else {
+					Poll::Ready(Some(Ok(chunk)))
+				}
+			}
+			Poll::Ready(Some(Err(error))) => Poll::Ready(Some(Err(error.into()))),
+			Poll::Ready(None) => Poll::Ready(None),
+			Poll::Pending => Poll::Pending,
+		}
+	}
+}
+
+/// A limit on the number of requests a router handles at once, shared by all of its connections.
+#[derive(Debug, Clone)]
+pub(super) struct InFlight {
+	max: usize,
+	current: Arc<AtomicUsize>,
+}
+
+impl InFlight {
+	pub(super) fn new(max: usize) -> Self {
+		Self {
+			max,
+			current: Arc::new(AtomicUsize::new(0)),
+		}
+	}
+
+	/// Count a request as in flight until the guard is dropped, or return `None` if the limit has
+	/// been reached.
+	pub(super) fn acquire(&self) -> Option<InFlightGuard> {
+		let max = self.max;
+		self.current
+			.fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+				Some(current + 1).filter(|_| current < max)
+			})
+			.ok()?;
+		Some(InFlightGuard(Arc::clone(&self.current)))
+	}
+}
+
+pub(super) struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::AcqRel);
+	}
+}
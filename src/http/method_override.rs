@@ -0,0 +1,114 @@
+use super::{query::decode_pairs, Next, ResponseFuture};
+use crate::Request;
+use hyper::{
+	body::HttpBody,
+	header::{HeaderValue, CONTENT_TYPE},
+	Body, Method,
+};
+
+/// The header a request names the method it should be routed with in.
+pub const METHOD_OVERRIDE_HEADER: &str = "x-http-method-override";
+
+/// The form field a request names the method it should be routed with in, if it doesn't have
+/// the header.
+const METHOD_FIELD: &str = "_method";
+
+/// The longest form body that is read for the method field.
+const MAX_FORM_LENGTH: u64 = 64 * 1024;
+
+/// Middleware that routes `POST` requests with the method named in their
+/// `X-HTTP-Method-Override` header, or in the `_method` field of their urlencoded form body if
+/// they don't have the header, for clients behind proxies that only pass on `GET` and `POST`.
+/// Register it with [middleware](struct.HttpRouter.html#method.middleware), which runs it before
+/// the request is routed.
+///
+/// ```
+/// # use grout::{hyper::{Body, Method, StatusCode}, method_override, path, test::TestClient, PathSegment, Request, Response, ResponseBuilder, Router};
+/// async fn delete_user(_params: Vec<String>, _req: Request) -> Response {
+/// 	Ok(ResponseBuilder::default().status(StatusCode::NO_CONTENT).body(Body::empty())?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let router = Router::default()
+/// 	.register(Method::DELETE, path![users / _], delete_user)
+/// 	.build()?
+/// 	.middleware(method_override());
+/// let client = TestClient::new(router);
+///
+/// let req = hyper::Request::post("/users/1")
+/// 	.header("x-http-method-override", "DELETE")
+/// 	.body(Body::empty())?;
+/// assert_eq!(client.send(req).await?.status(), StatusCode::NO_CONTENT);
+///
+/// let req = hyper::Request::post("/users/1")
+/// 	.header("content-type", "application/x-www-form-urlencoded")
+/// 	.body(Body::from("_method=DELETE"))?;
+/// assert_eq!(client.send(req).await?.status(), StatusCode::NO_CONTENT);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Only `POST` requests are overridden, with methods such as `PUT`, `PATCH`, `DELETE` or an
+/// extension method. `GET`, `HEAD`, `OPTIONS`, `TRACE` and `CONNECT` are ignored, so a request
+/// can't be turned into one that caches and logs treat as harmless. Requests naming one of them
+/// or a method that isn't valid are routed as they are. Form bodies are
+/// only read if they are known to be at most 64 KiB, and are passed on to the handler intact.
+pub fn method_override() -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static
+{
+	|mut req, next| {
+		if req.method() != Method::POST {
+			return next.run(req);
+		}
+
+		if let Some(header) = req.headers().get(METHOD_OVERRIDE_HEADER) {
+			if let Some(method) = parse_method(header.as_bytes()) {
+				*req.method_mut() = method;
+			}
+			return next.run(req);
+		}
+
+		let readable = HttpBody::size_hint(req.body())
+			.upper()
+			.is_some_and(|len| len <= MAX_FORM_LENGTH);
+		if !is_form(req.headers().get(CONTENT_TYPE)) || !readable {
+			return next.run(req);
+		}
+
+		Box::pin(async move {
+			let (mut parts, body) = req.into_parts();
+			let body = hyper::body::to_bytes(body).await?;
+			let method = decode_pairs(&body)
+				.ok()
+				.and_then(|pairs| pairs.into_iter().find(|(name, _)| name == METHOD_FIELD))
+				.and_then(|(_, method)| parse_method(method.as_bytes()));
+			if let Some(method) = method {
+				parts.method = method;
+			}
+			next.run(Request::from_parts(parts, Body::from(body))).await
+		})
+	}
+}
+
+/// The method a request is overridden with, which must be valid and not one of the ignored ones.
+fn parse_method(method: &[u8]) -> Option<Method> {
+	let method = Method::from_bytes(method).ok()?;
+	let safe = [
+		Method::GET,
+		Method::HEAD,
+		Method::OPTIONS,
+		Method::TRACE,
+		Method::CONNECT,
+	];
+	Some(method).filter(|method| !safe.contains(method))
+}
+
+fn is_form(content_type: Option<&HeaderValue>) -> bool {
+	content_type
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.split(';').next())
+		.is_some_and(|mime| {
+			mime.trim()
+				.eq_ignore_ascii_case("application/x-www-form-urlencoded")
+		})
+}
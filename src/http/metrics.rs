@@ -0,0 +1,321 @@
+use super::ResponseFuture;
+use crate::Request;
+use hyper::{header::CONTENT_TYPE, http::response::Builder, Body, Method, StatusCode};
+use std::{
+	collections::HashMap,
+	fmt::Write,
+	future::ready,
+	sync::{Arc, Mutex, MutexGuard},
+	time::Instant,
+};
+
+/// The default upper bounds of the latency histogram buckets, in seconds.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+	0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The route label of requests that didn't match a route.
+const UNMATCHED: &str = "unmatched";
+
+/// Request metrics in the Prometheus text format, recorded by a router with
+/// [metrics](struct.HttpRouter.html#method.metrics) and served with
+/// [handler](#method.handler).
+///
+/// Requests are labeled by method, the template of the route they matched, such as
+/// `/users/{id}`, and the status of their response, so the number of series doesn't grow with
+/// the number of paths requested. Requests that don't match a route are labeled with the route
+/// `unmatched`, and methods other than the standard ones with the method `OTHER`. Three metrics
+/// are recorded:
+///
+/// - `grout_requests_total`, a counter of responses
+/// - `grout_request_duration_seconds`, a histogram of the time from routing a request to its
+///   response, which doesn't include router middleware
+/// - `grout_requests_in_flight`, a gauge of the requests being handled, without the status
+///
+/// Clones share their metrics.
+///
+/// ```
+/// # use grout::{hyper::{Body, Method}, path, test::TestClient, Metrics, PathSegment, Request, Response, ResponseBuilder, Router};
+/// async fn user(_params: Vec<String>, _req: Request) -> Response {
+/// 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let metrics = Metrics::default();
+/// let router = Router::default()
+/// 	.register(Method::GET, path![users / {id}], user)
+/// 	.register(Method::GET, path![metrics], metrics.handler())
+/// 	.build()?
+/// 	.metrics(metrics);
+///
+/// let client = TestClient::new(router);
+/// client.get("/users/1").await?;
+/// client.get("/users/2").await?;
+///
+/// let res = client.get("/metrics").await?;
+/// let text = std::str::from_utf8(res.body())?;
+/// assert!(text.contains(r#"grout_requests_total{method="GET",route="/users/{id}",status="200"} 2"#));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Metrics {
+	inner: Arc<Registry>,
+}
+
+#[derive(Debug)]
+struct Registry {
+	buckets: Vec<f64>,
+	series: Mutex<HashMap<SeriesKey, Series>>,
+	in_flight: Mutex<HashMap<(String, String), u64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct SeriesKey {
+	method: String,
+	route: String,
+	status: u16,
+}
+
+#[derive(Debug)]
+struct Series {
+	/// The number of observations in each bucket, not counting the ones in earlier buckets.
+	buckets: Vec<u64>,
+	sum: f64,
+	count: u64,
+}
+
+impl Default for Metrics {
+	fn default() -> Self {
+		Self::with_buckets(DEFAULT_BUCKETS.to_vec())
+	}
+}
+
+impl Metrics {
+	/// Record latencies in histogram buckets with these upper bounds, in seconds.
+	pub fn with_buckets(mut buckets: Vec<f64>) -> Self {
+		buckets.retain(|bound| bound.is_finite());
+		buckets.sort_by(f64::total_cmp);
+		buckets.dedup();
+		Self {
+			inner: Arc::new(Registry {
+				buckets,
+				series: Default::default(),
+				in_flight: Default::default(),
+			}),
+		}
+	}
+
+	/// Record the response future of a request routed to a route with a template, or to none.
+	pub(super) fn observe(
+		&self,
+		method: &Method,
+		route: Option<&str>,
+		fut: ResponseFuture,
+	) -> ResponseFuture {
+		let guard = InFlight::new(
+			Arc::clone(&self.inner),
+			method_label(method),
+			route.unwrap_or(UNMATCHED).to_owned(),
+		);
+		let start = Instant::now();
+		Box::pin(async move {
+			let res = fut.await;
+			let status = match &res {
+				Ok(response) => response.status(),
+				Err(e) => e.status(),
+			};
+			guard.finish(status, start.elapsed().as_secs_f64());
+			res
+		})
+	}
+
+	/// Render the metrics in the Prometheus text exposition format.
+	pub fn render(&self) -> String {
+		let mut series: Vec<_> = self
+			.inner
+			.series()
+			.iter()
+			.map(|(key, series)| {
+				(
+					key.clone(),
+					series.buckets.clone(),
+					series.sum,
+					series.count,
+				)
+			})
+			.collect();
+		series.sort_by(|a, b| a.0.cmp(&b.0));
+		let mut in_flight: Vec<_> = self
+			.inner
+			.in_flight()
+			.iter()
+			.map(|(key, count)| (key.clone(), *count))
+			.collect();
+		in_flight.sort();
+
+		let mut out = String::new();
+		out.push_str("# HELP grout_requests_total Requests handled, by route and status.\n");
+		out.push_str("# TYPE grout_requests_total counter\n");
+		for (key, _, _, count) in &series {
+			writeln!(out, "grout_requests_total{{{}}} {}", labels(key), count).unwrap();
+		}
+
+		out.push_str(
+			"# HELP grout_request_duration_seconds Time from routing requests to their responses.\n",
+		);
+		out.push_str("# TYPE grout_request_duration_seconds histogram\n");
+		for (key, buckets, sum, count) in &series {
+			let labels = labels(key);
+			let mut cumulative = 0;
+			for (bound, observed) in self.inner.buckets.iter().zip(buckets) {
+				cumulative += observed;
+				writeln!(
+					out,
+					"grout_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+					labels, bound, cumulative
+				)
+				.unwrap();
+			}
+			writeln!(
+				out,
+				"grout_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+				labels, count
+			)
+			.unwrap();
+			writeln!(
+				out,
+				"grout_request_duration_seconds_sum{{{}}} {}",
+				labels, sum
+			)
+			.unwrap();
+			writeln!(
+				out,
+				"grout_request_duration_seconds_count{{{}}} {}",
+				labels, count
+			)
+			.unwrap();
+		}
+
+		out.push_str("# HELP grout_requests_in_flight Requests being handled, by route.\n");
+		out.push_str("# TYPE grout_requests_in_flight gauge\n");
+		for ((method, route), count) in &in_flight {
+			writeln!(
+				out,
+				"grout_requests_in_flight{{method=\"{}\",route=\"{}\"}} {}",
+				escape(method),
+				escape(route),
+				count
+			)
+			.unwrap();
+		}
+		out
+	}
+
+	/// Create a handler that serves the metrics, to be registered on a route such as `/metrics`
+	/// for Prometheus to scrape.
+	pub fn handler(
+		&self,
+	) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static {
+		let metrics = self.clone();
+		move |_params, _req| {
+			let response = Builder::default()
+				.status(StatusCode::OK)
+				.header(CONTENT_TYPE, "text/plain; version=0.0.4")
+				.body(Body::from(metrics.render()));
+			Box::pin(ready(response.map_err(Into::into)))
+		}
+	}
+}
+
+impl Registry {
+	fn series(&self) -> MutexGuard<'_, HashMap<SeriesKey, Series>> {
+		self.series.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn in_flight(&self) -> MutexGuard<'_, HashMap<(String, String), u64>> {
+		self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// Counts a request as in flight until it is dropped, so requests whose connection closes before
+/// they finish aren't counted forever.
+struct InFlight {
+	registry: Arc<Registry>,
+	key: (String, String),
+}
+
+impl InFlight {
+	fn new(registry: Arc<Registry>, method: String, route: String) -> Self {
+		let key = (method, route);
+		*registry.in_flight().entry(key.clone()).or_insert(0) += 1;
+		Self { registry, key }
+	}
+
+	fn finish(self, status: StatusCode, seconds: f64) {
+		let key = SeriesKey {
+			method: self.key.0.clone(),
+			route: self.key.1.clone(),
+			status: status.as_u16(),
+		};
+		let registry = &self.registry;
+		let mut all = registry.series();
+		let series = all.entry(key).or_insert_with(|| Series {
+			buckets: vec![0; registry.buckets.len()],
+			sum: 0.0,
+			count: 0,
+		});
+		if let Some(bucket) = registry.buckets.iter().position(|bound| seconds <= *bound) {
+			series.buckets[bucket] += 1;
+		}
+		series.sum += seconds;
+		series.count += 1;
+	}
+}
+
+impl Drop for InFlight {
+	fn drop(&mut self) {
+		let mut in_flight = self.registry.in_flight();
+		if let Some(count) = in_flight.get_mut(&self.key) {
+			*count -= 1;
+		}
+	}
+}
+
+/// The method label of a request, which groups non-standard methods together so clients can't
+/// create series at will.
+fn method_label(method: &Method) -> String {
+	let standard = [
+		Method::GET,
+		Method::HEAD,
+		Method::POST,
+		Method::PUT,
+		Method::DELETE,
+		Method::CONNECT,
+		Method::OPTIONS,
+		Method::TRACE,
+		Method::PATCH,
+	];
+	if standard.contains(method) {
+		method.as_str().to_owned()
+	} else {
+		"OTHER".to_owned()
+	}
+}
+
+fn labels(key: &SeriesKey) -> String {
+	format!(
+		"method=\"{}\",route=\"{}\",status=\"{}\"",
+		escape(&key.method),
+		escape(&key.route),
+		key.status
+	)
+}
+
+fn escape(value: &str) -> String {
+	value
+		.replace('\\', "\\\\")
+		.replace('"', "\\\"")
+		.replace('\n', "\\n")
+}
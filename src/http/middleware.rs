@@ -0,0 +1,165 @@
+use super::{Config, DynErrorHandler, RequestParts};
+use crate::{Error, Request, Response};
+use hyper::Body;
+use std::{future::Future, pin::Pin, sync::Arc};
+
+/// A boxed future resolving to the response for a request.
+pub(super) type ResponseFuture = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+/// A middleware function registered with
+/// [middleware](struct.HttpRouter.html#method.middleware).
+pub(super) type DynMiddleware = Arc<dyn Fn(Request, Next) -> ResponseFuture + Send + Sync>;
+
+/// The rest of the middleware chain for a request, ending with routing it to its handler, or with
+/// the handler itself for middleware attached to a single route.
+pub struct Next {
+	config: Option<Arc<Config>>,
+	index: usize,
+	endpoint: Box<dyn FnOnce(Request) -> ResponseFuture + Send>,
+}
+
+impl Next {
+	pub(super) fn new(
+		config: Arc<Config>,
+		endpoint: impl FnOnce(Request) -> ResponseFuture + Send + 'static,
+	) -> Self {
+		Self {
+			config: Some(config),
+			index: 0,
+			endpoint: Box::new(endpoint),
+		}
+	}
+
+	fn endpoint(endpoint: impl FnOnce(Request) -> ResponseFuture + Send + 'static) -> Self {
+		Self {
+			config: None,
+			index: 0,
+			endpoint: Box::new(endpoint),
+		}
+	}
+
+	/// The error handler of the router, if this is the middleware chain of a router.
+	pub(super) fn error_handler(&self) -> Option<DynErrorHandler> {
+		self.config
+			.as_ref()
+			.map(|config| Arc::clone(&config.internal_error))
+	}
+
+	/// Pass the request to the next middleware, or route it if this is the last one.
+	pub fn run(mut self, req: Request) -> ResponseFuture {
+		let middleware = self
+			.config
+			.as_ref()
+			.and_then(|config| config.middleware.get(self.index).cloned());
+		match middleware {
+			None => (self.endpoint)(req),
+			Some(middleware) => {
+				self.index += 1;
+				middleware(req, self)
+			}
+		}
+	}
+}
+
+/// Attach middleware to a single route handler, so it only runs for requests routed to that
+/// handler. Middleware attached with `with` runs after the middleware of the
+/// [router](struct.HttpRouter.html#method.middleware), and the last one attached runs first.
+///
+/// ```
+/// # use grout::{hyper::{header::AUTHORIZATION, Body, Method, StatusCode}, path, Error, HandlerExt, Next, PathSegment, Request, Response, ResponseBuilder, Router};
+/// # async fn dashboard(_params: Vec<String>, _req: Request) -> Response {
+/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// # }
+/// # async fn index(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+/// async fn auth(req: Request, next: Next) -> Response {
+/// 	if !req.headers().contains_key(AUTHORIZATION) {
+/// 		return Err(Error::new(StatusCode::UNAUTHORIZED, "missing credentials"));
+/// 	}
+/// 	next.run(req).await
+/// }
+///
+/// let router = Router::default()
+/// 	.register(Method::GET, path![], index)
+/// 	.register(Method::GET, path![admin / **], dashboard.with(auth));
+/// # let _ = router.build();
+/// ```
+pub trait HandlerExt<T>: Sized {
+	fn with<M, U>(
+		self,
+		middleware: M,
+	) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static
+	where
+		M: Fn(Request, Next) -> U + Send + Sync + 'static,
+		U: Future<Output = Response> + Send + 'static;
+
+	/// Handle the errors of this route handler with its own error handler, instead of the
+	/// [error handler](struct.HttpRouter.html#method.internal_error_handler) of the router.
+	/// Errors from middleware attached with [with](#tymethod.with) after this are still handled
+	/// by the router.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method, StatusCode}, path, test::TestClient, Error, HandlerExt, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// async fn webhook(_params: Vec<String>, _req: Request) -> Response {
+	/// 	Err(Error::new(StatusCode::INTERNAL_SERVER_ERROR, "queue is full"))
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// // Acknowledge webhooks even when they fail, so they aren't retried.
+	/// let router = Router::default()
+	/// 	.register(Method::POST, path![webhooks / **], webhook.on_error(|_e, _parts| {
+	/// 		ResponseBuilder::default().body(Body::empty()).unwrap()
+	/// 	}))
+	/// 	.build()?;
+	/// let client = TestClient::new(router);
+	///
+	/// let req = hyper::Request::post("/webhooks/github").body(Body::empty())?;
+	/// assert_eq!(client.send(req).await?.status(), StatusCode::OK);
+	/// # Ok(())
+	/// # }
+	/// ```
+	fn on_error<E>(
+		self,
+		error_handler: E,
+	) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static
+	where
+		E: Fn(Error, &RequestParts) -> hyper::Response<Body> + Send + Sync + 'static;
+}
+
+impl<F, T> HandlerExt<T> for F
+where
+	F: Fn(Vec<String>, Request) -> T + Send + Sync + 'static,
+	T: Future<Output = Response> + Send + 'static,
+{
+	fn with<M, U>(
+		self,
+		middleware: M,
+	) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static
+	where
+		M: Fn(Request, Next) -> U + Send + Sync + 'static,
+		U: Future<Output = Response> + Send + 'static,
+	{
+		let handler = Arc::new(self);
+		move |params, req| {
+			let handler = Arc::clone(&handler);
+			let next = Next::endpoint(move |req| Box::pin(handler(params, req)));
+			Box::pin(middleware(req, next))
+		}
+	}
+
+	fn on_error<E>(
+		self,
+		error_handler: E,
+	) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static
+	where
+		E: Fn(Error, &RequestParts) -> hyper::Response<Body> + Send + Sync + 'static,
+	{
+		let error_handler = Arc::new(error_handler);
+		move |params, req| {
+			let error_handler = Arc::clone(&error_handler);
+			let parts = RequestParts::from(&req);
+			let fut = self(params, req);
+			Box::pin(async move { Ok(fut.await.unwrap_or_else(|e| error_handler(e, &parts))) })
+		}
+	}
+}
@@ -0,0 +1,1685 @@
+use crate::{
+	path_template,
+	router::{has_trailing_slash, Captures, RouteNames},
+	BuildError, Params, Path, RouteError, RouteMeta, RouteNode, Router, TrailingSlash, UrlError,
+};
+use arc_swap::ArcSwap;
+use hyper::{
+	body::Body,
+	header::{ALLOW, LOCATION},
+	http::{
+		method::InvalidMethod, response::Builder, Extensions, HeaderMap, Method, StatusCode, Uri,
+		Version,
+	},
+	server::conn::AddrStream,
+	service::Service,
+};
+use std::{
+	convert::Infallible,
+	future::{ready, Future, Ready},
+	hash::Hash,
+	net::{IpAddr, SocketAddr},
+	pin::Pin,
+	sync::{Arc, Mutex},
+	task::{Context, Poll},
+};
+
+mod auth;
+#[cfg(any(feature = "form", feature = "json", feature = "multipart"))]
+pub mod body;
+mod client_ip;
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "cookies")]
+pub mod cookies;
+mod cors;
+#[cfg(feature = "csrf")]
+pub mod csrf;
+mod data;
+#[cfg(feature = "compression")]
+mod deflate;
+mod error;
+mod extract;
+#[cfg(feature = "fs")]
+pub mod fs;
+#[cfg(feature = "health")]
+pub mod health;
+mod host;
+mod into_response;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+mod limit;
+mod method_override;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod middleware;
+#[cfg(feature = "multipart")]
+mod multipart;
+mod negotiate;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+mod prefix;
+mod query;
+mod rate_limit;
+mod request_id;
+#[cfg(feature = "serve")]
+mod serve;
+mod server;
+#[cfg(feature = "session")]
+pub mod session;
+#[cfg(feature = "sse")]
+pub mod sse;
+pub mod test;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(any(feature = "csrf", feature = "session"))]
+mod token;
+mod trace_context;
+#[cfg(all(unix, feature = "unix"))]
+mod unix;
+#[cfg(any(feature = "form", feature = "query"))]
+mod urlencoded;
+
+pub use auth::{basic_auth, bearer_auth, constant_time_eq};
+pub use client_ip::{Cidr, ClientIp, InvalidCidr};
+#[cfg(feature = "compression")]
+pub use compression::*;
+pub use cors::*;
+pub use data::*;
+pub use error::*;
+pub use extract::{extract, ExtractFuture, FromRequest, Handler};
+pub use host::*;
+pub use hyper;
+pub use into_response::{responder, IntoResponse};
+#[cfg(feature = "json")]
+pub use json::*;
+pub use limit::{body_limit, PayloadTooLarge};
+#[cfg(feature = "timeout")]
+pub use limit::{timeout, TimedOut};
+pub use method_override::{method_override, METHOD_OVERRIDE_HEADER};
+#[cfg(feature = "metrics")]
+pub use metrics::{Metrics, DEFAULT_BUCKETS};
+use middleware::{DynMiddleware, ResponseFuture};
+pub use middleware::{HandlerExt, Next};
+pub use negotiate::{negotiate, negotiate_language};
+pub use prefix::*;
+pub use query::Query;
+pub use rate_limit::rate_limit;
+pub use request_id::{request_id, RequestId, REQUEST_ID_HEADER};
+#[cfg(feature = "serve")]
+pub use serve::*;
+pub use server::*;
+#[cfg(feature = "tls")]
+pub use tls::*;
+pub use trace_context::{trace_context, TraceContext, TRACEPARENT_HEADER, TRACESTATE_HEADER};
+#[cfg(all(unix, feature = "unix"))]
+pub use unix::*;
+
+pub use hyper::http::response::Builder as ResponseBuilder;
+pub type Request = hyper::Request<Body>;
+pub type Response = Result<hyper::Response<Body>, Error>;
+
+fn default_error_handler(e: Error, _parts: &RequestParts) -> hyper::Response<Body> {
+	Builder::default()
+		.status(e.status())
+		.body(e.client_message().into_owned().into())
+		.unwrap()
+}
+
+fn sync_error_handler(handler: ErrorHandler) -> DynErrorHandler {
+	Arc::new(move |e, parts| Box::pin(ready(handler(e, &parts))))
+}
+
+fn default_not_found_handler(_req: Request) -> hyper::Response<Body> {
+	Builder::default().status(404).body(Body::empty()).unwrap()
+}
+
+fn default_method_not_allowed_handler(
+	_req: Request,
+	allowed: Vec<Method>,
+) -> hyper::Response<Body> {
+	Builder::default()
+		.status(StatusCode::METHOD_NOT_ALLOWED)
+		.header(ALLOW, allow_header(&allowed))
+		.body(Body::empty())
+		.unwrap()
+}
+
+fn options_response(allowed: &[Method]) -> hyper::Response<Body> {
+	Builder::default()
+		.status(StatusCode::NO_CONTENT)
+		.header(ALLOW, allow_header(allowed))
+		.body(Body::empty())
+		.unwrap()
+}
+
+/// Format methods as the value of an `Allow` header.
+pub fn allow_header(methods: &[Method]) -> String {
+	methods
+		.iter()
+		.map(Method::as_str)
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+fn status_response(status: StatusCode) -> hyper::Response<Body> {
+	Builder::default()
+		.status(status)
+		.body(Body::empty())
+		.unwrap()
+}
+
+/// Access to the routing information attached to a request.
+pub trait RequestExt {
+	/// The parameters matched while routing the request. These are the same parameters that are
+	/// passed to handlers registered with [register](struct.Router.html#method.register).
+	fn params(&self) -> &Params;
+
+	/// The state of type `T` registered with [with_state](struct.HttpRouter.html#method.with_state),
+	/// if there is any.
+	fn data<T: Send + Sync + 'static>(&self) -> Option<&Data<T>>;
+
+	/// The decoded [query](struct.Query.html) of the request, which is empty if there is none.
+	fn query(&self) -> Result<Query, Error>;
+
+	/// Build the URL path of a named route of the router handling the request, like
+	/// [Router::url_for](struct.Router.html#method.url_for).
+	fn url_for(&self, name: &str, params: &[&str]) -> Result<String, UrlError>;
+
+	/// The [ID](struct.RequestId.html) of the request, if the
+	/// [request_id](fn.request_id.html) middleware is registered.
+	fn request_id(&self) -> Option<&str>;
+
+	/// The [trace context](struct.TraceContext.html) of the request, if the
+	/// [trace_context](fn.trace_context.html) middleware is registered.
+	fn trace_context(&self) -> Option<&TraceContext>;
+
+	/// The address of the client the request came from, if it was served over TCP. Behind a
+	/// proxy this is the address of the proxy.
+	fn remote_addr(&self) -> Option<SocketAddr>;
+
+	/// The address of the client the request came from, resolved through the
+	/// [trusted proxies](struct.HttpRouter.html#method.trusted_proxies) of the router.
+	fn client_ip(&self) -> Option<IpAddr>;
+
+	/// The [metadata](struct.RouteMeta.html) of the route handling the request, if it was
+	/// registered with any. This is set while routing, so per-route middleware added with
+	/// [with](trait.HandlerExt.html#method.with) can read it but router middleware can't.
+	fn route_meta(&self) -> Option<&RouteMeta>;
+
+	/// The [cookies](cookies/struct.CookieJar.html) sent with the request.
+	#[cfg(feature = "cookies")]
+	fn cookies(&self) -> cookies::CookieJar;
+
+	/// The [session](session/struct.Session.html) of the request, if the session middleware is
+	/// registered.
+	#[cfg(feature = "session")]
+	fn session(&self) -> Option<&session::Session>;
+
+	/// The [CSRF token](csrf/index.html) of the request, if the CSRF middleware is registered.
+	#[cfg(feature = "csrf")]
+	fn csrf_token(&self) -> Option<&str>;
+
+	/// The [claims](jwt/struct.Claims.html) of the verified token of the request, if the JWT
+	/// middleware is registered.
+	#[cfg(feature = "jwt")]
+	fn claims(&self) -> Option<&jwt::Claims>;
+}
+
+impl RequestExt for Request {
+	fn params(&self) -> &Params {
+		static EMPTY: Params = Params::empty();
+		self.extensions().get().unwrap_or(&EMPTY)
+	}
+
+	fn data<T: Send + Sync + 'static>(&self) -> Option<&Data<T>> {
+		self.extensions().get()
+	}
+
+	fn query(&self) -> Result<Query, Error> {
+		Query::parse(self.uri().query().unwrap_or_default())
+	}
+
+	fn url_for(&self, name: &str, params: &[&str]) -> Result<String, UrlError> {
+		match self.extensions().get::<RouteUrls>() {
+			Some(RouteUrls(names)) => crate::router::url_for(names, name, params),
+			None => Err(UrlError::UnknownRoute {
+				name: name.to_owned(),
+			}),
+		}
+	}
+
+	fn request_id(&self) -> Option<&str> {
+		self.extensions().get().map(RequestId::as_str)
+	}
+
+	fn trace_context(&self) -> Option<&TraceContext> {
+		self.extensions().get()
+	}
+
+	fn remote_addr(&self) -> Option<SocketAddr> {
+		self.extensions().get().copied()
+	}
+
+	fn client_ip(&self) -> Option<IpAddr> {
+		self.extensions().get().map(|ClientIp(ip)| *ip)
+	}
+
+	fn route_meta(&self) -> Option<&RouteMeta> {
+		self.extensions().get::<Arc<RouteMeta>>().map(Arc::as_ref)
+	}
+
+	#[cfg(feature = "cookies")]
+	fn cookies(&self) -> cookies::CookieJar {
+		cookies::CookieJar::from_headers(self.headers())
+	}
+
+	#[cfg(feature = "session")]
+	fn session(&self) -> Option<&session::Session> {
+		self.extensions().get()
+	}
+
+	#[cfg(feature = "csrf")]
+	fn csrf_token(&self) -> Option<&str> {
+		self.extensions()
+			.get::<csrf::CsrfToken>()
+			.map(csrf::CsrfToken::as_str)
+	}
+
+	#[cfg(feature = "jwt")]
+	fn claims(&self) -> Option<&jwt::Claims> {
+		self.extensions().get()
+	}
+}
+
+/// The named routes of the router handling a request, kept in its extensions.
+#[derive(Clone)]
+struct RouteUrls(RouteNames<'static>);
+
+/// Request metadata captured before the route handler takes ownership of the request.
+#[derive(Debug, Clone)]
+pub struct RequestParts {
+	pub method: Method,
+	pub uri: Uri,
+	pub version: Version,
+	pub headers: HeaderMap,
+}
+
+impl From<&Request> for RequestParts {
+	fn from(req: &Request) -> Self {
+		Self {
+			method: req.method().clone(),
+			uri: req.uri().clone(),
+			version: req.version(),
+			headers: req.headers().clone(),
+		}
+	}
+}
+
+/// A function that can convert an error into a response. The parts of the request that produced
+/// the error are provided for logging and context-aware error pages. Handlers that need to await
+/// something are set with [async_error_handler](struct.HttpRouter.html#method.async_error_handler).
+pub type ErrorHandler = Arc<dyn Fn(Error, &RequestParts) -> hyper::Response<Body> + Send + Sync>;
+
+/// A boxed future resolving to the response for an error, returned by asynchronous error handlers.
+pub type ErrorResponseFuture = Pin<Box<dyn Future<Output = hyper::Response<Body>> + Send>>;
+
+/// An error handler as it is stored by the router, taking the parts of the request by value so
+/// it can be asynchronous.
+type DynErrorHandler = Arc<dyn Fn(Error, RequestParts) -> ErrorResponseFuture + Send + Sync>;
+
+/// A function that handles unroutable requests and creates a response.
+pub type NotFoundHandler = Arc<dyn Fn(Request) -> hyper::Response<Body> + Send + Sync>;
+
+/// A function that handles requests for a path that exists under other methods, given the methods
+/// that are allowed, and creates a response.
+pub type MethodNotAllowedHandler =
+	Arc<dyn Fn(Request, Vec<Method>) -> hyper::Response<Body> + Send + Sync>;
+
+/// A function that transforms every response before it is sent.
+pub type ResponseMapper = fn(res: hyper::Response<Body>) -> hyper::Response<Body>;
+
+/// Inserts a piece of shared state into the extensions of a request.
+type StateInserter = Arc<dyn Fn(&mut Extensions) + Send + Sync>;
+
+type InnerHttpRouter<'a, P> = Router<'a, P, Request, Response>;
+
+/// The default maximum length of a request URI.
+pub const DEFAULT_MAX_URI_LENGTH: usize = 8 * 1024;
+
+/// The default maximum number of path segments in a request.
+pub const DEFAULT_MAX_PATH_SEGMENTS: usize = 128;
+
+/// Settings shared by every connection served by an HTTP router.
+#[derive(Clone)]
+struct Config {
+	internal_error: DynErrorHandler,
+	not_found: NotFoundHandler,
+	/// Not found handlers for the paths under a prefix, without a trailing slash.
+	scoped_not_found: Vec<(String, NotFoundHandler)>,
+	method_not_allowed: MethodNotAllowedHandler,
+	map_response: Option<ResponseMapper>,
+	max_uri_length: usize,
+	max_path_segments: usize,
+	max_body_size: Option<u64>,
+	in_flight: Option<limit::InFlight>,
+	auto_options: bool,
+	cors: Option<Cors>,
+	trusted_proxies: Vec<Cidr>,
+	middleware: Vec<DynMiddleware>,
+	state: Vec<StateInserter>,
+	#[cfg(feature = "fs")]
+	spa: Option<fs::SpaFallback>,
+	#[cfg(feature = "compression")]
+	compression: Option<Compression>,
+	#[cfg(feature = "metrics")]
+	metrics: Option<Metrics>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			internal_error: sync_error_handler(Arc::new(default_error_handler)),
+			not_found: Arc::new(default_not_found_handler),
+			scoped_not_found: vec![],
+			method_not_allowed: Arc::new(default_method_not_allowed_handler),
+			map_response: None,
+			max_uri_length: DEFAULT_MAX_URI_LENGTH,
+			max_path_segments: DEFAULT_MAX_PATH_SEGMENTS,
+			max_body_size: None,
+			in_flight: None,
+			auto_options: true,
+			cors: None,
+			trusted_proxies: vec![],
+			middleware: vec![],
+			state: vec![],
+			#[cfg(feature = "fs")]
+			spa: None,
+			#[cfg(feature = "compression")]
+			compression: None,
+			#[cfg(feature = "metrics")]
+			metrics: None,
+		}
+	}
+}
+
+impl Config {
+	/// The not found handler of the longest prefix a path is under, if any.
+	fn scoped_not_found(&self, path: &str) -> Option<&NotFoundHandler> {
+		self.scoped_not_found
+			.iter()
+			.filter(|(prefix, _)| is_under(path, prefix))
+			.max_by_key(|(prefix, _)| prefix.len())
+			.map(|(_, handler)| handler)
+	}
+
+	fn within_limits(&self, uri: &Uri) -> bool {
+		let length = uri.scheme_str().map_or(0, |scheme| scheme.len() + 3)
+			+ uri
+				.authority()
+				.map_or(0, |authority| authority.as_str().len())
+			+ uri.path_and_query().map_or(0, |path| path.as_str().len());
+		if length > self.max_uri_length {
+			return false;
+		}
+
+		uri.path().bytes().filter(|&b| b == b'/').count() <= self.max_path_segments
+	}
+
+	/// Reject a request that is over the URI or body size limits, before any middleware sees it.
+	fn limit(&self, req: Request) -> Result<Request, StatusCode> {
+		if !self.within_limits(req.uri()) {
+			return Err(StatusCode::URI_TOO_LONG);
+		}
+
+		match self.max_body_size {
+			Some(max) => limit::limit_body(req, max).ok_or(StatusCode::PAYLOAD_TOO_LARGE),
+			None => Ok(req),
+		}
+	}
+}
+
+/// The hyper service for a [Router](struct.Router.html). Routes are keyed on the request method
+/// unless a different [RequestPrefix](trait.RequestPrefix.html) is used.
+///
+/// Cloning an HTTP router shares its route table, so a clone kept after passing the router to
+/// hyper can [reload](#method.reload) or [update](#method.update) the routes of the running
+/// server.
+///
+/// A `HEAD` request for a path without a `HEAD` route is handled by the `GET` route for the path.
+/// The body of every response to a `HEAD` request is dropped.
+///
+/// ```
+/// # use grout::{hyper::{body, service::Service, Body, Method, StatusCode}, path, PathSegment, Request, Response, ResponseBuilder, Router};
+/// async fn handler(_params: Vec<String>, _req: Request) -> Response {
+/// 	Ok(ResponseBuilder::default().body(Body::from("hello"))?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let mut router = Router::default().register(Method::GET, path![health], handler).build()?;
+/// let mut service = router.call(()).await?;
+/// let req = hyper::Request::head("/health").body(Body::empty())?;
+/// let res = service.call(req).await?;
+/// assert_eq!(res.status(), StatusCode::OK);
+/// assert!(body::to_bytes(res.into_body()).await?.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+pub struct HttpRouter<P = Method> {
+	router: Arc<ArcSwap<InnerHttpRouter<'static, P>>>,
+	/// Held while the route table is replaced, so concurrent updates don't lose each other's
+	/// changes.
+	writer: Arc<Mutex<()>>,
+	config: Arc<Config>,
+}
+
+impl<P> Clone for HttpRouter<P> {
+	fn clone(&self) -> Self {
+		Self {
+			router: Arc::clone(&self.router),
+			writer: Arc::clone(&self.writer),
+			config: Arc::clone(&self.config),
+		}
+	}
+}
+
+impl<P: RequestPrefix> HttpRouter<P> {
+	/// Validate and atomically replace the route table. Requests that have already been routed
+	/// finish with the routes they started with, and every later request uses the new routes.
+	pub fn reload(&self, router: InnerHttpRouter<'static, P>) -> Result<(), BuildError> {
+		self.swap(router).map(drop)
+	}
+
+	/// Validate and atomically replace the route table like [reload](#method.reload), returning
+	/// the routes it replaced, such as to roll back a configuration that turns out to be wrong.
+	/// An invalid route table leaves the current one in place.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method, StatusCode}, path, test::TestClient, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+	/// # }
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let router = Router::default().register(Method::GET, path![v1], handler).build()?;
+	/// let client = TestClient::new(router.clone());
+	///
+	/// // On SIGHUP, build the routes from the new configuration.
+	/// let old = router.swap(Router::default().register(Method::GET, path![v2], handler))?;
+	/// assert_eq!(client.get("/v1").await?.status(), StatusCode::NOT_FOUND);
+	/// assert_eq!(client.get("/v2").await?.status(), StatusCode::OK);
+	///
+	/// let invalid = Router::default()
+	/// 	.register(Method::GET, path![v3], handler)
+	/// 	.register(Method::GET, path![v3], handler);
+	/// assert!(router.swap(invalid).is_err());
+	/// assert_eq!(client.get("/v2").await?.status(), StatusCode::OK);
+	///
+	/// assert!(old.find_node(&Method::GET, "/v1").1.is_some());
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn swap(
+		&self,
+		router: InnerHttpRouter<'static, P>,
+	) -> Result<Arc<InnerHttpRouter<'static, P>>, BuildError> {
+		router.validate()?;
+		let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+		Ok(self.router.swap(Arc::new(router)))
+	}
+
+	/// Change the routes of the running server, such as to add the endpoints of a plugin loaded
+	/// after startup. The change is made to a copy of the current route table, which shares its
+	/// route handlers, and replaces it like [reload](#method.reload) if it is valid. Updates are
+	/// applied one at a time, so concurrent updates all take effect.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method, StatusCode}, path, test::TestClient, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// async fn plugin(_params: Vec<String>, _req: Request) -> Response {
+	/// 	Ok(ResponseBuilder::default().body(Body::from("plugin"))?)
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let router = Router::default().build()?;
+	/// let client = TestClient::new(router.clone());
+	/// assert_eq!(client.get("/plugin").await?.status(), StatusCode::NOT_FOUND);
+	///
+	/// router.update(|routes| routes.register(Method::GET, path![plugin], plugin))?;
+	/// assert_eq!(client.get("/plugin").await?.body(), "plugin");
+	///
+	/// router.update(|routes| routes.unregister(&Method::GET, path![plugin]))?;
+	/// assert_eq!(client.get("/plugin").await?.status(), StatusCode::NOT_FOUND);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn update<F>(&self, change: F) -> Result<(), BuildError>
+	where
+		P: Clone,
+		F: FnOnce(InnerHttpRouter<'static, P>) -> InnerHttpRouter<'static, P>,
+	{
+		let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+		let router = change(self.router.load().as_ref().clone());
+		router.validate()?;
+		self.router.store(Arc::new(router));
+		Ok(())
+	}
+
+	/// Build the URL path of a named route, like [Router::url_for](struct.Router.html#method.url_for),
+	/// with the current route table.
+	pub fn url_for(&self, name: &str, params: &[&str]) -> Result<String, UrlError> {
+		self.router.load().url_for(name, params)
+	}
+}
+
+impl<P> HttpRouter<P> {
+	/// Create the service that handles the requests of a single connection.
+	pub(crate) fn handler(&self) -> RouteHandler<'static, P> {
+		RouteHandler {
+			router: Arc::clone(&self.router),
+			config: Arc::clone(&self.config),
+			remote_addr: None,
+		}
+	}
+
+	/// Set the handler used when a route handler returns an error. Handlers can be functions or
+	/// closures, which can capture what they need to build responses, such as a logger or
+	/// templates.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method, StatusCode}, path, test::TestClient, Error, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// # use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+	/// async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	/// 	Err(Error::new(StatusCode::BAD_GATEWAY, "upstream closed the connection"))
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let failures = Arc::new(AtomicUsize::new(0));
+	/// let counted = Arc::clone(&failures);
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![proxy / **], handler)
+	/// 	.build()?
+	/// 	.internal_error_handler(move |e, _parts| {
+	/// 		counted.fetch_add(1, Ordering::SeqCst);
+	/// 		ResponseBuilder::default().status(e.status()).body(Body::empty()).unwrap()
+	/// 	});
+	/// let client = TestClient::new(router);
+	///
+	/// assert_eq!(client.get("/proxy/a").await?.status(), StatusCode::BAD_GATEWAY);
+	/// assert_eq!(failures.load(Ordering::SeqCst), 1);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn internal_error_handler<F>(mut self, handler: F) -> Self
+	where
+		F: Fn(Error, &RequestParts) -> hyper::Response<Body> + Send + Sync + 'static,
+	{
+		Arc::make_mut(&mut self.config).internal_error = sync_error_handler(Arc::new(handler));
+		self
+	}
+
+	/// Set an asynchronous handler used when a route handler returns an error, in place of the
+	/// [internal error handler](#method.internal_error_handler). It owns the parts of the request,
+	/// so it can log them or render an error page from them.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method, StatusCode}, path, test::TestClient, Error, PathSegment, Request, RequestParts, Response, ResponseBuilder, Router};
+	/// async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	/// 	Err(Error::new(StatusCode::FORBIDDEN, "not yours"))
+	/// }
+	///
+	/// async fn error_page(e: Error, parts: RequestParts) -> hyper::Response<Body> {
+	/// 	let page = format!("<h1>{}</h1><p>{}: {}</p>", e.status(), parts.uri.path(), e.client_message());
+	/// 	ResponseBuilder::default()
+	/// 		.status(e.status())
+	/// 		.header("content-type", "text/html")
+	/// 		.body(Body::from(page))
+	/// 		.unwrap()
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![secrets / _], handler)
+	/// 	.build()?
+	/// 	.async_error_handler(error_page);
+	/// let client = TestClient::new(router);
+	///
+	/// let res = client.get("/secrets/1").await?;
+	/// assert_eq!(res.status(), StatusCode::FORBIDDEN);
+	/// assert_eq!(res.body(), "<h1>403 Forbidden</h1><p>/secrets/1: not yours</p>");
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn async_error_handler<F, T>(mut self, handler: F) -> Self
+	where
+		F: Fn(Error, RequestParts) -> T + Send + Sync + 'static,
+		T: Future<Output = hyper::Response<Body>> + Send + 'static,
+	{
+		Arc::make_mut(&mut self.config).internal_error =
+			Arc::new(move |e, parts| Box::pin(handler(e, parts)));
+		self
+	}
+
+	/// Set the handler used when no route matches the request, which can be a function or a
+	/// closure.
+	pub fn not_found_handler<F>(mut self, handler: F) -> Self
+	where
+		F: Fn(Request) -> hyper::Response<Body> + Send + Sync + 'static,
+	{
+		Arc::make_mut(&mut self.config).not_found = Arc::new(handler);
+		self
+	}
+
+	/// Set the handler used when no route matches a request for a path under a prefix, such as
+	/// `/api`, instead of the [not found handler](#method.not_found_handler). The prefix covers
+	/// whole segments, so `/api` covers `/api` and `/api/users` but not `/apis`. Where prefixes
+	/// nest, the handler of the longest one is used, and setting a handler for a prefix again
+	/// replaces it. Scoped handlers take precedence over the
+	/// [single-page app fallback](#method.spa_fallback).
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method, StatusCode}, test::TestClient, ResponseBuilder, Router};
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let router = Router::<Method, _, _>::default()
+	/// 	.build()?
+	/// 	.not_found_handler(|_req| {
+	/// 		ResponseBuilder::default().status(404).body(Body::from("<h1>Not found</h1>")).unwrap()
+	/// 	})
+	/// 	.scoped_not_found_handler("/api", |_req| {
+	/// 		ResponseBuilder::default().status(404).body(Body::from(r#"{"error":"not_found"}"#)).unwrap()
+	/// 	});
+	/// let client = TestClient::new(router);
+	///
+	/// let res = client.get("/api/users").await?;
+	/// assert_eq!(res.status(), StatusCode::NOT_FOUND);
+	/// assert_eq!(res.body(), r#"{"error":"not_found"}"#);
+	/// assert_eq!(client.get("/apis").await?.body(), "<h1>Not found</h1>");
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn scoped_not_found_handler<F>(mut self, prefix: &str, handler: F) -> Self
+	where
+		F: Fn(Request) -> hyper::Response<Body> + Send + Sync + 'static,
+	{
+		let prefix = prefix.trim_end_matches('/').to_owned();
+		let scoped = &mut Arc::make_mut(&mut self.config).scoped_not_found;
+		scoped.retain(|(other, _)| *other != prefix);
+		scoped.push((prefix, Arc::new(handler)));
+		self
+	}
+
+	/// Serve the index file of a single-page app for unmatched `GET` requests whose `Accept`
+	/// header includes `text/html`, so that client-side routes load the app when visited
+	/// directly. Requests for paths under any of the API prefixes, and requests that don't
+	/// accept HTML, still go to the [not found handler](#method.not_found_handler).
+	///
+	/// ```
+	/// # use grout::{fs::serve_dir, hyper::Method, path, PathSegment, Router};
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![assets / **], serve_dir("./dist/assets"))
+	/// 	.build()
+	/// 	.unwrap()
+	/// 	.spa_fallback("./dist/index.html", &["/api"]);
+	/// ```
+	#[cfg(feature = "fs")]
+	pub fn spa_fallback(
+		mut self,
+		index: impl Into<std::path::PathBuf>,
+		api_prefixes: &[&str],
+	) -> Self {
+		Arc::make_mut(&mut self.config).spa =
+			Some(fs::SpaFallback::new(index.into(), api_prefixes));
+		self
+	}
+
+	/// Set the handler used when no route matches the request method, but routes for the path
+	/// exist under other methods. The default responds with `405 Method Not Allowed`, an `Allow`
+	/// header listing the methods and an empty body.
+	///
+	/// ```
+	/// # use grout::{hyper::{header::ALLOW, service::Service, Body, Method, StatusCode}, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let mut router = Router::default()
+	/// 	.register(Method::GET, path![users / _], handler)
+	/// 	.register(Method::DELETE, path![users / _], handler)
+	/// 	.build()?;
+	///
+	/// let mut service = router.call(()).await?;
+	/// let req = hyper::Request::post("/users/42").body(Body::empty())?;
+	/// let res = service.call(req).await?;
+	/// assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+	/// assert_eq!(res.headers()[ALLOW], "DELETE, GET, HEAD, OPTIONS");
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn method_not_allowed_handler<F>(mut self, handler: F) -> Self
+	where
+		F: Fn(Request, Vec<Method>) -> hyper::Response<Body> + Send + Sync + 'static,
+	{
+		Arc::make_mut(&mut self.config).method_not_allowed = Arc::new(handler);
+		self
+	}
+
+	/// Respond to errors and unmatched requests with JSON bodies instead of the plain text and
+	/// empty defaults, using [json_error_handler](fn.json_error_handler.html) and
+	/// [json_not_found_handler](fn.json_not_found_handler.html).
+	#[cfg(feature = "json")]
+	pub fn json_errors(self) -> Self {
+		self.internal_error_handler(json_error_handler)
+			.not_found_handler(json_not_found_handler)
+	}
+
+	/// Set a function that transforms every response before it is sent, whether it came from a
+	/// route handler, the error handler, the not found handler or the router itself.
+	pub fn map_response(mut self, map: ResponseMapper) -> Self {
+		Arc::make_mut(&mut self.config).map_response = Some(map);
+		self
+	}
+
+	/// Set whether `OPTIONS` requests for a path without an `OPTIONS` route are answered with
+	/// `204 No Content` and an `Allow` header listing the methods of the path, which is the
+	/// default. When disabled, they are handled like requests for any other unrouted method, and
+	/// `OPTIONS` is no longer listed in `Allow` headers unless it has a route.
+	///
+	/// ```
+	/// # use grout::{hyper::{header::ALLOW, service::Service, Body, Method, StatusCode}, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let mut router = Router::default().register(Method::POST, path![users], handler).build()?;
+	/// let mut service = router.call(()).await?;
+	/// let req = hyper::Request::options("/users").body(Body::empty())?;
+	/// let res = service.call(req).await?;
+	/// assert_eq!(res.status(), StatusCode::NO_CONTENT);
+	/// assert_eq!(res.headers()[ALLOW], "OPTIONS, POST");
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn auto_options(mut self, enabled: bool) -> Self {
+		Arc::make_mut(&mut self.config).auto_options = enabled;
+		self
+	}
+
+	/// Handle cross-origin requests with the given settings. See [Cors](struct.Cors.html).
+	///
+	/// Panics if the settings allow credentials from any origin.
+	///
+	/// ```
+	/// # use grout::{hyper::{header::{HeaderValue, ACCESS_CONTROL_ALLOW_ORIGIN, VARY}, service::Service, Body, Method, StatusCode}, path, Cors, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+	/// # }
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let mut router = Router::default()
+	/// 	.register(Method::PUT, path![users / _], handler)
+	/// 	.build()?
+	/// 	.cors(Cors::default().allow_origin("https://example.com"));
+	/// let mut service = router.call(()).await?;
+	///
+	/// let preflight = hyper::Request::options("/users/42")
+	/// 	.header("origin", "https://example.com")
+	/// 	.header("access-control-request-method", "PUT")
+	/// 	.body(Body::empty())?;
+	/// let res = service.call(preflight).await?;
+	/// assert_eq!(res.status(), StatusCode::NO_CONTENT);
+	/// assert_eq!(res.headers()["access-control-allow-methods"], "PUT");
+	///
+	/// let req = hyper::Request::put("/users/42")
+	/// 	.header("origin", "https://example.com")
+	/// 	.body(Body::empty())?;
+	/// let res = service.call(req).await?;
+	/// assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+	/// assert_eq!(res.headers()[VARY], "origin");
+	///
+	/// // Caches must not reuse the answer to another origin for this one.
+	/// let req = hyper::Request::put("/users/42")
+	/// 	.header("origin", "https://evil.example")
+	/// 	.body(Body::empty())?;
+	/// let res = service.call(req).await?;
+	/// assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+	/// assert_eq!(res.headers()[VARY], "origin");
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn cors(mut self, cors: Cors) -> Self {
+		assert!(
+			!cors.allows_credentials_from_any_origin(),
+			"CORS credentials must not be allowed from any origin"
+		);
+		Arc::make_mut(&mut self.config).cors = Some(cors);
+		self
+	}
+
+	/// Compress responses for clients that accept it.
+	///
+	/// ```
+	/// # use grout::{hyper::{header::{CONTENT_ENCODING, CONTENT_TYPE}, service::Service, Body, Method}, path, Compression, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// async fn report(_params: Vec<String>, _req: Request) -> Response {
+	/// 	let body = "all systems nominal\n".repeat(100);
+	/// 	Ok(ResponseBuilder::default().header(CONTENT_TYPE, "text/plain").body(body.into())?)
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let mut router = Router::default()
+	/// 	.register(Method::GET, path![report], report)
+	/// 	.build()?
+	/// 	.compression(Compression::default());
+	///
+	/// let mut service = router.call(()).await?;
+	/// let req = hyper::Request::get("/report")
+	/// 	.header("accept-encoding", "deflate, gzip;q=0.8")
+	/// 	.body(Body::empty())?;
+	/// let res = service.call(req).await?;
+	/// assert_eq!(res.headers()[CONTENT_ENCODING], "deflate");
+	/// # Ok(())
+	/// # }
+	/// ```
+	#[cfg(feature = "compression")]
+	pub fn compression(mut self, compression: Compression) -> Self {
+		Arc::make_mut(&mut self.config).compression = Some(compression);
+		self
+	}
+
+	/// Record the count, latency and status of requests in [metrics](struct.Metrics.html),
+	/// labeled by the route they matched.
+	#[cfg(feature = "metrics")]
+	pub fn metrics(mut self, metrics: Metrics) -> Self {
+		Arc::make_mut(&mut self.config).metrics = Some(metrics);
+		self
+	}
+
+	/// Add a middleware function that runs around the routing of every request. Middleware is
+	/// called in the order it is added with the request and the [Next](struct.Next.html) step of
+	/// the chain, which routes the request after the last middleware. A middleware can change the
+	/// request before passing it on, change the response afterwards, or respond without passing
+	/// the request on at all. Errors are handled by the
+	/// [error handler](#method.internal_error_handler) like errors from route handlers.
+	///
+	/// ```
+	/// # use grout::{hyper::{header::AUTHORIZATION, service::Service, Body, Method, StatusCode}, path, Error, Next, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+	/// # }
+	/// async fn auth(req: Request, next: Next) -> Response {
+	/// 	if !req.headers().contains_key(AUTHORIZATION) {
+	/// 		return Err(Error::new(StatusCode::UNAUTHORIZED, "missing credentials"));
+	/// 	}
+	///
+	/// 	let mut res = next.run(req).await?;
+	/// 	res.headers_mut().insert("x-authenticated", "true".parse()?);
+	/// 	Ok(res)
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let mut router = Router::default()
+	/// 	.register(Method::GET, path![users], handler)
+	/// 	.build()?
+	/// 	.middleware(auth);
+	/// let mut service = router.call(()).await?;
+	/// let req = hyper::Request::get("/users").body(Body::empty())?;
+	/// assert_eq!(service.call(req).await?.status(), StatusCode::UNAUTHORIZED);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn middleware<F, T>(mut self, middleware: F) -> Self
+	where
+		F: Fn(Request, Next) -> T + Send + Sync + 'static,
+		T: Future<Output = Response> + Send + 'static,
+	{
+		Arc::make_mut(&mut self.config)
+			.middleware
+			.push(Arc::new(move |req, next| Box::pin(middleware(req, next))));
+		self
+	}
+
+	/// Share state with every handler. The state is inserted into the extensions of every request
+	/// as a [Data](struct.Data.html) handle before routing, so it can be read with
+	/// [data](trait.RequestExt.html#tymethod.data) by handlers and the not found handler alike.
+	/// Each type of state can be registered once; registering a type again replaces it.
+	pub fn with_state<T: Send + Sync + 'static>(mut self, state: T) -> Self {
+		let state = Data::new(state);
+		Arc::make_mut(&mut self.config)
+			.state
+			.push(Arc::new(move |extensions| {
+				extensions.insert(state.clone());
+			}));
+		self
+	}
+
+	/// Set the maximum length of a request URI, defaulting to
+	/// [DEFAULT_MAX_URI_LENGTH](constant.DEFAULT_MAX_URI_LENGTH.html). Longer URIs are rejected with
+	/// `414 URI Too Long` before router middleware and routing.
+	pub fn max_uri_length(mut self, length: usize) -> Self {
+		Arc::make_mut(&mut self.config).max_uri_length = length;
+		self
+	}
+
+	/// Set the maximum number of segments in a request path, defaulting to
+	/// [DEFAULT_MAX_PATH_SEGMENTS](constant.DEFAULT_MAX_PATH_SEGMENTS.html). Segments are counted
+	/// as the number of `/` in the path, including empty segments, and paths with more are
+	/// rejected with `414 URI Too Long` before router middleware and routing. Routes that
+	/// legitimately expect deeply nested paths need a higher limit, and the URI length limit still
+	/// applies to them.
+	pub fn max_path_segments(mut self, segments: usize) -> Self {
+		Arc::make_mut(&mut self.config).max_path_segments = segments;
+		self
+	}
+
+	/// Set the maximum size of request bodies, which is unlimited by default. Requests declaring a
+	/// larger `Content-Length` are rejected with `413 Payload Too Large` before router middleware
+	/// and routing, and reading a body without one fails with
+	/// [PayloadTooLarge](struct.PayloadTooLarge.html) past the limit, in middleware too. Individual routes can have a lower limit with [body_limit](fn.body_limit.html).
+	pub fn max_body_size(mut self, size: u64) -> Self {
+		Arc::make_mut(&mut self.config).max_body_size = Some(size);
+		self
+	}
+
+	/// Set the maximum number of requests handled at once, which is unlimited by default.
+	/// Requests beyond the limit are answered right away with `503 Service Unavailable` instead of
+	/// waiting, so latency stays bounded under load spikes. A request counts until its response
+	/// is ready, not while the body is being sent.
+	///
+	/// The limit is shared by every connection served by the router and its clones, and setting it
+	/// again starts a new count.
+	///
+	/// ```
+	/// # use grout::{hyper::{service::Service, Body, Method, StatusCode}, path, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// # use std::time::Duration;
+	/// async fn slow(_params: Vec<String>, _req: Request) -> Response {
+	/// 	tokio::time::delay_for(Duration::from_millis(100)).await;
+	/// 	Ok(ResponseBuilder::default().body(Body::empty())?)
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let mut router = Router::default()
+	/// 	.register(Method::GET, path![], slow)
+	/// 	.build()?
+	/// 	.max_in_flight(1);
+	///
+	/// let mut service = router.call(()).await?;
+	/// let first = service.call(hyper::Request::get("/").body(Body::empty())?);
+	/// let second = service.call(hyper::Request::get("/").body(Body::empty())?).await?;
+	/// assert_eq!(second.status(), StatusCode::SERVICE_UNAVAILABLE);
+	/// assert_eq!(first.await?.status(), StatusCode::OK);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn max_in_flight(mut self, requests: usize) -> Self {
+		Arc::make_mut(&mut self.config).in_flight = Some(limit::InFlight::new(requests));
+		self
+	}
+
+	/// Trust the forwarding headers of requests from proxies in these ranges, such as the load
+	/// balancers in front of the server, when resolving the
+	/// [client address](trait.RequestExt.html#tymethod.client_ip) of requests. Without trusted
+	/// proxies, the client address is the remote address of the connection.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method}, path, test::TestClient, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+	/// async fn ip(_params: Vec<String>, req: Request) -> Response {
+	/// 	let ip = req.client_ip().map(|ip| ip.to_string()).unwrap_or_default();
+	/// 	Ok(ResponseBuilder::default().body(Body::from(ip))?)
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![], ip)
+	/// 	.build()?
+	/// 	.trusted_proxies(vec!["10.0.0.0/8".parse()?]);
+	///
+	/// let req = || hyper::Request::get("/").header("x-forwarded-for", "198.51.100.7, 10.0.0.2").body(Body::empty());
+	/// let behind_proxy = TestClient::new(router.clone()).remote_addr(([10, 0, 0, 1], 50000).into());
+	/// assert_eq!(behind_proxy.send(req()?).await?.body(), "198.51.100.7");
+	///
+	/// // Clients that connect directly can't choose their address.
+	/// let direct = TestClient::new(router).remote_addr(([192, 0, 2, 1], 50000).into());
+	/// assert_eq!(direct.send(req()?).await?.body(), "192.0.2.1");
+	/// # Ok(())
+	/// # }
+	/// ```
+	///
+	/// Use the client address to [rate limit](fn.rate_limit.html) clients behind the proxies:
+	///
+	/// ```
+	/// # use grout::{rate_limit, RequestExt};
+	/// # use std::time::Duration;
+	/// let limit = rate_limit(100, Duration::from_secs(60), |req| Some(req.client_ip()?.to_string()));
+	/// ```
+	pub fn trusted_proxies(mut self, proxies: impl IntoIterator<Item = Cidr>) -> Self {
+		Arc::make_mut(&mut self.config)
+			.trusted_proxies
+			.extend(proxies);
+		self
+	}
+}
+
+impl<'a, P: Eq + Hash> InnerHttpRouter<'a, P> {
+	/// Register a route handler that only takes the request. The matched parameters are in the
+	/// request extensions, where they can be read with [params](trait.RequestExt.html#tymethod.params)
+	/// by the handler and anything else that sees the request.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method}, path, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+	/// async fn handler(req: Request) -> Response {
+	/// 	let id = req.params().get(0).unwrap_or_default().to_owned();
+	/// 	Ok(ResponseBuilder::default().body(Body::from(id))?)
+	/// }
+	///
+	/// let router = Router::default().handle(Method::GET, path![users / _], handler);
+	/// ```
+	pub fn handle<F, T>(self, prefix: P, path: Path<'a>, handler: F) -> Self
+	where
+		F: Fn(Request) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Response> + Send,
+	{
+		self.register_boxed(
+			prefix,
+			path,
+			Box::new(move |_params, req: Request| Box::pin(handler(req))),
+		)
+	}
+
+	/// Register a [handler taking extractors](trait.Handler.html), such as the parameters, query,
+	/// state or JSON body of the request, as its arguments. See [extract](fn.extract.html).
+	pub fn route<H, Args>(self, prefix: P, path: Path<'a>, handler: H) -> Self
+	where
+		H: Handler<Args>,
+		Args: 'static,
+	{
+		self.register_boxed(
+			prefix,
+			path,
+			Box::new(move |_params, req: Request| handler.call(req)),
+		)
+	}
+
+	/// Register a synchronous route handler that is run on the blocking thread pool of the tokio
+	/// runtime, for CPU-bound work that would otherwise stall other requests. If the handler
+	/// panics, the panic is passed to the error handler as a `500 Internal Server Error`.
+	#[cfg(feature = "blocking")]
+	pub fn register_blocking<F>(self, prefix: P, path: Path<'a>, handler: F) -> Self
+	where
+		F: Fn(Vec<String>, Request) -> Response + Send + Sync + 'static,
+	{
+		let handler = Arc::new(handler);
+		self.register_boxed(
+			prefix,
+			path,
+			Box::new(move |params: Params, req: Request| {
+				let handler = Arc::clone(&handler);
+				let task = tokio::task::spawn_blocking(move || handler(params.into_vec(), req));
+				Box::pin(async move { task.await.unwrap_or_else(|e| Err(e.into())) })
+			}),
+		)
+	}
+}
+
+impl<'a> InnerHttpRouter<'a, Method> {
+	/// Register a route handler for an extension method, such as the `PROPFIND` and `MKCOL`
+	/// methods of WebDAV, straight from the result of parsing it with `Method::from_bytes`. A
+	/// method that isn't valid is reported by [validate](struct.Router.html#method.validate)
+	/// instead of being unwrapped. Requests with extension methods are routed like any other,
+	/// and their methods are listed in `Allow` headers.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method, StatusCode}, path, test::TestClient, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+	/// # }
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let router = Router::default()
+	/// 	.register_method(Method::from_bytes(b"PROPFIND"), path![files / **], handler)
+	/// 	.register(Method::GET, path![files / **], handler)
+	/// 	.build()?;
+	/// let client = TestClient::new(router);
+	///
+	/// let req = hyper::Request::builder()
+	/// 	.method(Method::from_bytes(b"PROPFIND")?)
+	/// 	.uri("/files/docs")
+	/// 	.body(Body::empty())?;
+	/// assert_eq!(client.send(req).await?.status(), StatusCode::OK);
+	///
+	/// let req = hyper::Request::builder().method("MKCOL").uri("/files/new").body(Body::empty())?;
+	/// let res = client.send(req).await?;
+	/// assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+	/// assert_eq!(res.headers()["allow"], "GET, HEAD, OPTIONS, PROPFIND");
+	///
+	/// let invalid = Router::default().register_method(Method::from_bytes(b"BAD METHOD"), path![], handler);
+	/// assert!(invalid.validate().is_err());
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn register_method<F, T>(
+		self,
+		method: Result<Method, InvalidMethod>,
+		path: Path<'a>,
+		route: F,
+	) -> Self
+	where
+		F: Fn(Vec<String>, Request) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Response> + Send,
+	{
+		match method {
+			Ok(method) => self.register(method, path, route),
+			Err(_) => self.with_error(RouteError::InvalidMethod {
+				path: path_template(&path),
+			}),
+		}
+	}
+}
+
+impl<P: RequestPrefix> InnerHttpRouter<'static, P> {
+	/// Validate the registered routes and create the hyper service for them. Every problem found
+	/// by [validate](struct.Router.html#method.validate) is reported together, so a router that
+	/// fails to build can be fixed in one go.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment::{self, *}, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let result = Router::default()
+	/// 	.register(Method::GET, vec![Static("files".into()), CatchAll, Static("raw".into())], handler)
+	/// 	.register(Method::GET, vec![Optional, Static("edit".into())], handler)
+	/// 	.register(Method::POST, path![files], handler)
+	/// 	.register(Method::POST, path![files], handler)
+	/// 	.build();
+	///
+	/// let errors = match result {
+	/// 	Ok(_) => unreachable!(),
+	/// 	Err(e) => e.errors,
+	/// };
+	/// let mut errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+	/// errors.sort();
+	/// assert_eq!(errors, vec![
+	/// 	"/_?/edit: only the last segment can be optional",
+	/// 	"/files/**: segments after a catch-all can never match",
+	/// 	"/files: registered more than once",
+	/// ]);
+	/// ```
+	pub fn build(self) -> Result<HttpRouter<P>, BuildError> {
+		self.validate()?;
+		Ok(HttpRouter::from(self))
+	}
+}
+
+impl<P> From<InnerHttpRouter<'static, P>> for HttpRouter<P> {
+	fn from(inner: InnerHttpRouter<'static, P>) -> Self {
+		Self {
+			router: Arc::new(ArcSwap::from_pointee(inner)),
+			writer: Default::default(),
+			config: Default::default(),
+		}
+	}
+}
+
+/// A connection the [HttpRouter](struct.HttpRouter.html) service is called with, which hyper
+/// does for every connection it accepts. Its remote address is added to the extensions of each
+/// request on the connection, where handlers read it with
+/// [remote_addr](trait.RequestExt.html#tymethod.remote_addr). Call the router with `()` for
+/// connections without an address, such as in tests.
+///
+/// ```
+/// # use grout::{hyper::{service::Service, Body, Method}, path, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+/// async fn ip(_params: Vec<String>, req: Request) -> Response {
+/// 	let ip = req.remote_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+/// 	Ok(ResponseBuilder::default().body(Body::from(ip))?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let mut router = Router::default().register(Method::GET, path![], ip).build()?;
+/// let mut service = router.call(std::net::SocketAddr::from(([192, 0, 2, 1], 50000))).await?;
+/// let res = service.call(Request::new(Body::empty())).await?;
+/// assert_eq!(hyper::body::to_bytes(res.into_body()).await?, "192.0.2.1");
+/// # Ok(())
+/// # }
+/// ```
+pub trait Connection {
+	fn remote_addr(&self) -> Option<SocketAddr>;
+}
+
+impl Connection for () {
+	fn remote_addr(&self) -> Option<SocketAddr> {
+		None
+	}
+}
+
+impl Connection for SocketAddr {
+	fn remote_addr(&self) -> Option<SocketAddr> {
+		Some(*self)
+	}
+}
+
+impl Connection for &AddrStream {
+	fn remote_addr(&self) -> Option<SocketAddr> {
+		Some(AddrStream::remote_addr(self))
+	}
+}
+
+impl<T: Connection, P> Service<T> for HttpRouter<P> {
+	type Response = RouteHandler<'static, P>;
+	type Error = Infallible;
+	type Future = Ready<Result<Self::Response, Self::Error>>;
+
+	fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, connection: T) -> Self::Future {
+		let mut handler = self.handler();
+		handler.remote_addr = connection.remote_addr();
+		ready(Ok(handler))
+	}
+}
+
+/// Responsible for handling the actual HTTP requests from hyper.
+///
+/// Both this and [HttpRouter](struct.HttpRouter.html) implement `tower::Service`, which hyper
+/// re-exports as `hyper::service::Service`, so tower middleware can be layered around them.
+///
+/// With the `tracing` feature, every request runs in an `INFO` span named `request` with the
+/// method, path and matched route template of the request, its [ID](fn.request_id.html) and
+/// [client address](trait.RequestExt.html#tymethod.client_ip) when they are known, and the
+/// status and latency of the response. An event is logged in the span once the response is
+/// ready, so subscribers get one access log line per request, and errors passed to the
+/// [error handler](struct.HttpRouter.html#method.internal_error_handler) are recorded on the
+/// span and logged, at `ERROR` level for server errors and `DEBUG` level for the rest.
+///
+/// ```
+/// # use grout::{hyper::{service::Service, Body, Method, StatusCode}, path, PathSegment, Request, Response, ResponseBuilder, Router};
+/// # use std::time::Duration;
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let mut router = Router::default().register(Method::GET, path![users], handler).build()?;
+/// let handler = router.call(()).await?;
+/// let mut service = tower::ServiceBuilder::new()
+/// 	.timeout(Duration::from_secs(30))
+/// 	.service(handler);
+///
+/// let req = hyper::Request::get("/users").body(Body::empty())?;
+/// assert_eq!(service.call(req).await?.status(), StatusCode::OK);
+/// # Ok(())
+/// # }
+/// ```
+pub struct RouteHandler<'a, P = Method> {
+	router: Arc<ArcSwap<InnerHttpRouter<'a, P>>>,
+	config: Arc<Config>,
+	remote_addr: Option<SocketAddr>,
+}
+
+impl<'a, P> RouteHandler<'a, P> {
+	/// The same handler for a connection from another address.
+	pub(crate) fn with_remote_addr(mut self, addr: SocketAddr) -> Self {
+		self.remote_addr = Some(addr);
+		self
+	}
+}
+
+impl<'a, P> Clone for RouteHandler<'a, P> {
+	fn clone(&self) -> Self {
+		Self {
+			router: Arc::clone(&self.router),
+			config: Arc::clone(&self.config),
+			remote_addr: self.remote_addr,
+		}
+	}
+}
+
+/// The prefixes a request is routed through, from the most to the least specific.
+fn prefix_chain<P: RequestPrefix>(prefix: P) -> Vec<P> {
+	let mut chain = vec![];
+	let mut prefix = Some(prefix);
+	while let Some(current) = prefix {
+		prefix = current.fallback();
+		chain.push(current);
+	}
+	chain
+}
+
+/// Find the node for a request, falling back through less specific prefixes and then the routes
+/// registered for any prefix until a route matches. A `HEAD` request without a route of its own
+/// is routed to the `GET` route for the path.
+fn find_node<'r, P: RequestPrefix>(
+	router: &'r InnerHttpRouter<'_, P>,
+	req: &Request,
+	path: &'r str,
+) -> (Captures<'r>, Option<&'r RouteNode<'r, Request, Response>>) {
+	let chain = prefix_chain(P::from_request(req));
+	let find = |prefixes: &[P]| {
+		prefixes
+			.iter()
+			.map(|prefix| router.find_prefixed(prefix, path, Some(req)))
+			.find(|found| found.1.is_some())
+	};
+
+	if let Some(found) = find(&chain) {
+		return found;
+	}
+
+	if req.method() == Method::HEAD {
+		let get: Vec<P> = chain
+			.iter()
+			.map(|prefix| prefix.with_method(Method::GET))
+			.collect();
+		if let Some(found) = find(&get) {
+			return found;
+		}
+	}
+
+	router.find_any(path, Some(req))
+}
+
+/// Route a request and start handling it.
+fn dispatch<P: RequestPrefix>(
+	router: &InnerHttpRouter<'static, P>,
+	config: &Config,
+	mut req: Request,
+) -> ResponseFuture {
+	let uri = req.uri().clone();
+	let (params, maybe_node) = find_node(router, &req, uri.path());
+	#[cfg(feature = "tracing")]
+	if let Some(template) = maybe_node.and_then(|node| node.template.as_deref()) {
+		tracing::Span::current().record("route", template);
+	}
+
+	#[cfg(feature = "metrics")]
+	let method = req.method().clone();
+	#[cfg(feature = "metrics")]
+	let observe = |route: Option<&str>, fut: ResponseFuture| match &config.metrics {
+		Some(metrics) => metrics.observe(&method, route, fut),
+		None => fut,
+	};
+
+	if let Some((node, route)) = maybe_node.and_then(|node| Some((node, node.route_for(&req)?))) {
+		let params = params.into_owned().collect::<Params>().with_names(node);
+		req.extensions_mut().insert(params.clone());
+		if let Some(meta) = &node.meta {
+			req.extensions_mut().insert(Arc::clone(meta));
+		}
+		#[cfg(feature = "metrics")]
+		return observe(node.template.as_deref(), route(params, req));
+		#[cfg(not(feature = "metrics"))]
+		return route(params, req);
+	}
+
+	let mut allowed = allowed_methods(router, &req, uri.path());
+	if allowed.is_empty() {
+		if let Some(response) = canonical_redirect(router, &req, &uri) {
+			let fut = respond(response);
+			#[cfg(feature = "metrics")]
+			let fut = observe(None, fut);
+			return fut;
+		}
+	}
+
+	if config.auto_options && !allowed.is_empty() {
+		allowed.push(Method::OPTIONS);
+		allowed.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+		allowed.dedup();
+	}
+
+	let scoped = if allowed.is_empty() {
+		config.scoped_not_found(req.uri().path())
+	} else {
+		None
+	};
+
+	#[cfg(feature = "fs")]
+	if let Some(spa) = config
+		.spa
+		.as_ref()
+		.filter(|spa| allowed.is_empty() && scoped.is_none() && spa.accepts(&req))
+	{
+		return spa.serve(req);
+	}
+
+	let fut = respond(if let Some(not_found) = scoped {
+		not_found(req)
+	} else if allowed.is_empty() {
+		(config.not_found)(req)
+	} else if config.auto_options && req.method() == Method::OPTIONS {
+		options_response(&allowed)
+	} else {
+		(config.method_not_allowed)(req, allowed)
+	});
+	#[cfg(feature = "metrics")]
+	let fut = observe(None, fut);
+	fut
+}
+
+/// Whether a path is under a prefix without a trailing slash, covering whole segments.
+pub(crate) fn is_under(path: &str, prefix: &str) -> bool {
+	path.strip_prefix(prefix)
+		.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// A `308 Permanent Redirect` from a path with a trailing slash to the path without it, keeping
+/// the query string, if the router [redirects](enum.TrailingSlash.html) to canonical paths and
+/// the path without the slash has a route for the request.
+fn canonical_redirect<P: RequestPrefix>(
+	router: &InnerHttpRouter<'_, P>,
+	req: &Request,
+	uri: &Uri,
+) -> Option<hyper::Response<Body>> {
+	if router.trailing_slash_policy() != TrailingSlash::RedirectToCanonical
+		|| !has_trailing_slash(uri.path())
+	{
+		return None;
+	}
+
+	let path = match uri.path().trim_end_matches('/') {
+		"" => "/",
+		path => path,
+	};
+	if find_node(router, req, path).1.is_none() && allowed_methods(router, req, path).is_empty() {
+		return None;
+	}
+
+	let location = match uri.query() {
+		Some(query) => format!("{}?{}", path, query),
+		None => path.to_owned(),
+	};
+	Builder::default()
+		.status(StatusCode::PERMANENT_REDIRECT)
+		.header(LOCATION, location)
+		.body(Body::empty())
+		.ok()
+}
+
+fn respond(response: hyper::Response<Body>) -> ResponseFuture {
+	Box::pin(ready(Ok(response)))
+}
+
+/// The methods with a route for the path of a request, among the prefixes the request would be
+/// routed through if it had that method. The methods are sorted so responses are stable, and
+/// `HEAD` is included whenever `GET` is.
+fn allowed_methods<P: RequestPrefix>(
+	router: &InnerHttpRouter<'_, P>,
+	req: &Request,
+	path: &str,
+) -> Vec<Method> {
+	let chain = prefix_chain(P::from_request(req));
+	let mut allowed: Vec<Method> = router
+		.prefixes_for(path, Some(req))
+		.into_iter()
+		.filter(|candidate| {
+			chain
+				.iter()
+				.any(|prefix| prefix.with_method(candidate.method().clone()) == **candidate)
+		})
+		.map(|candidate| candidate.method().clone())
+		.collect();
+	if allowed.contains(&Method::GET) {
+		allowed.push(Method::HEAD);
+	}
+	allowed.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+	allowed.dedup();
+	allowed
+}
+
+impl<P: RequestPrefix> Service<Request> for RouteHandler<'static, P> {
+	type Response = hyper::Response<Body>;
+	type Error = Infallible;
+	type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+	fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, mut req: Request) -> Self::Future {
+		let in_flight = match self.config.in_flight.as_ref().map(limit::InFlight::acquire) {
+			Some(None) => {
+				let response = status_response(StatusCode::SERVICE_UNAVAILABLE);
+				return Box::pin(ready(Ok(response)));
+			}
+			Some(guard) => guard,
+			None => None,
+		};
+
+		for insert in &self.config.state {
+			insert(req.extensions_mut());
+		}
+		if let Some(addr) = self.remote_addr {
+			let client_ip = ClientIp::resolve(&req, addr.ip(), &self.config.trusted_proxies);
+			req.extensions_mut().insert(addr);
+			req.extensions_mut().insert(client_ip);
+		}
+
+		#[cfg(feature = "tracing")]
+		let span = tracing::info_span!(
+			"request",
+			method = %req.method(),
+			path = req.uri().path(),
+			route = tracing::field::Empty,
+			request_id = tracing::field::Empty,
+			client_ip = tracing::field::Empty,
+			status = tracing::field::Empty,
+			latency = tracing::field::Empty,
+			error = tracing::field::Empty,
+		);
+		#[cfg(feature = "tracing")]
+		if let Some(ClientIp(ip)) = req.extensions().get() {
+			span.record("client_ip", tracing::field::display(ip));
+		}
+
+		let preflight = self
+			.config
+			.cors
+			.as_ref()
+			.filter(|_| self.config.within_limits(req.uri()))
+			.and_then(|cors| cors.preflight(&req));
+		let cors_origin = match (&self.config.cors, &preflight) {
+			(Some(cors), None) => cors.allowed_origin(&req),
+			_ => None,
+		};
+
+		let parts = RequestParts::from(&req);
+		let router = self.router.load_full();
+		req.extensions_mut()
+			.insert(RouteUrls(Arc::clone(router.names())));
+		let req = self.config.limit(req);
+		let config = Arc::clone(&self.config);
+		let fut: Self::Future = Box::pin(async move {
+			let _in_flight = in_flight;
+			let response = match (req, preflight) {
+				(Err(status), _) => Ok(status_response(status)),
+				(Ok(_), Some(response)) => Ok(response),
+				(Ok(req), None) if config.middleware.is_empty() => {
+					dispatch(&router, &config, req).await
+				}
+				(Ok(req), None) => {
+					let endpoint = Arc::clone(&config);
+					let next = Next::new(Arc::clone(&config), move |req| {
+						dispatch(&router, &endpoint, req)
+					});
+					next.run(req).await
+				}
+			};
+
+			let mut response = match response {
+				Ok(response) => response,
+				Err(e) => {
+					#[cfg(feature = "tracing")]
+					traced_error(&e);
+					(config.internal_error)(e, parts.clone()).await
+				}
+			};
+			if let Some(cors) = &config.cors {
+				cors.apply(cors_origin, &mut response);
+			}
+
+			let response = match config.map_response {
+				Some(map) => map(response),
+				None => response,
+			};
+
+			#[cfg(feature = "compression")]
+			let response = match &config.compression {
+				Some(compression) => compression.apply(&parts.headers, response).await,
+				None => response,
+			};
+
+			Ok(if parts.method == Method::HEAD {
+				let (parts, _) = response.into_parts();
+				hyper::Response::from_parts(parts, Body::empty())
+			} else {
+				response
+			})
+		});
+
+		#[cfg(feature = "tracing")]
+		let fut = Box::pin(traced(span, fut));
+
+		fut
+	}
+}
+
+/// Run the response future inside the request span, recording the status and latency once the
+/// response is ready.
+#[cfg(feature = "tracing")]
+async fn traced(
+	span: tracing::Span,
+	fut: impl Future<Output = Result<hyper::Response<Body>, Infallible>>,
+) -> Result<hyper::Response<Body>, Infallible> {
+	use tracing::Instrument;
+
+	let start = std::time::Instant::now();
+	let res = fut.instrument(span.clone()).await;
+	let latency = start.elapsed();
+	span.record("latency", tracing::field::debug(latency));
+	if let Ok(response) = &res {
+		let status = response.status().as_u16();
+		span.record("status", status);
+		tracing::info!(parent: &span, status, ?latency, "finished request");
+	}
+	res
+}
+
+/// Record an error passed to the error handler on the request span, logging server errors as
+/// errors and others, which are usually the fault of the client, at debug level.
+#[cfg(feature = "tracing")]
+fn traced_error(e: &Error) {
+	let span = tracing::Span::current();
+	span.record("error", tracing::field::display(e));
+	if e.status().is_server_error() {
+		tracing::error!(error = %e, status = e.status().as_u16(), "request failed");
+	} else {
+		tracing::debug!(error = %e, status = e.status().as_u16(), "request failed");
+	}
+}
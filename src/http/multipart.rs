@@ -0,0 +1,297 @@
+use crate::{Error, PayloadTooLarge};
+use hyper::{
+	body::{Bytes, HttpBody},
+	header::{HeaderMap, HeaderName, HeaderValue, CONTENT_DISPOSITION, CONTENT_TYPE},
+	Body, StatusCode,
+};
+
+/// The maximum size of the headers of a part.
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+	/// Before the first delimiter.
+	Preamble,
+	/// Right after a delimiter, before either the headers of a part or the end.
+	Delimiter,
+	/// Reading the contents of a part.
+	Part,
+	Done,
+}
+
+/// The parts of a `multipart/form-data` request body, read as they arrive, from
+/// [body::multipart](fn.multipart.html).
+pub struct Multipart {
+	body: Body,
+	/// The delimiter between parts, including the line break before it.
+	delimiter: Vec<u8>,
+	buf: Vec<u8>,
+	state: State,
+	field_limit: Option<u64>,
+	read: u64,
+}
+
+/// A field of a multipart body, whose contents are read in chunks or all at once.
+pub struct Field<'a> {
+	multipart: &'a mut Multipart,
+	headers: HeaderMap,
+	name: Option<String>,
+	file_name: Option<String>,
+}
+
+impl Multipart {
+	pub(super) fn new(body: Body, boundary: &str) -> Self {
+		let mut delimiter = b"\r\n--".to_vec();
+		delimiter.extend_from_slice(boundary.as_bytes());
+		Self {
+			body,
+			delimiter,
+			// The first delimiter has no line break before it, so one is added to find it the same
+			// way as the others.
+			buf: b"\r\n".to_vec(),
+			state: State::Preamble,
+			field_limit: None,
+			read: 0,
+		}
+	}
+
+	/// Limit the size of the contents of each field. Reading a field past the limit fails with
+	/// [PayloadTooLarge](../struct.PayloadTooLarge.html). Fields are unlimited by default, but the
+	/// [body size limit](../struct.HttpRouter.html#method.max_body_size) still applies.
+	pub fn field_limit(mut self, limit: u64) -> Self {
+		self.field_limit = Some(limit);
+		self
+	}
+
+	/// The next field of the body, or `None` after the last one. The rest of the previous field is
+	/// skipped if it wasn't read.
+	pub async fn next_field(&mut self) -> Result<Option<Field<'_>>, Error> {
+		while self.state == State::Part {
+			self.chunk().await?;
+		}
+
+		if self.state == State::Preamble {
+			self.skip_preamble().await?;
+		}
+
+		if self.state == State::Done {
+			return Ok(None);
+		}
+
+		while self.buf.len() < 2 {
+			self.fill().await?;
+		}
+		if self.buf.starts_with(b"--") {
+			self.state = State::Done;
+			return Ok(None);
+		}
+
+		let headers = self.headers().await?;
+		let disposition = headers
+			.get(CONTENT_DISPOSITION)
+			.and_then(|disposition| disposition.to_str().ok())
+			.unwrap_or_default();
+		let name = disposition_param(disposition, "name");
+		let file_name = disposition_param(disposition, "filename");
+
+		self.state = State::Part;
+		self.read = 0;
+		Ok(Some(Field {
+			multipart: self,
+			headers,
+			name,
+			file_name,
+		}))
+	}
+
+	async fn skip_preamble(&mut self) -> Result<(), Error> {
+		loop {
+			if let Some(at) = find(&self.buf, &self.delimiter) {
+				self.buf.drain(..at + self.delimiter.len());
+				self.state = State::Delimiter;
+				return Ok(());
+			}
+
+			let keep = self.buf.len().min(self.delimiter.len() - 1);
+			self.buf.drain(..self.buf.len() - keep);
+			self.fill().await?;
+		}
+	}
+
+	/// Read the headers of a part, which follow the rest of the delimiter line.
+	async fn headers(&mut self) -> Result<HeaderMap, Error> {
+		let end = loop {
+			if let Some(end) = find(&self.buf, b"\r\n\r\n") {
+				break end;
+			}
+			if self.buf.len() > MAX_HEADER_SIZE {
+				return Err(malformed("part headers are too large"));
+			}
+			self.fill().await?;
+		};
+
+		let block: Vec<u8> = self.buf.drain(..end + 4).collect();
+		let mut lines = block[..end].split(|&byte| byte == b'\n');
+		// Anything on the rest of the delimiter line is padding.
+		lines.next();
+
+		let mut headers = HeaderMap::new();
+		for line in lines {
+			let line = line.strip_suffix(b"\r").unwrap_or(line);
+			let colon = line
+				.iter()
+				.position(|&byte| byte == b':')
+				.ok_or_else(|| malformed("invalid part header"))?;
+			let name = HeaderName::from_bytes(&line[..colon])
+				.map_err(|_| malformed("invalid part header name"))?;
+			let value = HeaderValue::from_bytes(trim(&line[colon + 1..]))
+				.map_err(|_| malformed("invalid part header value"))?;
+			headers.append(name, value);
+		}
+
+		Ok(headers)
+	}
+
+	/// The next chunk of the current part, or `None` once it has been read.
+	async fn chunk(&mut self) -> Result<Option<Bytes>, Error> {
+		if self.state != State::Part {
+			return Ok(None);
+		}
+
+		loop {
+			let (len, end) = match find(&self.buf, &self.delimiter) {
+				Some(at) => (at, true),
+				// Everything but what could be the start of a delimiter is part of the contents.
+				None => (
+					self.buf.len().saturating_sub(self.delimiter.len() - 1),
+					false,
+				),
+			};
+
+			if len > 0 || end {
+				let chunk: Bytes = self.buf.drain(..len).collect::<Vec<_>>().into();
+				if end {
+					self.buf.drain(..self.delimiter.len());
+					self.state = State::Delimiter;
+				}
+
+				self.read += len as u64;
+				if let Some(limit) = self.field_limit.filter(|&limit| self.read > limit) {
+					return Err(PayloadTooLarge { limit }.into());
+				}
+
+				if len > 0 {
+					return Ok(Some(chunk));
+				}
+				return Ok(None);
+			}
+
+			self.fill().await?;
+		}
+	}
+
+	async fn fill(&mut self) -> Result<(), Error> {
+		match self.body.data().await {
+			Some(data) => {
+				self.buf.extend_from_slice(&data?);
+				Ok(())
+			}
+			None => Err(malformed("multipart body ended early")),
+		}
+	}
+}
+
+impl Field<'_> {
+	/// The name of the field, from its `Content-Disposition`.
+	pub fn name(&self) -> Option<&str> {
+		self.name.as_deref()
+	}
+
+	/// The name of the uploaded file, for file fields.
+	pub fn file_name(&self) -> Option<&str> {
+		self.file_name.as_deref()
+	}
+
+	/// The `Content-Type` of the field.
+	pub fn content_type(&self) -> Option<&str> {
+		self.headers.get(CONTENT_TYPE)?.to_str().ok()
+	}
+
+	/// All headers of the field.
+	pub fn headers(&self) -> &HeaderMap {
+		&self.headers
+	}
+
+	/// The next chunk of the contents, or `None` once they have been read.
+	pub async fn chunk(&mut self) -> Result<Option<Bytes>, Error> {
+		self.multipart.chunk().await
+	}
+
+	/// Read the rest of the contents.
+	pub async fn bytes(mut self) -> Result<Bytes, Error> {
+		let mut contents = Vec::new();
+		while let Some(chunk) = self.chunk().await? {
+			contents.extend_from_slice(&chunk);
+		}
+		Ok(contents.into())
+	}
+
+	/// Read the rest of the contents as UTF-8 text, which fails with `400 Bad Request` if it isn't.
+	pub async fn text(self) -> Result<String, Error> {
+		String::from_utf8(self.bytes().await?.to_vec())
+			.map_err(|e| Error::new(StatusCode::BAD_REQUEST, e))
+	}
+}
+
+fn malformed(message: &'static str) -> Error {
+	Error::new(StatusCode::BAD_REQUEST, message)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+	haystack
+		.windows(needle.len())
+		.position(|window| window == needle)
+}
+
+fn trim(mut bytes: &[u8]) -> &[u8] {
+	while let [b' ' | b'\t', rest @ ..] = bytes {
+		bytes = rest;
+	}
+	while let [rest @ .., b' ' | b'\t'] = bytes {
+		bytes = rest;
+	}
+	bytes
+}
+
+/// A parameter of a `Content-Disposition` or `Content-Type` header, unquoting it if needed.
+pub(super) fn disposition_param(header: &str, param: &str) -> Option<String> {
+	let mut rest = header.split_once(';')?.1;
+	loop {
+		let (name, after) = rest.split_once('=')?;
+		let after = after.trim_start();
+		let (value, next) = if let Some(quoted) = after.strip_prefix('"') {
+			let mut value = String::new();
+			let mut chars = quoted.char_indices();
+			let end = loop {
+				match chars.next()? {
+					(_, '\\') => value.push(chars.next()?.1),
+					(i, '"') => break i + 1,
+					(_, c) => value.push(c),
+				}
+			};
+			let next = quoted[end..].split_once(';').map(|(_, next)| next);
+			(value, next)
+		} else {
+			let (value, next) = match after.split_once(';') {
+				Some((value, next)) => (value, Some(next)),
+				None => (after, None),
+			};
+			(value.trim().to_owned(), next)
+		};
+
+		if name.trim().eq_ignore_ascii_case(param) {
+			return Some(value);
+		}
+		rest = next?;
+	}
+}
@@ -0,0 +1,129 @@
+use crate::{Error, Request};
+use hyper::{
+	header::{HeaderName, ACCEPT, ACCEPT_LANGUAGE},
+	StatusCode,
+};
+
+/// Pick the representation of a response to send among the media types a handler can produce,
+/// by the q-values of the `Accept` header of the request. Ranges such as `text/*` and `*/*` are
+/// supported, and the most specific range matching a media type decides its q-value. Ties go to
+/// the media type listed first, as does a request without an `Accept` header.
+///
+/// Fails with `406 Not Acceptable` if the request accepts none of the media types.
+///
+/// ```
+/// # use grout::{hyper::{header::{ACCEPT, CONTENT_TYPE}, Body}, negotiate, Request, Response, ResponseBuilder};
+/// async fn user(_params: Vec<String>, req: Request) -> Response {
+/// 	let media_type = negotiate(&req, &["application/json", "text/html"])?;
+/// 	let body = match media_type {
+/// 		"application/json" => r#"{"name":"alice"}"#,
+/// 		_ => "<p>alice</p>",
+/// 	};
+/// 	Ok(ResponseBuilder::default().header(CONTENT_TYPE, media_type).body(Body::from(body))?)
+/// }
+///
+/// let req = hyper::Request::get("/").header(ACCEPT, "text/*, application/json;q=0.5").body(Body::empty()).unwrap();
+/// assert_eq!(negotiate(&req, &["application/json", "text/html"]).unwrap(), "text/html");
+///
+/// let req = hyper::Request::get("/").header(ACCEPT, "image/png").body(Body::empty()).unwrap();
+/// assert_eq!(negotiate(&req, &["application/json"]).unwrap_err().status(), 406);
+/// ```
+pub fn negotiate<'a>(req: &Request, available: &[&'a str]) -> Result<&'a str, Error> {
+	pick(req, ACCEPT, available, |range, media_type| {
+		let media_type = media_type
+			.split(';')
+			.next()
+			.unwrap_or_default()
+			.trim()
+			.to_ascii_lowercase();
+		match range.split_once('/') {
+			_ if range == media_type => Some(3),
+			Some(("*", "*")) => Some(1),
+			Some((kind, "*")) if media_type.split('/').next() == Some(kind) => Some(2),
+			_ => None,
+		}
+	})
+}
+
+/// Pick the language of a response among the language tags a handler can produce, by the
+/// q-values of the `Accept-Language` header of the request. A range matches a tag that is the
+/// same or starts with it and a `-`, so `en` matches `en-GB`, and `*` matches every tag. The most
+/// specific range matching a tag decides its q-value. Ties go to the tag listed first, as does a
+/// request without an `Accept-Language` header.
+///
+/// Fails with `406 Not Acceptable` if the request accepts none of the languages.
+///
+/// ```
+/// # use grout::{hyper::{header::ACCEPT_LANGUAGE, Body}, negotiate_language};
+/// let req = hyper::Request::get("/").header(ACCEPT_LANGUAGE, "fr-CH, fr;q=0.9, en;q=0.8").body(Body::empty()).unwrap();
+/// assert_eq!(negotiate_language(&req, &["en-US", "fr-FR"]).unwrap(), "fr-FR");
+/// ```
+pub fn negotiate_language<'a>(req: &Request, available: &[&'a str]) -> Result<&'a str, Error> {
+	pick(req, ACCEPT_LANGUAGE, available, |range, tag| {
+		let tag = tag.to_ascii_lowercase();
+		if range == "*" {
+			Some(0)
+		} else if tag == range
+			|| (tag.starts_with(range) && tag.as_bytes().get(range.len()) == Some(&b'-'))
+		{
+			Some(range.len())
+		} else {
+			None
+		}
+	})
+}
+
+/// Pick the candidate with the highest q-value, where `specificity` tells whether a range of
+/// the header matches a candidate and how specifically.
+fn pick<'a>(
+	req: &Request,
+	header: HeaderName,
+	available: &[&'a str],
+	specificity: impl Fn(&str, &str) -> Option<usize>,
+) -> Result<&'a str, Error> {
+	// Without the header anything is acceptable.
+	let ranges = quality_values(req, header);
+	let mut best: Option<(&str, f32)> = None;
+	for &candidate in available {
+		let q = if ranges.is_empty() {
+			1.0
+		} else {
+			ranges
+				.iter()
+				.filter_map(|(range, q)| Some((specificity(range, candidate)?, *q)))
+				.max_by_key(|(specificity, _)| *specificity)
+				.map_or(0.0, |(_, q)| q)
+		};
+		if q > 0.0 && best.is_none_or(|(_, best)| q > best) {
+			best = Some((candidate, q));
+		}
+	}
+
+	best.map(|(candidate, _)| candidate).ok_or_else(|| {
+		Error::new(
+			StatusCode::NOT_ACCEPTABLE,
+			"none of the available representations are acceptable",
+		)
+	})
+}
+
+/// The values of a header made of comma separated values with q-values, such as `Accept`, in
+/// the order they are listed. Values are lowercase and without parameters, and values with an
+/// invalid q-value are skipped.
+pub(super) fn quality_values(req: &Request, header: HeaderName) -> Vec<(String, f32)> {
+	req.headers()
+		.get_all(header)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.filter_map(|entry| {
+			let mut params = entry.split(';');
+			let value = params.next()?.trim().to_ascii_lowercase();
+			let q = params
+				.filter_map(|param| param.split_once('='))
+				.find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+				.map_or(Some(1.0), |(_, q)| q.trim().parse::<f32>().ok())?;
+			Some((value, q)).filter(|(value, _)| !value.is_empty())
+		})
+		.collect()
+}
@@ -0,0 +1,296 @@
+//! Generating an [OpenAPI 3](https://spec.openapis.org/oas/v3.0.3) document from the route table.
+//!
+//! Every route registered under a prefix becomes an operation of its path and method, with its
+//! dynamic segments as path parameters. The [metadata](../struct.RouteMeta.html) of a route
+//! fills in the rest: its name is the operation ID, and its description, tags and
+//! authentication schemes are documented as they are. Schemas of request and response bodies
+//! can be added to the metadata with [SchemaExt](trait.SchemaExt.html).
+//!
+//! ```
+//! # use grout::{hyper::{service::Service, Body, Method}, openapi::{OpenApi, SchemaExt}, path, PathSegment, Request, Response, RouteMeta, Router};
+//! # use serde_json::json;
+//! # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let meta = RouteMeta::default()
+//! 	.name("user_detail")
+//! 	.tag("users")
+//! 	.response_schema(json!({ "type": "object", "properties": { "name": { "type": "string" } } }));
+//! let router = Router::default()
+//! 	.register_with(Method::GET, path![users / {id}], meta, handler)
+//! 	.register(Method::POST, path![users], handler);
+//!
+//! let document = OpenApi::new("Users", "1.0.0").document(&router);
+//! assert_eq!(document["paths"]["/users/{id}"]["get"]["operationId"], "user_detail");
+//! assert_eq!(document["paths"]["/users/{id}"]["get"]["parameters"][0]["name"], "id");
+//! assert!(document["paths"]["/users"]["post"].is_object());
+//!
+//! // Serve the document at `/openapi.json`.
+//! let mut router = OpenApi::new("Users", "1.0.0").register(router, Method::GET).build()?;
+//! let mut service = router.call(()).await?;
+//! let req = hyper::Request::get("/openapi.json").body(Body::empty())?;
+//! let res = service.call(req).await?;
+//! let body = hyper::body::to_bytes(res.into_body()).await?;
+//! let served: serde_json::Value = serde_json::from_slice(&body)?;
+//! assert_eq!(served["info"]["title"], "Users");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Routes registered with [register_any](../struct.Router.html#method.register_any) aren't
+//! documented, as OpenAPI has no way to describe an operation for every method. Paths with an
+//! optional segment are documented both with and without it, and a catch-all segment is
+//! documented as a single parameter, which OpenAPI can only describe as one segment.
+
+use super::{InnerHttpRouter, RequestPrefix, ResponseFuture};
+use crate::{Json, PathSegment, Request, RouteMeta, Router};
+use hyper::Method;
+use serde_json::{json, Map, Value};
+use std::{future::ready, sync::Arc};
+
+/// The path the document is served at by [register](struct.OpenApi.html#method.register).
+pub const DOCUMENT_PATH: &str = "openapi.json";
+
+/// The key of [RouteMeta::extra](../struct.RouteMeta.html#structfield.extra) holding the JSON
+/// schema of the request body of a route.
+pub const REQUEST_SCHEMA: &str = "openapi.request_schema";
+
+/// The key of [RouteMeta::extra](../struct.RouteMeta.html#structfield.extra) holding the JSON
+/// schema of the response body of a route.
+pub const RESPONSE_SCHEMA: &str = "openapi.response_schema";
+
+/// Annotate [route metadata](../struct.RouteMeta.html) with the schemas of the JSON bodies of a
+/// route, which are kept in its extra metadata.
+pub trait SchemaExt {
+	fn request_schema(self, schema: Value) -> Self;
+
+	fn response_schema(self, schema: Value) -> Self;
+}
+
+impl SchemaExt for RouteMeta {
+	fn request_schema(self, schema: Value) -> Self {
+		self.with(REQUEST_SCHEMA, schema.to_string())
+	}
+
+	fn response_schema(self, schema: Value) -> Self {
+		self.with(RESPONSE_SCHEMA, schema.to_string())
+	}
+}
+
+/// The information about an API that isn't part of its routes, used to generate its document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenApi {
+	title: String,
+	version: String,
+	description: Option<String>,
+	servers: Vec<String>,
+}
+
+impl OpenApi {
+	/// Describe an API with its title and the version of the API (not of OpenAPI).
+	pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+		Self {
+			title: title.into(),
+			version: version.into(),
+			description: None,
+			servers: vec![],
+		}
+	}
+
+	pub fn description(mut self, description: impl Into<String>) -> Self {
+		self.description = Some(description.into());
+		self
+	}
+
+	/// Add the URL of a server the API is available at.
+	pub fn server(mut self, url: impl Into<String>) -> Self {
+		self.servers.push(url.into());
+		self
+	}
+
+	/// Generate the document of the routes of a router.
+	pub fn document<P, Req, Res>(&self, router: &Router<'_, P, Req, Res>) -> Value
+	where
+		P: RequestPrefix,
+		Req: 'static,
+		Res: 'static,
+	{
+		let mut paths = Map::new();
+		let mut schemes = Map::new();
+		for route in router.routes() {
+			let method = match route.prefix.map(RequestPrefix::method) {
+				Some(method) if is_documented(method) => method.as_str().to_ascii_lowercase(),
+				_ => continue,
+			};
+
+			for (path, parameters) in operation_paths(&route.path) {
+				let item = paths
+					.entry(path)
+					.or_insert_with(|| Value::Object(Map::new()));
+				if item.get(&method).is_some() {
+					continue;
+				}
+
+				let operation = operation(parameters, route.meta, &mut schemes);
+				item[method.as_str()] = operation;
+			}
+		}
+
+		let mut info = json!({ "title": self.title, "version": self.version });
+		if let Some(description) = &self.description {
+			info["description"] = json!(description);
+		}
+
+		let mut document = json!({ "openapi": "3.0.3", "info": info, "paths": paths });
+		if !self.servers.is_empty() {
+			let servers: Vec<Value> = self
+				.servers
+				.iter()
+				.map(|url| json!({ "url": url }))
+				.collect();
+			document["servers"] = json!(servers);
+		}
+		if !schemes.is_empty() {
+			document["components"] = json!({ "securitySchemes": schemes });
+		}
+		document
+	}
+
+	/// Register a handler serving the document of a router at `/openapi.json` under a prefix,
+	/// such as `Method::GET`. The document is generated from the routes registered so far, so this
+	/// should be called last, and doesn't include its own route.
+	pub fn register<'a, P: RequestPrefix>(
+		&self,
+		router: InnerHttpRouter<'a, P>,
+		prefix: P,
+	) -> InnerHttpRouter<'a, P> {
+		let document = self.document(&router);
+		router.register(
+			prefix,
+			vec![PathSegment::Static(DOCUMENT_PATH.into())],
+			serve(document),
+		)
+	}
+}
+
+/// Create a handler that serves a document as JSON.
+pub fn serve(
+	document: Value,
+) -> impl Fn(Vec<String>, Request) -> ResponseFuture + Send + Sync + 'static {
+	let document = Arc::new(document);
+	move |_params, _req| Box::pin(ready(Json(document.as_ref()).into_response()))
+}
+
+/// Whether OpenAPI can describe operations with a method.
+fn is_documented(method: &Method) -> bool {
+	[
+		Method::GET,
+		Method::PUT,
+		Method::POST,
+		Method::DELETE,
+		Method::OPTIONS,
+		Method::HEAD,
+		Method::PATCH,
+		Method::TRACE,
+	]
+	.contains(method)
+}
+
+/// The OpenAPI paths of a route template with the names of their parameters. A template ending
+/// in an optional segment has a path without it as well.
+fn operation_paths(template: &str) -> Vec<(String, Vec<String>)> {
+	let mut path = String::new();
+	let mut parameters = vec![];
+	let mut without = None;
+	for segment in template.split('/').filter(|segment| !segment.is_empty()) {
+		let name = match segment {
+			"_" | "(_)" | "**" => format!("param{}", parameters.len() + 1),
+			"_?" => {
+				without = Some((path.clone(), parameters.clone()));
+				format!("param{}", parameters.len() + 1)
+			}
+			_ => match segment
+				.strip_prefix('{')
+				.and_then(|name| name.strip_suffix('}'))
+			{
+				Some(name) => name.to_owned(),
+				None => {
+					path.push('/');
+					path.push_str(segment);
+					continue;
+				}
+			},
+		};
+		path.push_str("/{");
+		path.push_str(&name);
+		path.push('}');
+		parameters.push(name);
+	}
+
+	let mut paths = vec![(path, parameters)];
+	paths.extend(without);
+	for (path, _) in &mut paths {
+		if path.is_empty() {
+			path.push('/');
+		}
+	}
+	paths
+}
+
+/// The operation of a route, adding the security schemes it requires to `schemes`.
+fn operation(
+	parameters: Vec<String>,
+	meta: Option<&RouteMeta>,
+	schemes: &mut Map<String, Value>,
+) -> Value {
+	let parameters: Vec<Value> = parameters
+		.into_iter()
+		.map(
+			|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }),
+		)
+		.collect();
+	let mut response = json!({ "description": "OK" });
+	let mut operation = json!({ "parameters": parameters });
+
+	if let Some(meta) = meta {
+		if let Some(name) = &meta.name {
+			operation["operationId"] = json!(name);
+		}
+		if let Some(description) = &meta.description {
+			operation["description"] = json!(description);
+		}
+		if !meta.tags.is_empty() {
+			operation["tags"] = json!(meta.tags);
+		}
+		if !meta.auth.is_empty() {
+			let security: Vec<Value> = meta
+				.auth
+				.iter()
+				.map(|scheme| json!({ scheme: [] }))
+				.collect();
+			operation["security"] = json!(security);
+			for scheme in &meta.auth {
+				schemes
+					.entry(scheme.as_str())
+					.or_insert_with(|| json!({ "type": "http", "scheme": scheme }));
+			}
+		}
+		if let Some(schema) = schema(meta, REQUEST_SCHEMA) {
+			operation["requestBody"] = json!({
+				"required": true,
+				"content": { "application/json": { "schema": schema } },
+			});
+		}
+		if let Some(schema) = schema(meta, RESPONSE_SCHEMA) {
+			response["content"] = json!({ "application/json": { "schema": schema } });
+		}
+	}
+
+	operation["responses"] = json!({ "200": response });
+	operation
+}
+
+/// A schema annotated with [SchemaExt](trait.SchemaExt.html), skipping ones that aren't JSON.
+fn schema(meta: &RouteMeta, key: &str) -> Option<Value> {
+	serde_json::from_str(meta.extra.get(key)?).ok()
+}
@@ -0,0 +1,316 @@
+use super::negotiate::quality_values;
+use crate::Request;
+use hyper::{
+	header::{ACCEPT, HOST},
+	Method, Version,
+};
+use std::hash::{Hash, Hasher};
+
+/// The most media types of an `Accept` header that are tried when routing.
+const MAX_ACCEPTED: usize = 16;
+
+/// Derives the key used to select a route tree from an incoming request. The HTTP router is keyed
+/// on the request method unless another prefix type is registered.
+pub trait RequestPrefix: Eq + Hash + Send + Sync + Sized + 'static {
+	/// The most specific prefix for the request.
+	fn from_request(req: &Request) -> Self;
+
+	/// A less specific prefix to try when no route matches under this one.
+	fn fallback(&self) -> Option<Self> {
+		None
+	}
+
+	/// The request method this prefix routes.
+	fn method(&self) -> &Method;
+
+	/// The same prefix for a different method.
+	fn with_method(&self, method: Method) -> Self;
+}
+
+impl RequestPrefix for Method {
+	fn from_request(req: &Request) -> Self {
+		req.method().clone()
+	}
+
+	fn method(&self) -> &Method {
+		self
+	}
+
+	fn with_method(&self, method: Method) -> Self {
+		method
+	}
+}
+
+/// Keys routes on the host a request was addressed to as well as its method. Routes registered
+/// without a host match requests for any host that has no route of its own.
+///
+/// ```
+/// # use grout::{hyper::Method, HostPrefix};
+/// let api = HostPrefix::new("api.example.com", Method::GET);
+/// let fallback = HostPrefix::any(Method::GET);
+/// ```
+///
+/// The host is taken from [request_host](fn.request_host.html), so this works for both
+/// origin-form requests with a `Host` header and the absolute-form requests sent to proxies.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HostPrefix {
+	pub host: Option<String>,
+	pub method: Method,
+}
+
+impl HostPrefix {
+	pub fn new(host: &str, method: Method) -> Self {
+		Self {
+			host: Some(host.to_ascii_lowercase()),
+			method,
+		}
+	}
+
+	pub fn any(method: Method) -> Self {
+		Self { host: None, method }
+	}
+}
+
+impl RequestPrefix for HostPrefix {
+	fn from_request(req: &Request) -> Self {
+		Self {
+			host: request_host(req).map(str::to_ascii_lowercase),
+			method: req.method().clone(),
+		}
+	}
+
+	fn fallback(&self) -> Option<Self> {
+		self.host.as_ref().map(|_| Self::any(self.method.clone()))
+	}
+
+	fn method(&self) -> &Method {
+		&self.method
+	}
+
+	fn with_method(&self, method: Method) -> Self {
+		Self {
+			host: self.host.clone(),
+			method,
+		}
+	}
+}
+
+/// Keys routes on the HTTP version of a request as well as its method, e.g. to serve HTTP/2
+/// clients differently from HTTP/1.1 clients on the same path.
+///
+/// ```
+/// # use grout::{hyper::{Method, Version}, VersionPrefix};
+/// let h2 = VersionPrefix::new(Version::HTTP_2, Method::GET);
+/// let fallback = VersionPrefix::any(Method::GET);
+/// ```
+///
+/// A request is first routed among the routes registered for its version. If none of them match,
+/// it falls back to the routes registered without a version, so only the routes that differ
+/// between versions need to be registered more than once.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VersionPrefix {
+	pub version: Option<Version>,
+	pub method: Method,
+}
+
+impl VersionPrefix {
+	pub fn new(version: Version, method: Method) -> Self {
+		Self {
+			version: Some(version),
+			method,
+		}
+	}
+
+	pub fn any(method: Method) -> Self {
+		Self {
+			version: None,
+			method,
+		}
+	}
+}
+
+impl RequestPrefix for VersionPrefix {
+	fn from_request(req: &Request) -> Self {
+		Self::new(req.version(), req.method().clone())
+	}
+
+	fn fallback(&self) -> Option<Self> {
+		self.version.map(|_| Self::any(self.method.clone()))
+	}
+
+	fn method(&self) -> &Method {
+		&self.method
+	}
+
+	fn with_method(&self, method: Method) -> Self {
+		Self {
+			version: self.version,
+			method,
+		}
+	}
+}
+
+/// Keys routes on a media type the request accepts as well as its method, e.g. to route
+/// `Accept: application/vnd.api+json` to a different handler than `text/html` for the same path.
+///
+/// ```
+/// # use grout::{hyper::{header::ACCEPT, service::Service, Body, Method}, path, AcceptPrefix, HttpRouter, PathSegment, Request, Response, ResponseBuilder, Router};
+/// async fn api(_params: Vec<String>, _req: Request) -> Response {
+/// 	Ok(ResponseBuilder::default().body(Body::from("api"))?)
+/// }
+///
+/// async fn page(_params: Vec<String>, _req: Request) -> Response {
+/// 	Ok(ResponseBuilder::default().body(Body::from("page"))?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let mut router: HttpRouter<AcceptPrefix> = Router::default()
+/// 	.register(AcceptPrefix::new("application/vnd.api+json", Method::GET), path![users], api)
+/// 	.register(AcceptPrefix::any(Method::GET), path![users], page)
+/// 	.build()?;
+///
+/// let mut service = router.call(()).await?;
+/// let req = hyper::Request::get("/users")
+/// 	.header(ACCEPT, "text/html;q=0.9, application/vnd.api+json")
+/// 	.body(Body::empty())?;
+/// let res = service.call(req).await?;
+/// assert_eq!(hyper::body::to_bytes(res.into_body()).await?, "api");
+///
+/// let req = hyper::Request::get("/users").header(ACCEPT, "text/html").body(Body::empty())?;
+/// let res = service.call(req).await?;
+/// assert_eq!(hyper::body::to_bytes(res.into_body()).await?, "page");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A request is routed among the routes registered for each media type of its `Accept` header
+/// in order of preference, by q-value and then the order they are listed in, and then among the
+/// routes registered without a media type. Parameters and wildcards such as `text/*` are ignored,
+/// as are media types the client refuses with `q=0`, and only the first 16 media types are tried.
+#[derive(Debug, Clone)]
+pub struct AcceptPrefix {
+	pub media_type: Option<String>,
+	pub method: Method,
+	/// The less preferred media types to fall back to, which aren't part of the key.
+	rest: Vec<String>,
+}
+
+impl AcceptPrefix {
+	pub fn new(media_type: &str, method: Method) -> Self {
+		Self {
+			media_type: Some(media_type.to_ascii_lowercase()),
+			method,
+			rest: vec![],
+		}
+	}
+
+	pub fn any(method: Method) -> Self {
+		Self {
+			media_type: None,
+			method,
+			rest: vec![],
+		}
+	}
+}
+
+impl PartialEq for AcceptPrefix {
+	fn eq(&self, other: &Self) -> bool {
+		self.media_type == other.media_type && self.method == other.method
+	}
+}
+
+impl Eq for AcceptPrefix {}
+
+impl Hash for AcceptPrefix {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.media_type.hash(state);
+		self.method.hash(state);
+	}
+}
+
+impl RequestPrefix for AcceptPrefix {
+	fn from_request(req: &Request) -> Self {
+		let mut accepted = accepted_media_types(req).into_iter();
+		Self {
+			media_type: accepted.next(),
+			method: req.method().clone(),
+			rest: accepted.collect(),
+		}
+	}
+
+	fn fallback(&self) -> Option<Self> {
+		self.media_type.as_ref()?;
+		let mut rest = self.rest.iter().cloned();
+		Some(Self {
+			media_type: rest.next(),
+			method: self.method.clone(),
+			rest: rest.collect(),
+		})
+	}
+
+	fn method(&self) -> &Method {
+		&self.method
+	}
+
+	fn with_method(&self, method: Method) -> Self {
+		Self {
+			media_type: self.media_type.clone(),
+			method,
+			rest: self.rest.clone(),
+		}
+	}
+}
+
+/// The concrete media types of the `Accept` headers of a request, most preferred first.
+fn accepted_media_types(req: &Request) -> Vec<String> {
+	let mut accepted: Vec<(String, f32)> = quality_values(req, ACCEPT)
+		.into_iter()
+		.filter(|(media_type, q)| *q > 0.0 && !media_type.contains('*'))
+		.take(MAX_ACCEPTED)
+		.collect();
+
+	// The sort is stable, so media types with the same q-value keep the order they are listed in.
+	accepted.sort_by(|a, b| b.1.total_cmp(&a.1));
+	let mut media_types: Vec<String> = vec![];
+	for (media_type, _) in accepted {
+		if !media_types.contains(&media_type) {
+			media_types.push(media_type);
+		}
+	}
+	media_types
+}
+
+/// Determine the host (without port) a request was addressed to.
+///
+/// When the request target is in absolute-form (`GET http://example.com/path`), the authority of
+/// the URI takes precedence over the `Host` header as required by RFC 7230 section 5.4. Otherwise
+/// the `Host` header is used.
+///
+/// ```
+/// # use grout::{hyper::{self, Body}, request_host};
+/// let req = hyper::Request::get("http://example.com/path")
+/// 	.header("Host", "other.example.com:8080")
+/// 	.body(Body::empty())
+/// 	.unwrap();
+/// assert_eq!(request_host(&req), Some("example.com"));
+/// ```
+pub fn request_host(req: &Request) -> Option<&str> {
+	if let Some(authority) = req.uri().authority() {
+		return Some(authority.host());
+	}
+
+	let host = req.headers().get(HOST)?.to_str().ok()?;
+	Some(strip_port(host)).filter(|host| !host.is_empty())
+}
+
+fn strip_port(host: &str) -> &str {
+	if host.starts_with('[') {
+		return host.find(']').map_or(host, |end| &host[..=end]);
+	}
+
+	match host.rfind(':') {
+		Some(idx) if host[idx + 1..].bytes().all(|b| b.is_ascii_digit()) => &host[..idx],
+		_ => host,
+	}
+}
@@ -0,0 +1,122 @@
+use crate::Error;
+use hyper::StatusCode;
+#[cfg(feature = "query")]
+use serde::de::DeserializeOwned;
+
+/// The decoded names and values of a query string, in the order they were given. Read the query of
+/// a request with [query](trait.RequestExt.html#tymethod.query).
+///
+/// ```
+/// # use grout::{hyper::Body, Query, Request, RequestExt, Response, ResponseBuilder};
+/// async fn search(_params: Vec<String>, req: Request) -> Response {
+/// 	let query = req.query()?;
+/// 	let term = query.get("q").unwrap_or_default();
+/// 	let tags: Vec<&str> = query.get_all("tag").collect();
+/// 	Ok(ResponseBuilder::default().body(Body::from(format!("{} {:?}", term, tags)))?)
+/// }
+///
+/// let query = Query::parse("q=rust+router&tag=http&tag=async")?;
+/// assert_eq!(query.get("q"), Some("rust router"));
+/// assert_eq!(query.get_all("tag").collect::<Vec<_>>(), ["http", "async"]);
+/// # Ok::<(), grout::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+	pairs: Vec<(String, String)>,
+}
+
+impl Query {
+	/// Parse a query string, without the leading `?`. Malformed percent-encoding and invalid UTF-8
+	/// fail with `400 Bad Request`.
+	pub fn parse(query: &str) -> Result<Self, Error> {
+		let pairs = decode_pairs(query.as_bytes())
+			.map_err(|message| Error::new(StatusCode::BAD_REQUEST, message))?;
+		Ok(Self { pairs })
+	}
+
+	/// The first value given for a name.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.pairs
+			.iter()
+			.find(|(key, _)| key == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// Every value given for a name.
+	pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.pairs
+			.iter()
+			.filter(move |(key, _)| key == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// Every name and value.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.pairs
+			.iter()
+			.map(|(name, value)| (name.as_str(), value.as_str()))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.pairs.is_empty()
+	}
+
+	/// Deserialize the query into a value. Fields can be strings, numbers, booleans, unit enum
+	/// variants and options of those, and names given more than once deserialize into sequences.
+	/// Failing to deserialize gives `400 Bad Request`.
+	///
+	/// ```
+	/// # use grout::Query;
+	/// #[derive(serde::Deserialize)]
+	/// struct Search {
+	/// 	q: String,
+	/// 	page: Option<u32>,
+	/// 	#[serde(default)]
+	/// 	tag: Vec<String>,
+	/// }
+	///
+	/// let search: Search = Query::parse("q=grout&page=2&tag=a&tag=b")?.deserialize()?;
+	/// assert_eq!(search.page, Some(2));
+	/// assert_eq!(search.tag, ["a", "b"]);
+	/// # Ok::<(), grout::Error>(())
+	/// ```
+	#[cfg(feature = "query")]
+	pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, Error> {
+		super::urlencoded::from_pairs(self.pairs.clone())
+			.map_err(|e| Error::new(StatusCode::BAD_REQUEST, e))
+	}
+}
+
+/// Decode urlencoded data, such as a query string or a form body, into names and values.
+pub(crate) fn decode_pairs(data: &[u8]) -> Result<Vec<(String, String)>, &'static str> {
+	data.split(|&byte| byte == b'&')
+		.filter(|pair| !pair.is_empty())
+		.map(|pair| {
+			let mut parts = pair.splitn(2, |&byte| byte == b'=');
+			let name = decode(parts.next().unwrap_or_default())?;
+			let value = decode(parts.next().unwrap_or_default())?;
+			Ok((name, value))
+		})
+		.collect()
+}
+
+/// Decode a urlencoded name or value, where `+` is a space.
+fn decode(data: &[u8]) -> Result<String, &'static str> {
+	let mut decoded = Vec::with_capacity(data.len());
+	let mut bytes = data.iter();
+	while let Some(&byte) = bytes.next() {
+		decoded.push(match byte {
+			b'+' => b' ',
+			b'%' => {
+				let mut hex = || bytes.next().and_then(|&digit| (digit as char).to_digit(16));
+				match (hex(), hex()) {
+					(Some(high), Some(low)) => (high * 16 + low) as u8,
+					_ => return Err("invalid percent-encoding"),
+				}
+			}
+			byte => byte,
+		});
+	}
+
+	String::from_utf8(decoded).map_err(|_| "invalid UTF-8")
+}
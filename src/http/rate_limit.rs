@@ -0,0 +1,136 @@
+use super::{status_response, Next, ResponseFuture};
+use crate::Request;
+use hyper::{header::RETRY_AFTER, StatusCode};
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+const MIN_PRUNE_LEN: usize = 1024;
+
+/// Middleware that limits how often each client can make requests, answering requests over the
+/// limit with `429 Too Many Requests` and a `Retry-After` header. Register it with
+/// [middleware](struct.HttpRouter.html#method.middleware) to reject requests before they are
+/// routed, or attach it to single routes with [with](trait.HandlerExt.html#tymethod.with).
+///
+/// Clients are told apart by the key extracted from each request, such as an API key header or an
+/// address set by a proxy; requests without a key aren't limited. Every key has a bucket of
+/// `requests` tokens that refills over `per`, and each request takes a token, so clients can
+/// burst up to `requests` at once. Panics if either is zero.
+///
+/// ```
+/// # use grout::{hyper::{service::Service, Body, Method, StatusCode}, path, rate_limit, PathSegment, Request, Response, ResponseBuilder, Router};
+/// # use std::time::Duration;
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let api_key = |req: &Request| {
+/// 	let key = req.headers().get("x-api-key")?;
+/// 	Some(key.to_str().ok()?.to_owned())
+/// };
+///
+/// let mut router = Router::default()
+/// 	.register(Method::GET, path![], handler)
+/// 	.build()?
+/// 	.middleware(rate_limit(1, Duration::from_secs(60), api_key));
+///
+/// let mut service = router.call(()).await?;
+/// let req = || hyper::Request::get("/").header("x-api-key", "abc").body(Body::empty());
+/// assert_eq!(service.call(req()?).await?.status(), StatusCode::OK);
+/// let res = service.call(req()?).await?;
+/// assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+/// assert_eq!(res.headers()["retry-after"], "60");
+/// # Ok(())
+/// # }
+/// ```
+pub fn rate_limit<K>(
+	requests: u32,
+	per: Duration,
+	key: K,
+) -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static
+where
+	K: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+{
+	assert!(requests > 0, "rate limit must allow at least one request");
+	assert!(
+		per > Duration::from_secs(0),
+		"rate limit period must not be zero"
+	);
+	let limiter = Arc::new(Limiter {
+		capacity: f64::from(requests),
+		refill: f64::from(requests) / per.as_secs_f64(),
+		buckets: Mutex::new(Buckets {
+			buckets: HashMap::new(),
+			prune_at: MIN_PRUNE_LEN,
+		}),
+	});
+	let key = Arc::new(key);
+
+	move |req, next| {
+		let wait = key(&req).and_then(|key| limiter.acquire(key));
+		match wait {
+			None => next.run(req),
+			Some(wait) => {
+				let mut response = status_response(StatusCode::TOO_MANY_REQUESTS);
+				let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+				response.headers_mut().insert(RETRY_AFTER, seconds.into());
+				Box::pin(async { Ok(response) })
+			}
+		}
+	}
+}
+
+struct Limiter {
+	capacity: f64,
+	/// Tokens added per second.
+	refill: f64,
+	buckets: Mutex<Buckets>,
+}
+
+struct Buckets {
+	buckets: HashMap<String, Bucket>,
+	/// The number of buckets at which full ones are dropped, which keeps clients that have gone
+	/// away from using memory.
+	prune_at: usize,
+}
+
+struct Bucket {
+	tokens: f64,
+	updated: Instant,
+}
+
+impl Limiter {
+	/// Take a token for a key, or return how long until one is available.
+	fn acquire(&self, key: String) -> Option<Duration> {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+		if buckets.buckets.len() >= buckets.prune_at {
+			buckets
+				.buckets
+				.retain(|_, bucket| self.tokens(bucket, now) < self.capacity);
+			buckets.prune_at = MIN_PRUNE_LEN.max(buckets.buckets.len() * 2);
+		}
+
+		let capacity = self.capacity;
+		let bucket = buckets.buckets.entry(key).or_insert(Bucket {
+			tokens: capacity,
+			updated: now,
+		});
+		bucket.tokens = self.tokens(bucket, now);
+		bucket.updated = now;
+		if bucket.tokens >= 1.0 {
+			bucket.tokens -= 1.0;
+			None
+		} else {
+			Some(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill))
+		}
+	}
+
+	fn tokens(&self, bucket: &Bucket, now: Instant) -> f64 {
+		let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+		(bucket.tokens + elapsed * self.refill).min(self.capacity)
+	}
+}
@@ -0,0 +1,137 @@
+use super::{Next, RequestParts, ResponseFuture};
+use crate::Request;
+use hyper::header::{HeaderName, HeaderValue};
+use std::{
+	collections::hash_map::RandomState,
+	hash::{BuildHasher, Hasher},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+};
+
+/// The header request IDs are read from and sent in.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The longest request ID that is accepted from a request.
+const MAX_LENGTH: usize = 200;
+
+/// The ID of a request, set by the [request_id](fn.request_id.html) middleware and read with
+/// [request_id](trait.RequestExt.html#tymethod.request_id).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+}
+
+/// Middleware that gives every request an ID, so the logs of the services a request passes
+/// through can be correlated. The ID is taken from the `X-Request-Id` header of the request if
+/// it has a valid one, as set by a proxy or the service that sent it, and is otherwise
+/// generated. It is stored in the extensions of the request, set in its `X-Request-Id` header
+/// and sent back in the `X-Request-Id` header of the response.
+///
+/// Register it with [middleware](struct.HttpRouter.html#method.middleware) before other
+/// middleware, so errors are passed to the
+/// [error handler](struct.HttpRouter.html#method.internal_error_handler) with the ID in the
+/// headers of the request and their responses have the ID too.
+///
+/// ```
+/// # use grout::{hyper::{service::Service, Body, Method}, path, request_id, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+/// async fn handler(_params: Vec<String>, req: Request) -> Response {
+/// 	let id = req.request_id().unwrap_or_default().to_owned();
+/// 	Ok(ResponseBuilder::default().body(Body::from(id))?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let mut router = Router::default()
+/// 	.register(Method::GET, path![], handler)
+/// 	.build()?
+/// 	.middleware(request_id());
+///
+/// let mut service = router.call(()).await?;
+/// let req = hyper::Request::get("/").header("x-request-id", "abc-123").body(Body::empty())?;
+/// let res = service.call(req).await?;
+/// assert_eq!(res.headers()["x-request-id"], "abc-123");
+/// assert_eq!(hyper::body::to_bytes(res.into_body()).await?, "abc-123");
+///
+/// let res = service.call(Request::new(Body::empty())).await?;
+/// assert_eq!(res.headers()["x-request-id"].len(), 32);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// IDs from requests are accepted if they are up to 200 visible ASCII characters. Generated IDs
+/// are 32 hexadecimal characters, unique within the process and unpredictable across processes.
+pub fn request_id() -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static {
+	let generator = Arc::new(Generator::default());
+
+	move |mut req, next| {
+		let id = req
+			.headers()
+			.get(REQUEST_ID_HEADER)
+			.filter(|id| is_valid(id))
+			.cloned()
+			.unwrap_or_else(|| generator.request_id());
+		let header = HeaderName::from_static(REQUEST_ID_HEADER);
+		req.headers_mut().insert(header.clone(), id.clone());
+		let text = id.to_str().unwrap_or_default().to_owned();
+		#[cfg(feature = "tracing")]
+		tracing::Span::current().record("request_id", text.as_str());
+		req.extensions_mut().insert(RequestId(text));
+
+		let error_handler = next
+			.error_handler()
+			.map(|handler| (handler, RequestParts::from(&req)));
+		Box::pin(async move {
+			let mut response = match (next.run(req).await, error_handler) {
+				(Ok(response), _) => response,
+				(Err(e), Some((handler, parts))) => handler(e, parts).await,
+				(Err(e), None) => return Err(e),
+			};
+			response.headers_mut().insert(header, id);
+			Ok(response)
+		})
+	}
+}
+
+fn is_valid(id: &HeaderValue) -> bool {
+	(1..=MAX_LENGTH).contains(&id.len()) && id.as_bytes().iter().all(u8::is_ascii_graphic)
+}
+
+/// Generates IDs that are unique within the process, from a counter hashed with random keys.
+pub(super) struct Generator {
+	keys: (RandomState, RandomState),
+	counter: AtomicU64,
+}
+
+impl Default for Generator {
+	fn default() -> Self {
+		Self {
+			keys: (RandomState::new(), RandomState::new()),
+			counter: AtomicU64::new(0),
+		}
+	}
+}
+
+impl Generator {
+	/// The next 128 bit ID.
+	pub(super) fn next(&self) -> u128 {
+		let count = self.counter.fetch_add(1, Ordering::Relaxed);
+		let hash = |key: &RandomState| {
+			let mut hasher = key.build_hasher();
+			hasher.write_u64(count);
+			hasher.finish()
+		};
+
+		u128::from(hash(&self.keys.0)) << 64 | u128::from(hash(&self.keys.1))
+	}
+
+	fn request_id(&self) -> HeaderValue {
+		let id = format!("{:032x}", self.next());
+		HeaderValue::from_str(&id).expect("hex is a valid header value")
+	}
+}
@@ -0,0 +1,53 @@
+use crate::{HttpRouter, RequestPrefix};
+use hyper::Server;
+use std::{error::Error, future::Future, net::SocketAddr, time::Duration};
+use tokio::{sync::oneshot, time::timeout};
+
+/// Serve a router on an address until a shutdown signal resolves, then stop accepting
+/// connections and wait for the requests in flight to finish.
+///
+/// With a drain deadline, requests still in flight when the deadline passes are abandoned and an
+/// error is returned; the connections are closed once the runtime shuts down. Without one, this
+/// waits for as long as the requests take.
+///
+/// ```no_run
+/// # use grout::{hyper::{Body, Method}, path, serve_with_shutdown, PathSegment, Request, Response, ResponseBuilder, Router};
+/// # use std::time::Duration;
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// # }
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// 	let router = Router::default().register(Method::GET, path![], handler).build()?;
+/// 	let signal = async {
+/// 		tokio::signal::ctrl_c().await.ok();
+/// 	};
+/// 	let addr = ([127, 0, 0, 1], 3000).into();
+/// 	serve_with_shutdown(addr, router, signal, Some(Duration::from_secs(30))).await
+/// }
+/// ```
+pub async fn serve_with_shutdown<P: RequestPrefix>(
+	addr: SocketAddr,
+	router: HttpRouter<P>,
+	signal: impl Future<Output = ()> + Send + 'static,
+	drain: Option<Duration>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let (signalled, on_signal) = oneshot::channel();
+	let server = Server::try_bind(&addr)?
+		.serve(router)
+		.with_graceful_shutdown(async move {
+			signal.await;
+			signalled.send(()).ok();
+		});
+	let mut server = tokio::spawn(server);
+
+	// The sender is dropped without sending if the server stops before the signal.
+	if let (Ok(()), Some(drain)) = (on_signal.await, drain) {
+		return match timeout(drain, &mut server).await {
+			Ok(result) => Ok(result??),
+			Err(_) => Err("requests did not finish before the drain deadline".into()),
+		};
+	}
+
+	Ok(server.await??)
+}
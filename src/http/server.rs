@@ -0,0 +1,116 @@
+use crate::{HttpRouter, RequestPrefix};
+use hyper::{server::Builder, Server};
+use std::{error::Error, net::SocketAddr};
+
+/// The HTTP versions a server speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+	/// HTTP/1 and HTTP/2. Over cleartext, HTTP/2 clients must use prior knowledge (h2c); over
+	/// TLS, they negotiate it through ALPN. This is the default.
+	#[default]
+	Auto,
+	/// HTTP/1 only.
+	Http1,
+	/// HTTP/2 only, which over cleartext means every client must use prior knowledge.
+	Http2,
+}
+
+/// Connection settings for serving a router, for when the defaults of hyper don't fit, such as
+/// for multiplexed or gRPC-adjacent traffic. Settings left unset keep the defaults of hyper.
+///
+/// ```no_run
+/// # use grout::{hyper::{Body, Method}, path, PathSegment, Protocol, Request, Response, ResponseBuilder, Router, ServerOptions};
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// # }
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// 	let router = Router::default().register(Method::POST, path![rpc / _], handler).build()?;
+/// 	ServerOptions::default()
+/// 		.protocol(Protocol::Http2)
+/// 		.max_concurrent_streams(256)
+/// 		.initial_stream_window_size(1 << 20)
+/// 		.serve(([127, 0, 0, 1], 50051).into(), router)
+/// 		.await
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+	protocol: Protocol,
+	max_concurrent_streams: Option<u32>,
+	initial_stream_window_size: Option<u32>,
+	initial_connection_window_size: Option<u32>,
+	adaptive_window: bool,
+}
+
+impl ServerOptions {
+	pub fn protocol(mut self, protocol: Protocol) -> Self {
+		self.protocol = protocol;
+		self
+	}
+
+	/// Set the maximum number of concurrent HTTP/2 streams per connection.
+	pub fn max_concurrent_streams(mut self, max: u32) -> Self {
+		self.max_concurrent_streams = Some(max);
+		self
+	}
+
+	/// Set the initial HTTP/2 flow control window of each stream, in bytes.
+	pub fn initial_stream_window_size(mut self, size: u32) -> Self {
+		self.initial_stream_window_size = Some(size);
+		self
+	}
+
+	/// Set the initial HTTP/2 flow control window of each connection, in bytes.
+	pub fn initial_connection_window_size(mut self, size: u32) -> Self {
+		self.initial_connection_window_size = Some(size);
+		self
+	}
+
+	/// Size the HTTP/2 flow control windows from measurements of the connection, overriding the
+	/// initial window sizes.
+	pub fn adaptive_window(mut self, enabled: bool) -> Self {
+		self.adaptive_window = enabled;
+		self
+	}
+
+	/// Serve a router on an address with these settings.
+	pub async fn serve<P: RequestPrefix>(
+		&self,
+		addr: SocketAddr,
+		router: HttpRouter<P>,
+	) -> Result<(), Box<dyn Error + Send + Sync>> {
+		self.builder(Server::try_bind(&addr)?).serve(router).await?;
+		Ok(())
+	}
+
+	fn builder<I>(&self, builder: Builder<I>) -> Builder<I> {
+		// Each of these resets the other, so only the one in effect is set.
+		let builder = match self.protocol {
+			Protocol::Auto => builder,
+			Protocol::Http1 => builder.http1_only(true),
+			Protocol::Http2 => builder.http2_only(true),
+		};
+		builder
+			.http2_max_concurrent_streams(self.max_concurrent_streams)
+			.http2_initial_stream_window_size(self.initial_stream_window_size)
+			.http2_initial_connection_window_size(self.initial_connection_window_size)
+			.http2_adaptive_window(self.adaptive_window)
+	}
+
+	/// The settings for serving individual connections.
+	#[cfg(feature = "tls")]
+	pub(crate) fn http(&self) -> hyper::server::conn::Http {
+		let mut http = hyper::server::conn::Http::new();
+		match self.protocol {
+			Protocol::Auto => &mut http,
+			Protocol::Http1 => http.http1_only(true),
+			Protocol::Http2 => http.http2_only(true),
+		}
+		.http2_max_concurrent_streams(self.max_concurrent_streams)
+		.http2_initial_stream_window_size(self.initial_stream_window_size)
+		.http2_initial_connection_window_size(self.initial_connection_window_size)
+		.http2_adaptive_window(self.adaptive_window);
+		http
+	}
+}
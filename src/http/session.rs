@@ -0,0 +1,369 @@
+//! Sessions kept in a store and identified by a signed cookie.
+//!
+//! The [Sessions](struct.Sessions.html) middleware loads the session of every request into its
+//! extensions, where handlers read and change it through [Session](struct.Session.html). Once
+//! the response is ready, changed sessions are saved to the store and new ones get a cookie. If
+//! the handler fails, destroyed and regenerated sessions are still removed from the store, but
+//! other changes are not saved.
+//!
+//! ```
+//! # use grout::{hyper::{header::{COOKIE, SET_COOKIE}, service::Service, Body, Method}, path, session::{MemoryStore, Sessions}, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+//! async fn count(_params: Vec<String>, req: Request) -> Response {
+//! 	let session = req.session().unwrap();
+//! 	let visits: u32 = session.get("visits").and_then(|v| v.parse().ok()).unwrap_or(0) + 1;
+//! 	session.insert("visits", visits.to_string());
+//! 	Ok(ResponseBuilder::default().body(Body::from(visits.to_string()))?)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! // Use a long, random secret kept out of the source in production.
+//! let sessions = Sessions::new(MemoryStore::default(), b"a secret of at least 32 bytes....");
+//! let mut router = Router::default()
+//! 	.register(Method::GET, path![], count)
+//! 	.build()?
+//! 	.middleware(sessions.middleware());
+//!
+//! let mut service = router.call(()).await?;
+//! let res = service.call(Request::new(Body::empty())).await?;
+//! let cookie = res.headers()[SET_COOKIE].to_str()?;
+//! assert!(cookie.starts_with("session=") && cookie.contains("HttpOnly"));
+//!
+//! let id = cookie.split(';').next().unwrap().to_owned();
+//! let req = hyper::Request::builder().header(COOKIE, id).body(Body::empty())?;
+//! let res = service.call(req).await?;
+//! let body = hyper::body::to_bytes(res.into_body()).await?;
+//! assert_eq!(body, "2");
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+	cookies::{Cookie, CookieJar, SameSite},
+	token::{self, hex},
+	Next, ResponseFuture,
+};
+use crate::{Error, Request};
+use hyper::header::SET_COOKIE;
+use ring::hmac;
+use std::{
+	collections::HashMap,
+	fmt::{self, Debug, Formatter},
+	future::Future,
+	pin::Pin,
+	sync::{Arc, Mutex, MutexGuard},
+	time::Duration,
+};
+
+/// The data of a session.
+pub type SessionData = HashMap<String, String>;
+
+/// The future returned by the methods of a [SessionStore](trait.SessionStore.html).
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'a>>;
+
+/// The default name of the session cookie.
+pub const DEFAULT_COOKIE_NAME: &str = "session";
+
+/// Where sessions are kept between requests, keyed by their ID. Errors fail the request they
+/// happen in and reach the [error handler](../struct.HttpRouter.html#method.internal_error_handler).
+pub trait SessionStore: Send + Sync + 'static {
+	/// Load a session, or `None` if there is no session with the ID.
+	fn load<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<SessionData>>;
+
+	/// Save a session, replacing any earlier data.
+	fn save<'a>(&'a self, id: &'a str, data: SessionData) -> StoreFuture<'a, ()>;
+
+	/// Remove a session.
+	fn remove<'a>(&'a self, id: &'a str) -> StoreFuture<'a, ()>;
+}
+
+/// A [SessionStore](trait.SessionStore.html) that keeps sessions in memory, for development and
+/// tests. Sessions are lost when the server stops and are only removed when they are destroyed,
+/// so abandoned sessions use memory for as long as the server runs.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+	sessions: Mutex<HashMap<String, SessionData>>,
+}
+
+impl MemoryStore {
+	fn sessions(&self) -> MutexGuard<'_, HashMap<String, SessionData>> {
+		self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl SessionStore for MemoryStore {
+	fn load<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<SessionData>> {
+		let data = self.sessions().get(id).cloned();
+		Box::pin(async move { Ok(data) })
+	}
+
+	fn save<'a>(&'a self, id: &'a str, data: SessionData) -> StoreFuture<'a, ()> {
+		self.sessions().insert(id.to_owned(), data);
+		Box::pin(async { Ok(()) })
+	}
+
+	fn remove<'a>(&'a self, id: &'a str) -> StoreFuture<'a, ()> {
+		self.sessions().remove(id);
+		Box::pin(async { Ok(()) })
+	}
+}
+
+/// The session of a request, read from its extensions with
+/// [session](../trait.RequestExt.html#tymethod.session). Clones share the session, so changes
+/// made through any of them are saved.
+#[derive(Clone, Default)]
+pub struct Session {
+	inner: Arc<Mutex<SessionState>>,
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+	id: Option<String>,
+	data: SessionData,
+	changed: bool,
+	destroyed: bool,
+	regenerated: bool,
+}
+
+impl Session {
+	fn state(&self) -> MutexGuard<'_, SessionState> {
+		self.inner.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// The ID of the session, which new sessions only get once they are saved.
+	pub fn id(&self) -> Option<String> {
+		self.state().id.clone()
+	}
+
+	pub fn get(&self, key: &str) -> Option<String> {
+		self.state().data.get(key).cloned()
+	}
+
+	pub fn insert(&self, key: impl Into<String>, value: impl Into<String>) {
+		let mut state = self.state();
+		state.data.insert(key.into(), value.into());
+		state.changed = true;
+	}
+
+	pub fn remove(&self, key: &str) -> Option<String> {
+		let mut state = self.state();
+		state.changed = true;
+		state.data.remove(key)
+	}
+
+	/// A copy of all of the data in the session.
+	pub fn data(&self) -> SessionData {
+		self.state().data.clone()
+	}
+
+	/// Remove the session from the store and the browser, such as when logging out.
+	pub fn destroy(&self) {
+		let mut state = self.state();
+		state.data.clear();
+		state.destroyed = true;
+	}
+
+	/// Move the session to a new ID, keeping its data, such as when logging in. This keeps an
+	/// attacker who planted a session ID in the browser from sharing the session.
+	pub fn regenerate(&self) {
+		let mut state = self.state();
+		state.regenerated = true;
+		state.changed = true;
+	}
+}
+
+impl Debug for Session {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		// The ID is left out, as it is as good as a password.
+		f.debug_struct("Session")
+			.field("data", &self.state().data)
+			.finish()
+	}
+}
+
+/// Session middleware settings, turned into middleware with [middleware](#method.middleware).
+///
+/// The session cookie holds the ID of the session signed with HMAC-SHA256, so IDs that weren't
+/// issued by the server are ignored. It is `HttpOnly`, `Secure` and `SameSite=Lax` with a `Path`
+/// of `/` by default, and is removed when the browser closes unless a
+/// [max age](#method.max_age) is set.
+pub struct Sessions {
+	store: Arc<dyn SessionStore>,
+	key: hmac::Key,
+	cookie_name: String,
+	path: String,
+	domain: Option<String>,
+	max_age: Option<Duration>,
+	same_site: SameSite,
+	secure: bool,
+}
+
+impl Sessions {
+	/// Keep sessions in a store, signing their cookies with a secret key. The key should be at
+	/// least 32 random bytes, and changing it invalidates every session.
+	pub fn new(store: impl SessionStore, secret: &[u8]) -> Self {
+		Self {
+			store: Arc::new(store),
+			key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+			cookie_name: DEFAULT_COOKIE_NAME.to_owned(),
+			path: "/".to_owned(),
+			domain: None,
+			max_age: None,
+			same_site: SameSite::Lax,
+			secure: true,
+		}
+	}
+
+	pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+		self.cookie_name = name.into();
+		self
+	}
+
+	pub fn path(mut self, path: impl Into<String>) -> Self {
+		self.path = path.into();
+		self
+	}
+
+	pub fn domain(mut self, domain: impl Into<String>) -> Self {
+		self.domain = Some(domain.into());
+		self
+	}
+
+	/// Keep the session cookie for this long, instead of until the browser closes. The store
+	/// decides how long the sessions themselves are kept.
+	pub fn max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = Some(max_age);
+		self
+	}
+
+	pub fn same_site(mut self, same_site: SameSite) -> Self {
+		self.same_site = same_site;
+		self
+	}
+
+	/// Set whether the cookie is only sent over HTTPS, which it is by default. Browsers don't
+	/// send `Secure` cookies to plain HTTP servers other than `localhost`.
+	pub fn secure(mut self, secure: bool) -> Self {
+		self.secure = secure;
+		self
+	}
+
+	/// The middleware, to register with [middleware](../struct.HttpRouter.html#method.middleware).
+	pub fn middleware(
+		self,
+	) -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static {
+		let sessions = Arc::new(self);
+		move |req, next| {
+			let sessions = Arc::clone(&sessions);
+			Box::pin(async move { sessions.handle(req, next).await })
+		}
+	}
+
+	async fn handle(&self, mut req: Request, next: Next) -> crate::Response {
+		let id = CookieJar::from_headers(req.headers())
+			.get(&self.cookie_name)
+			.and_then(|cookie| self.verify(cookie));
+		let loaded = match &id {
+			Some(id) => self.store.load(id).await?,
+			None => None,
+		};
+
+		let session = Session::default();
+		if let Some(data) = loaded {
+			let mut state = session.state();
+			state.id = id;
+			state.data = data;
+		}
+		req.extensions_mut().insert(session.clone());
+
+		let result = next.run(req).await;
+		let (old_id, new_id, data) = {
+			let mut state = session.state();
+			let old_id = state.id.clone();
+			if state.destroyed {
+				(old_id, None, None)
+			} else if result.is_err() {
+				// The cookie of a new ID can't be sent with an error, so a regenerated session is
+				// dropped, which still retires the old ID. Other changes are discarded.
+				if !state.regenerated {
+					return result;
+				}
+				(old_id, None, None)
+			} else if state.changed {
+				if state.id.is_none() || state.regenerated {
+					state.id = Some(token::random()?);
+				}
+				(old_id, state.id.clone(), Some(state.data.clone()))
+			} else {
+				return result;
+			}
+		};
+
+		if let Some(old_id) = old_id.as_ref().filter(|old| Some(*old) != new_id.as_ref()) {
+			self.store.remove(old_id).await?;
+		}
+		let mut response = result?;
+
+		let cookie = match (new_id, data) {
+			(Some(new_id), Some(data)) => {
+				self.store.save(&new_id, data).await?;
+				let cookie = Cookie::new(self.cookie_name.as_str(), self.sign(&new_id));
+				match self.max_age {
+					Some(max_age) => cookie.max_age(max_age),
+					None => cookie,
+				}
+			}
+			_ => Cookie::removal(self.cookie_name.as_str()),
+		};
+
+		let mut cookie = cookie
+			.path(self.path.as_str())
+			.same_site(self.same_site)
+			.secure(self.secure)
+			.http_only(true);
+		if let Some(domain) = &self.domain {
+			cookie = cookie.domain(domain.as_str());
+		}
+		response
+			.headers_mut()
+			.append(SET_COOKIE, cookie.header_value()?);
+		Ok(response)
+	}
+
+	fn sign(&self, id: &str) -> String {
+		let tag = hmac::sign(&self.key, id.as_bytes());
+		format!("{}.{}", id, hex(tag.as_ref()))
+	}
+
+	/// The session ID in a cookie, if its signature is valid.
+	fn verify(&self, cookie: &str) -> Option<String> {
+		let (id, signature) = cookie.split_once('.')?;
+		let signature = unhex(signature)?;
+		hmac::verify(&self.key, id.as_bytes(), &signature).ok()?;
+		Some(id.to_owned())
+	}
+}
+
+impl Debug for Sessions {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Sessions")
+			.field("cookie_name", &self.cookie_name)
+			.field("path", &self.path)
+			.field("domain", &self.domain)
+			.field("max_age", &self.max_age)
+			.field("same_site", &self.same_site)
+			.field("secure", &self.secure)
+			.finish()
+	}
+}
+
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+	if hex.len() % 2 != 0 {
+		return None;
+	}
+
+	(0..hex.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+		.collect()
+}
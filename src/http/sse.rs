@@ -0,0 +1,187 @@
+//! Server-sent events: a response body that streams events to the client as they are produced.
+//!
+//! ```
+//! # use grout::{hyper::{Body, Method}, path, sse::{Event, EventStream}, PathSegment, Request, Response, Router};
+//! use std::time::Duration;
+//! use tokio::stream::StreamExt;
+//!
+//! async fn ticks(_params: Vec<String>, _req: Request) -> Response {
+//! 	let events = tokio::time::interval(Duration::from_secs(1))
+//! 		.map(|_| Event::default().event("tick").data("it's been a second"));
+//! 	Ok(EventStream::new(events).into())
+//! }
+//!
+//! let router = Router::default().register(Method::GET, path![ticks], ticks);
+//! # let _ = router.build();
+//! ```
+
+use futures_core::Stream;
+use hyper::{
+	body::Bytes,
+	header::{CACHE_CONTROL, CONTENT_TYPE},
+	http::response::Builder,
+	Body,
+};
+use std::{
+	convert::Infallible,
+	fmt::{self, Display, Formatter},
+	future::Future,
+	pin::Pin,
+	task::{Context, Poll},
+	time::Duration,
+};
+use tokio::time::{delay_for, Delay, Instant};
+
+/// The default interval between keep-alive comments.
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A single event. Its fields are sent in the order of the event stream format, so the `Display`
+/// implementation writes the event as it will appear on the wire.
+///
+/// Line breaks in the data are sent as separate `data` lines, which clients join back together.
+/// The other fields can't span lines, so line breaks are removed from them.
+///
+/// ```
+/// # use grout::sse::Event;
+/// let event = Event::default().event("update").id("7").data("first\nsecond");
+/// assert_eq!(event.to_string(), "event: update\nid: 7\ndata: first\ndata: second\n\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+	comment: Option<String>,
+	event: Option<String>,
+	id: Option<String>,
+	retry: Option<Duration>,
+	data: Option<String>,
+}
+
+impl Event {
+	/// Set the data of the event.
+	pub fn data(mut self, data: impl Into<String>) -> Self {
+		self.data = Some(data.into());
+		self
+	}
+
+	/// Set the type of the event, which clients dispatch on. Without one, the event is a
+	/// `message`.
+	pub fn event(mut self, event: impl Into<String>) -> Self {
+		self.event = Some(single_line(event.into()));
+		self
+	}
+
+	/// Set the ID of the event, which clients send back in `Last-Event-ID` when reconnecting.
+	pub fn id(mut self, id: impl Into<String>) -> Self {
+		self.id = Some(single_line(id.into()));
+		self
+	}
+
+	/// Set how long clients should wait before reconnecting if the stream is closed.
+	pub fn retry(mut self, retry: Duration) -> Self {
+		self.retry = Some(retry);
+		self
+	}
+
+	/// Set a comment, which clients ignore.
+	pub fn comment(mut self, comment: impl Into<String>) -> Self {
+		self.comment = Some(single_line(comment.into()));
+		self
+	}
+}
+
+fn single_line(mut field: String) -> String {
+	field.retain(|c| c != '\n' && c != '\r');
+	field
+}
+
+impl Display for Event {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		if let Some(comment) = &self.comment {
+			writeln!(f, ":{}", comment)?;
+		}
+		if let Some(event) = &self.event {
+			writeln!(f, "event: {}", event)?;
+		}
+		if let Some(id) = &self.id {
+			writeln!(f, "id: {}", id)?;
+		}
+		if let Some(retry) = self.retry {
+			writeln!(f, "retry: {}", retry.as_millis())?;
+		}
+		if let Some(data) = &self.data {
+			for line in data.lines() {
+				writeln!(f, "data: {}", line)?;
+			}
+		}
+		writeln!(f)
+	}
+}
+
+/// A stream of events to send as a response. Converting it into a response sets
+/// `Content-Type: text/event-stream` and turns off caching.
+///
+/// While no event is ready, a comment is sent every
+/// [DEFAULT_KEEP_ALIVE](constant.DEFAULT_KEEP_ALIVE.html) so that proxies don't close the
+/// connection as idle. The stream must be polled within a tokio runtime with the timer enabled
+/// unless keep-alive is turned off.
+pub struct EventStream {
+	events: Pin<Box<dyn Stream<Item = Event> + Send>>,
+	keep_alive: Option<Duration>,
+	delay: Option<Delay>,
+}
+
+impl EventStream {
+	pub fn new(events: impl Stream<Item = Event> + Send + 'static) -> Self {
+		Self {
+			events: Box::pin(events),
+			keep_alive: Some(DEFAULT_KEEP_ALIVE),
+			delay: None,
+		}
+	}
+
+	/// Set the interval between keep-alive comments, or turn them off with `None`.
+	pub fn keep_alive(mut self, interval: Option<Duration>) -> Self {
+		self.keep_alive = interval;
+		self
+	}
+}
+
+impl Stream for EventStream {
+	type Item = Result<Bytes, Infallible>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+		match this.events.as_mut().poll_next(cx) {
+			Poll::Ready(Some(event)) => {
+				if let (Some(delay), Some(interval)) = (&mut this.delay, this.keep_alive) {
+					delay.reset(Instant::now() + interval);
+				}
+				return Poll::Ready(Some(Ok(event.to_string().into())));
+			}
+			Poll::Ready(None) => return Poll::Ready(None),
+			Poll::Pending => {}
+		}
+
+		let interval = match this.keep_alive {
+			Some(interval) => interval,
+			None => return Poll::Pending,
+		};
+		let delay = this.delay.get_or_insert_with(|| delay_for(interval));
+		match Pin::new(&mut *delay).poll(cx) {
+			Poll::Ready(()) => {
+				delay.reset(Instant::now() + interval);
+				Poll::Ready(Some(Ok(Bytes::from_static(b":\n\n"))))
+			}
+			Poll::Pending => Poll::Pending,
+		}
+	}
+}
+
+impl From<EventStream> for hyper::Response<Body> {
+	fn from(events: EventStream) -> Self {
+		Builder::default()
+			.header(CONTENT_TYPE, "text/event-stream")
+			.header(CACHE_CONTROL, "no-cache")
+			.body(Body::wrap_stream(events))
+			.unwrap()
+	}
+}
@@ -0,0 +1,78 @@
+//! Sending requests to a router in tests, without binding a socket.
+//!
+//! ```
+//! # use grout::{hyper::{Body, Method, StatusCode}, path, test::TestClient, PathSegment, Request, Response, ResponseBuilder, Router};
+//! async fn hello(_params: Vec<String>, _req: Request) -> Response {
+//! 	Ok(ResponseBuilder::default().body(Body::from("hello"))?)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let router = Router::default().register(Method::GET, path![hello], hello).build()?;
+//! let client = TestClient::new(router);
+//!
+//! let res = client.get("/hello").await?;
+//! assert_eq!(res.status(), StatusCode::OK);
+//! assert_eq!(res.body(), "hello");
+//!
+//! let req = hyper::Request::delete("/hello").body(Body::empty())?;
+//! assert_eq!(client.send(req).await?.status(), StatusCode::METHOD_NOT_ALLOWED);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{HttpRouter, RequestPrefix, RouteHandler};
+use crate::Request;
+use hyper::{body::Bytes, service::Service, Body};
+use std::net::SocketAddr;
+
+/// Sends requests straight to the [RouteHandler](../struct.RouteHandler.html) of a router, as
+/// if they had come from a connection, and collects the bodies of the responses. Requests go
+/// through the same middleware, limits and error handlers as requests from a server.
+pub struct TestClient<P = hyper::Method> {
+	handler: RouteHandler<'static, P>,
+}
+
+impl<P: RequestPrefix> TestClient<P> {
+	pub fn new(router: HttpRouter<P>) -> Self {
+		Self {
+			handler: router.handler(),
+		}
+	}
+
+	/// Send the requests as if they came from a client at an address, which handlers read with
+	/// [remote_addr](../trait.RequestExt.html#tymethod.remote_addr).
+	pub fn remote_addr(mut self, addr: SocketAddr) -> Self {
+		self.handler = self.handler.with_remote_addr(addr);
+		self
+	}
+
+	/// Send a request, returning the response with its whole body. This only fails if the body
+	/// of the response fails while it is read, such as when a streamed body errors.
+	pub async fn send(&self, req: Request) -> Result<hyper::Response<Bytes>, hyper::Error> {
+		let mut handler = self.handler.clone();
+		let response = match handler.call(req).await {
+			Ok(response) => response,
+			Err(never) => match never {},
+		};
+
+		let (parts, body) = response.into_parts();
+		let body = hyper::body::to_bytes(body).await?;
+		Ok(hyper::Response::from_parts(parts, body))
+	}
+
+	/// Send a `GET` request for a URI, such as `/users?page=2`.
+	pub async fn get(&self, uri: &str) -> Result<hyper::Response<Bytes>, hyper::Error> {
+		let mut req = Request::new(Body::empty());
+		*req.uri_mut() = uri.parse().expect("invalid URI");
+		self.send(req).await
+	}
+}
+
+impl<P> Clone for TestClient<P> {
+	fn clone(&self) -> Self {
+		Self {
+			handler: self.handler.clone(),
+		}
+	}
+}
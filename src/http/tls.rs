@@ -0,0 +1,78 @@
+use crate::{HttpRouter, RequestPrefix, RouteHandler, ServerOptions};
+use hyper::{
+	server::conn::Http,
+	service::{service_fn, Service},
+};
+use std::{error::Error, net::SocketAddr, sync::Arc};
+use tokio::net::{TcpListener, TcpStream};
+pub use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
+
+/// Serve a router over TLS on an address, using a rustls server configuration. The server runs
+/// until accepting a connection fails.
+///
+/// Every connection is handled on its own task, so a slow handshake doesn't hold up other
+/// clients, and connections whose handshake fails are dropped. HTTP/2 is served to clients that
+/// negotiate it if the configuration advertises `h2` through ALPN.
+///
+/// ```no_run
+/// # use grout::{hyper::{Body, Method}, path, rustls::{NoClientAuth, ServerConfig}, serve_tls, PathSegment, Request, Response, ResponseBuilder, Router};
+/// # use std::sync::Arc;
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+/// # 	Ok(ResponseBuilder::default().body(Body::empty())?)
+/// # }
+/// # fn load_certificates() -> (Vec<grout::rustls::Certificate>, grout::rustls::PrivateKey) { unimplemented!() }
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// 	let (certificates, key) = load_certificates();
+/// 	let mut config = ServerConfig::new(NoClientAuth::new());
+/// 	config.set_single_cert(certificates, key)?;
+///
+/// 	let router = Router::default().register(Method::GET, path![], handler).build()?;
+/// 	serve_tls(([0, 0, 0, 0], 443).into(), Arc::new(config), router).await
+/// }
+/// ```
+pub async fn serve_tls<P: RequestPrefix>(
+	addr: SocketAddr,
+	config: Arc<rustls::ServerConfig>,
+	router: HttpRouter<P>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+	ServerOptions::default()
+		.serve_tls(addr, config, router)
+		.await
+}
+
+impl ServerOptions {
+	/// Serve a router over TLS with these settings, like [serve_tls](fn.serve_tls.html).
+	pub async fn serve_tls<P: RequestPrefix>(
+		&self,
+		addr: SocketAddr,
+		config: Arc<rustls::ServerConfig>,
+		router: HttpRouter<P>,
+	) -> Result<(), Box<dyn Error + Send + Sync>> {
+		let acceptor = TlsAcceptor::from(config);
+		let http = self.http();
+		let mut listener = TcpListener::bind(addr).await?;
+
+		loop {
+			let (stream, addr) = listener.accept().await?;
+			let acceptor = acceptor.clone();
+			let handler = router.handler().with_remote_addr(addr);
+			tokio::spawn(serve_connection(http.clone(), acceptor, stream, handler));
+		}
+	}
+}
+
+async fn serve_connection<P: RequestPrefix>(
+	http: Http,
+	acceptor: TlsAcceptor,
+	stream: TcpStream,
+	handler: RouteHandler<'static, P>,
+) {
+	if let Ok(stream) = acceptor.accept(stream).await {
+		// Calling the handler through a closure keeps the spawned future free of a higher-ranked
+		// lifetime bound on its service impl, which the compiler can't prove.
+		let service = service_fn(move |req| handler.clone().call(req));
+		http.serve_connection(stream, service).await.ok();
+	}
+}
@@ -0,0 +1,19 @@
+use crate::Error;
+use hyper::StatusCode;
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// A new random token of 32 bytes, hex encoded, for session IDs and CSRF tokens.
+pub(super) fn random() -> Result<String, Error> {
+	let mut token = [0; 32];
+	SystemRandom::new().fill(&mut token).map_err(|_| {
+		Error::new(
+			StatusCode::INTERNAL_SERVER_ERROR,
+			"failed to generate a random token",
+		)
+	})?;
+	Ok(hex(&token))
+}
+
+pub(super) fn hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
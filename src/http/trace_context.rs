@@ -0,0 +1,201 @@
+use super::{request_id::Generator, Next, ResponseFuture};
+use crate::Request;
+use hyper::header::HeaderValue;
+use std::{
+	fmt::{self, Display, Formatter},
+	sync::Arc,
+};
+
+/// The header the trace context of a request is read from and sent to other services in.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// The header a trace carries vendor-specific data in, which is passed on as it is.
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+/// The flag of a trace that its caller may have recorded it.
+const SAMPLED: u8 = 0x01;
+
+/// The [W3C trace context](https://www.w3.org/TR/trace-context/) of a request, set by the
+/// [trace_context](fn.trace_context.html) middleware and read with
+/// [trace_context](trait.RequestExt.html#tymethod.trace_context).
+///
+/// The context is the server span of the request within its trace. Send
+/// [traceparent](#method.traceparent) and [tracestate](#method.tracestate) with the requests a
+/// handler makes to other services so they continue the trace as children of this span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+	trace_id: u128,
+	span_id: u64,
+	parent_id: Option<u64>,
+	flags: u8,
+	tracestate: Option<HeaderValue>,
+}
+
+impl TraceContext {
+	/// The ID of the trace, which is new if the request didn't continue one.
+	pub fn trace_id(&self) -> String {
+		format!("{:032x}", self.trace_id)
+	}
+
+	/// The ID of the server span of the request.
+	pub fn span_id(&self) -> String {
+		format!("{:016x}", self.span_id)
+	}
+
+	/// The ID of the span of the caller, if the request continued a trace.
+	pub fn parent_id(&self) -> Option<String> {
+		self.parent_id.map(|id| format!("{:016x}", id))
+	}
+
+	/// Whether the caller may have recorded the trace. New traces are sampled.
+	pub fn sampled(&self) -> bool {
+		self.flags & SAMPLED != 0
+	}
+
+	/// The `traceparent` header for requests to other services, with the server span of this
+	/// request as their parent.
+	pub fn traceparent(&self) -> HeaderValue {
+		HeaderValue::from_str(&self.to_string()).expect("hex is a valid header value")
+	}
+
+	/// The `tracestate` header of the request, to send on with the `traceparent` header.
+	pub fn tracestate(&self) -> Option<&HeaderValue> {
+		self.tracestate.as_ref()
+	}
+
+	/// Continue the trace of a request, under a new span ID.
+	fn from_request(req: &Request, generator: &Generator) -> Self {
+		let span_id = new_span_id(generator);
+		let parent = req
+			.headers()
+			.get(TRACEPARENT_HEADER)
+			.and_then(|header| header.to_str().ok())
+			.and_then(parse_traceparent);
+		match parent {
+			Some((trace_id, parent_id, flags)) => Self {
+				trace_id,
+				span_id,
+				parent_id: Some(parent_id),
+				flags,
+				tracestate: req.headers().get(TRACESTATE_HEADER).cloned(),
+			},
+			None => Self {
+				trace_id: new_trace_id(generator),
+				span_id,
+				parent_id: None,
+				flags: SAMPLED,
+				tracestate: None,
+			},
+		}
+	}
+}
+
+/// Writes the value of a `traceparent` header, like [traceparent](#method.traceparent).
+impl Display for TraceContext {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"00-{:032x}-{:016x}-{:02x}",
+			self.trace_id, self.span_id, self.flags
+		)
+	}
+}
+
+/// Middleware that continues the [W3C trace context](https://www.w3.org/TR/trace-context/) of
+/// requests, or starts a new trace for requests without a valid `traceparent` header, and stores
+/// the [TraceContext](struct.TraceContext.html) of the server span of each request in its
+/// extensions. Register it with [middleware](struct.HttpRouter.html#method.middleware).
+///
+/// With the `tracing` feature, the rest of the request runs in an `INFO` span named `server`
+/// with the `trace_id`, `span_id` and `parent_id` of the context, so subscribers that export
+/// traces can link their spans to the trace.
+///
+/// ```
+/// # use grout::{hyper::{service::Service, Body, Method}, path, trace_context, PathSegment, Request, RequestExt, Response, ResponseBuilder, Router};
+/// async fn handler(_params: Vec<String>, req: Request) -> Response {
+/// 	let context = req.trace_context().unwrap();
+/// 	// Send `context.traceparent()` with requests to other services.
+/// 	Ok(ResponseBuilder::default().body(Body::from(context.trace_id()))?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let mut router = Router::default()
+/// 	.register(Method::GET, path![], handler)
+/// 	.build()?
+/// 	.middleware(trace_context());
+///
+/// let mut service = router.call(()).await?;
+/// let req = hyper::Request::get("/")
+/// 	.header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+/// 	.body(Body::empty())?;
+/// let res = service.call(req).await?;
+/// assert_eq!(hyper::body::to_bytes(res.into_body()).await?, "4bf92f3577b34da6a3ce929d0e0e4736");
+/// # Ok(())
+/// # }
+/// ```
+pub fn trace_context() -> impl Fn(Request, Next) -> ResponseFuture + Clone + Send + Sync + 'static {
+	let generator = Arc::new(Generator::default());
+	move |mut req, next| {
+		let context = TraceContext::from_request(&req, &generator);
+		#[cfg(feature = "tracing")]
+		let span = tracing::info_span!(
+			"server",
+			trace_id = %context.trace_id(),
+			span_id = %context.span_id(),
+			parent_id = context.parent_id().as_deref(),
+		);
+		req.extensions_mut().insert(context);
+
+		let fut = next.run(req);
+		#[cfg(feature = "tracing")]
+		let fut = Box::pin(tracing::Instrument::instrument(fut, span));
+		fut
+	}
+}
+
+// IDs of zero are invalid, so they are skipped.
+fn new_trace_id(generator: &Generator) -> u128 {
+	loop {
+		if let id @ 1.. = generator.next() {
+			return id;
+		}
+	}
+}
+
+fn new_span_id(generator: &Generator) -> u64 {
+	loop {
+		if let id @ 1.. = generator.next() as u64 {
+			return id;
+		}
+	}
+}
+
+/// The trace ID, parent ID and flags of a `traceparent` header. Headers of later versions are
+/// read like version `00`, ignoring what follows the fields it has.
+fn parse_traceparent(header: &str) -> Option<(u128, u64, u8)> {
+	let mut fields = header.trim().split('-');
+	let version = fields.next().filter(|version| is_hex(version, 2))?;
+	let trace_id = fields.next().filter(|id| is_hex(id, 32))?;
+	let parent_id = fields.next().filter(|id| is_hex(id, 16))?;
+	let flags = fields.next().filter(|flags| is_hex(flags, 2))?;
+	if version == "ff" || (version == "00" && fields.next().is_some()) {
+		return None;
+	}
+
+	let trace_id = u128::from_str_radix(trace_id, 16)
+		.ok()
+		.filter(|id| *id != 0)?;
+	let parent_id = u64::from_str_radix(parent_id, 16)
+		.ok()
+		.filter(|id| *id != 0)?;
+	let flags = u8::from_str_radix(flags, 16).ok()?;
+	Some((trace_id, parent_id, flags))
+}
+
+fn is_hex(field: &str, len: usize) -> bool {
+	field.len() == len
+		&& field
+			.bytes()
+			.all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
@@ -0,0 +1,59 @@
+use crate::{HttpRouter, RequestPrefix};
+use hyper::{
+	server::{accept, Server},
+	service::{make_service_fn, service_fn, Service},
+};
+use std::{convert::Infallible, error::Error, path::Path};
+use tokio::net::{unix::UCred, UnixListener, UnixStream};
+
+/// Serve a router over a Unix domain socket bound at the given path. The socket file must not
+/// already exist.
+///
+/// The credentials of the connected peer are inserted into the extensions of every request as a
+/// [UCred](https://docs.rs/tokio/0.2/tokio/net/unix/struct.UCred.html), which can be used to
+/// authorize local clients:
+///
+/// ```no_run
+/// # use grout::{hyper::{Body, Method}, path, serve_unix, PathSegment, Request, Response, ResponseBuilder, Router};
+/// use tokio::net::unix::UCred;
+///
+/// async fn handler(_params: Vec<String>, req: Request) -> Response {
+/// 	let uid = req.extensions().get::<UCred>().map(|cred| cred.uid);
+/// 	Ok(ResponseBuilder::default().body(format!("{:?}", uid).into())?)
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// 	let router = Router::default().register(Method::GET, path![], handler).build()?;
+/// 	serve_unix("/tmp/grout.sock", router).await
+/// }
+/// ```
+pub async fn serve_unix<P: RequestPrefix>(
+	path: impl AsRef<Path>,
+	router: HttpRouter<P>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let mut listener = UnixListener::bind(path)?;
+
+	let make_service = make_service_fn(move |stream: &UnixStream| {
+		let credentials: Option<UCred> = stream.peer_cred().ok();
+		let handler = router.handler();
+
+		async move {
+			Ok::<_, Infallible>(service_fn(move |mut req| {
+				if let Some(credentials) = credentials {
+					req.extensions_mut().insert(credentials);
+				}
+
+				handler.clone().call(req)
+			}))
+		}
+	});
+
+	Server::builder(accept::from_stream(listener.incoming()))
+		.serve(make_service)
+		.await?;
+	Ok(())
+}
+
+/// Another name for [serve_unix](fn.serve_unix.html), which is also enabled by the `uds` feature.
+pub use serve_unix as serve_uds;
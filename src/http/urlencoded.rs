@@ -0,0 +1,223 @@
+//! Deserializing `application/x-www-form-urlencoded` data into flat structs and maps.
+
+use serde::{
+	de::{
+		value::{Error, MapDeserializer, SeqDeserializer, StringDeserializer},
+		DeserializeOwned, Deserializer, Error as _, IntoDeserializer, Visitor,
+	},
+	forward_to_deserialize_any,
+};
+use std::{collections::HashMap, str::FromStr};
+
+/// Deserialize urlencoded data, such as a form body.
+#[cfg(feature = "form")]
+pub(crate) fn from_bytes<T: DeserializeOwned>(data: &[u8]) -> Result<T, Error> {
+	from_pairs(super::query::decode_pairs(data).map_err(Error::custom)?)
+}
+
+/// Deserialize decoded names and values. The values of names given more than once are collected
+/// in the order they were given, so they can deserialize into sequences.
+pub(crate) fn from_pairs<T: DeserializeOwned>(pairs: Vec<(String, String)>) -> Result<T, Error> {
+	let mut fields: Vec<(Part, Values)> = Vec::new();
+	let mut indices: HashMap<String, usize> = HashMap::new();
+	for (name, value) in pairs {
+		match indices.get(&name) {
+			Some(&index) => {
+				let (_, Values(values)) = &mut fields[index];
+				values.push(value);
+			}
+			None => {
+				indices.insert(name.clone(), fields.len());
+				fields.push((Part(name), Values(vec![value])));
+			}
+		}
+	}
+
+	T::deserialize(MapDeserializer::new(fields.into_iter()))
+}
+
+/// A decoded name or value, which deserializes into strings and anything that parses from them.
+struct Part(String);
+
+impl Part {
+	fn parse<T: FromStr>(self) -> Result<T, Error>
+	where
+		T::Err: std::fmt::Display,
+	{
+		self.0
+			.parse()
+			.map_err(|e| Error::custom(format_args!("invalid value {:?}: {}", self.0, e)))
+	}
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Part {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self {
+		self
+	}
+}
+
+macro_rules! deserialize_parsed {
+	($($method:ident => $visit:ident,)*) => {
+		$(
+			fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+				visitor.$visit(self.parse()?)
+			}
+		)*
+	};
+}
+
+impl<'de> Deserializer<'de> for Part {
+	type Error = Error;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_string(self.0)
+	}
+
+	deserialize_parsed! {
+		deserialize_bool => visit_bool,
+		deserialize_i8 => visit_i8,
+		deserialize_i16 => visit_i16,
+		deserialize_i32 => visit_i32,
+		deserialize_i64 => visit_i64,
+		deserialize_u8 => visit_u8,
+		deserialize_u16 => visit_u16,
+		deserialize_u32 => visit_u32,
+		deserialize_u64 => visit_u64,
+		deserialize_f32 => visit_f32,
+		deserialize_f64 => visit_f64,
+		deserialize_char => visit_char,
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_some(self)
+	}
+
+	fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_unit()
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		_variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		let variant: StringDeserializer<Error> = self.0.into_deserializer();
+		visitor.visit_enum(variant)
+	}
+
+	forward_to_deserialize_any! {
+		i128 u128 str string bytes byte_buf unit_struct seq tuple tuple_struct map struct
+		identifier ignored_any
+	}
+}
+
+/// The values given for a name, which deserialize into sequences, or like a single
+/// part if there is only one.
+struct Values(Vec<String>);
+
+impl Values {
+	fn single(mut self) -> Result<Part, Error> {
+		match self.0.len() {
+			1 => Ok(Part(self.0.remove(0))),
+			_ => Err(Error::custom("value given more than once")),
+		}
+	}
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Values {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self {
+		self
+	}
+}
+
+macro_rules! deserialize_single {
+	($($method:ident,)*) => {
+		$(
+			fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+				self.single()?.$method(visitor)
+			}
+		)*
+	};
+}
+
+impl<'de> Deserializer<'de> for Values {
+	type Error = Error;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match self.0.len() {
+			1 => self.single()?.deserialize_any(visitor),
+			_ => self.deserialize_seq(visitor),
+		}
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let mut seq = SeqDeserializer::new(self.0.into_iter().map(Part));
+		let value = visitor.visit_seq(&mut seq)?;
+		seq.end()?;
+		Ok(value)
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(
+		self,
+		_len: usize,
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_some(self)
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		name: &'static str,
+		variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Error> {
+		self.single()?.deserialize_enum(name, variants, visitor)
+	}
+
+	deserialize_single! {
+		deserialize_bool,
+		deserialize_i8,
+		deserialize_i16,
+		deserialize_i32,
+		deserialize_i64,
+		deserialize_u8,
+		deserialize_u16,
+		deserialize_u32,
+		deserialize_u64,
+		deserialize_f32,
+		deserialize_f64,
+		deserialize_char,
+		deserialize_str,
+		deserialize_string,
+		deserialize_unit,
+	}
+
+	forward_to_deserialize_any! {
+		i128 u128 bytes byte_buf unit_struct tuple_struct map struct identifier ignored_any
+	}
+}
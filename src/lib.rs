@@ -1,20 +1,23 @@
 //! A dead simple hash-based HTTP router built on hyper.
 //!
-//! ```
-//! use grout::{path, Body, Method, Request, Response, ResponseBuilder, RouterBuilder, Server};
+//! ```no_run
+//! use grout::{
+//! 	hyper::{Body, Method, Server},
+//! 	path, Request, Response, ResponseBuilder, Router,
+//! };
 //!
-//! async fn handler(params: Vec<String>, _req: Request) -> Response {
+//! async fn handler(_params: Vec<String>, _req: Request) -> Response {
 //! 	Ok(ResponseBuilder::default().body(Body::empty())?)
 //! }
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 //! 	let addr = ([127, 0, 0, 1], 3000).into();
-//! 	let router = RouterBuilder::default()
+//! 	let router = Router::default()
 //! 		.register(Method::GET, path![], handler)
 //! 		.register(Method::GET, path![foo / _], handler)
 //! 		.register(Method::POST, path![foo / _], handler)
-//! 		.build();
+//! 		.build()?;
 //!
 //! 	let server = Server::bind(&addr).serve(router);
 //! 	println!("Listening on http://{}", addr);
@@ -25,11 +28,19 @@
 //! ```
 //!
 //! Path segments denoted with a `_` are matched dynamically if no other static segment matches.
-//! Dynamic segments are passed into the route handler as the first parameter. Only one route can
-//! match any given request.
+//! Dynamic segments are passed into the route handler as the first parameter. They are also stored
+//! in the request extensions as `Params`, readable through `RequestExt::params`, which is how
+//! handlers registered with `Router::handle` receive them. Segments written as `{name}` are matched
+//! the same way and can also be read by name with `Params::named`. Only one route can match any
+//! given request. Routes registered with `Router::register_guarded` only match requests that pass
+//! their guard, and other requests fall through to the remaining candidates.
 //!
-//! The router builder exposes `internal_error_handler` and `not_found_handler` which can handle
-//! errors returned from handlers and unmatched requests respectively.
+//! The HTTP router exposes `internal_error_handler` and `not_found_handler` which can handle
+//! errors returned from handlers and unmatched requests respectively. Requests for a path that
+//! only has routes under other methods are answered with `405 Method Not Allowed` instead, which
+//! `method_not_allowed_handler` can customize.
+
+#![allow(clippy::tabs_in_doc_comments)]
 
 #[cfg(feature = "http")]
 mod http;
@@ -46,5 +57,9 @@ pub mod route;
 /// Use the RouterBuilder to create a Router: pass the router to hyper as the service.
 pub mod router;
 
+/// Building routers from route descriptions in configuration files.
+#[cfg(feature = "config")]
+pub mod config;
+
 pub use route::*;
 pub use router::*;
@@ -1,4 +1,16 @@
-use std::{future::Future, pin::Pin};
+use crate::RouteNode;
+use std::{
+	borrow::Cow,
+	collections::BTreeMap,
+	error::Error,
+	fmt::{self, Display, Formatter},
+	future::Future,
+	iter::FromIterator,
+	ops::Deref,
+	pin::Pin,
+	str::FromStr,
+	sync::Arc,
+};
 
 /// A route path is just a vec of [PathSegment](enum.PathSegment.html)s.
 ///
@@ -7,43 +19,429 @@ pub type Path<'a> = Vec<PathSegment<'a>>;
 
 /// Create a [Path](route/type.Path.html) with simplified syntax.
 /// ```
-/// path![foo / _ / bar / _] // -> vec![Static("foo"), Dynamic, Static("bar"), Dynamic]
+/// # use grout::{path, PathSegment::{self, *}};
+/// assert_eq!(path![foo / _ / bar / _], vec![Static("foo".into()), Dynamic, Static("bar".into()), Dynamic]);
+/// assert_eq!(path![users / {id}], vec![Static("users".into()), Named("id".into())]);
+/// assert_eq!(path![assets / **], vec![Static("assets".into()), CatchAll]);
+/// assert_eq!(path![users / _?], vec![Static("users".into()), Optional]);
+/// # fn numeric(segment: &str) -> bool { segment.bytes().all(|b| b.is_ascii_digit()) }
+/// assert_eq!(path![users / (numeric)], vec![Static("users".into()), Constrained(numeric)]);
 /// ```
 #[macro_export]
 macro_rules! path {
 	[] => { vec![] };
 	[ @single _ ] => {
-		PathSegment::Dynamic
+		$crate::PathSegment::Dynamic
+	};
+	[ @single ( $matcher:expr ) ] => {
+		$crate::PathSegment::Constrained($matcher)
+	};
+	[ @single { $name:ident } ] => {
+		$crate::PathSegment::Named(::std::borrow::Cow::Borrowed(stringify!($name)))
 	};
 	[ @single $first:tt ] => {
-		PathSegment::Static(stringify!($first))
+		$crate::PathSegment::Static(::std::borrow::Cow::Borrowed(stringify!($first)))
+	};
+	[ @munch [ $($done:expr),* ] _ ? ] => {
+		vec![$($done,)* $crate::PathSegment::Optional]
+	};
+	[ @munch [ $($done:expr),* ] * * ] => {
+		vec![$($done,)* $crate::PathSegment::CatchAll]
+	};
+	[ @munch [ $($done:expr),* ] $segment:tt ] => {
+		vec![$($done,)* $crate::path![@single $segment]]
 	};
-	[ $($segment:tt) / * ] => {
-		vec![$(path![@single $segment]), *]
+	[ @munch [ $($done:expr),* ] $segment:tt / $($rest:tt)+ ] => {
+		$crate::path![@munch [$($done,)* $crate::path![@single $segment]] $($rest)+]
+	};
+	[ $($segment:tt)+ ] => {
+		$crate::path![@munch [] $($segment)+]
 	};
 }
 
 /// Path segments are matched during routing. Static segments are matched through hash equality.
 /// If no static segments match, a corresponding dynamic segment is attempted. For example:
 /// `GET /foo/bar` matches `vec![Static("foo"), Dynamic]` instead of `vec![Dynamic, Dynamic]`.
+/// A router can reverse this with [Precedence::DynamicFirst](enum.Precedence.html).
+///
+/// A catch-all segment matches the rest of the path, one or more segments, and passes it as a
+/// single parameter with the segments joined by `/`. It is tried after the static and dynamic
+/// segments at the same position, and must be the last segment of a route.
+///
+/// A constrained segment is a dynamic segment that only matches values accepted by its function.
+/// It is tried after static segments and before the plain dynamic segment, so `/users/new` and
+/// `/users/123` can be routed to different handlers. Constrained segments at the same position are
+/// tried in an unspecified order, so their functions shouldn't accept the same values unless their
+/// routes are registered with different
+/// [priorities](struct.Router.html#method.register_with_priority).
+///
+/// ```
+/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+/// fn numeric(segment: &str) -> bool {
+/// 	segment.bytes().all(|b| b.is_ascii_digit())
+/// }
+///
+/// let router = Router::default()
+/// 	.register(Method::GET, path![users / (numeric)], handler)
+/// 	.register(Method::GET, path![users / _], handler);
+/// let template = |path| router.find_node(&Method::GET, path).1.unwrap().template.as_deref();
+/// assert_eq!(template("/users/123"), Some("/users/(_)"));
+/// assert_eq!(template("/users/new"), Some("/users/_"));
+/// ```
+///
+/// An optional segment is a trailing dynamic segment that can be left out, so the route also
+/// matches the path without it and its handler receives one parameter less. Anywhere but the end
+/// of a route it is the same as a dynamic segment.
+///
+/// ```
+/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+/// let router = Router::default().register(Method::GET, path![users / _?], handler);
+/// assert_eq!(router.find_node(&Method::GET, "/users").0, Vec::<String>::new());
+/// assert_eq!(router.find_node(&Method::GET, "/users/42").0, vec!["42"]);
+/// ```
+///
+/// ```
+/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+/// let router = Router::default().register(Method::GET, path![assets / **], handler);
+/// let (params, _) = router.find_node(&Method::GET, "/assets/js/vendor/lib.js");
+/// assert_eq!(params, vec!["js/vendor/lib.js"]);
+/// ```
 ///
 /// Dynamic parameters are collected during routing and passed into the handler in an ordered list.
-#[derive(Debug, Eq, PartialEq, Hash)]
+/// A named segment is matched exactly like a dynamic one, but its value can also be looked up by
+/// name with [Params::named](struct.Params.html#method.named).
+// Constrained segments compare by function address. The same function registered twice may end
+// up as two segments, which are then both tried, so this only costs a little matching time.
+#[allow(unpredictable_function_pointer_comparisons)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum PathSegment<'a> {
 	Dynamic,
-	Static(&'a str),
+	Static(Cow<'a, str>),
+	Named(Cow<'a, str>),
+	CatchAll,
+	Optional,
+	Constrained(fn(&str) -> bool),
+}
+
+impl<'a> Display for PathSegment<'a> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Dynamic => f.write_str("_"),
+			Self::Static(segment) => f.write_str(segment),
+			Self::Named(name) => write!(f, "{{{}}}", name),
+			Self::CatchAll => f.write_str("**"),
+			Self::Optional => f.write_str("_?"),
+			Self::Constrained(_) => f.write_str("(_)"),
+		}
+	}
+}
+
+/// Reconstruct the template of a path in the same notation as the [path!](../macro.path.html)
+/// macro, e.g. `/foo/_/bar/{id}`.
+pub fn path_template(path: &[PathSegment<'_>]) -> String {
+	if path.is_empty() {
+		return "/".to_owned();
+	}
+
+	path.iter().map(|segment| format!("/{}", segment)).collect()
+}
+
+/// The names of the dynamic segments of a route, in the order they appear in the path. Unnamed
+/// segments have no name.
+pub(crate) type Names = Arc<Vec<Option<String>>>;
+
+/// Collect the names of the dynamic segments of a path, if any of them are named.
+pub(crate) fn segment_names(path: &[PathSegment<'_>]) -> Option<Names> {
+	let names: Vec<_> = path
+		.iter()
+		.filter_map(|segment| match segment {
+			PathSegment::Static(_) => None,
+			PathSegment::Named(name) => Some(Some(name.to_string())),
+			_ => Some(None),
+		})
+		.collect();
+	Some(names)
+		.filter(|names| names.iter().any(Option::is_some))
+		.map(Arc::new)
+}
+
+/// The values of the dynamic segments matched for a request, in the order they appear in the
+/// path. Cloning is cheap, so everything that handles a request can share the same parameters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+	values: Option<Arc<Vec<String>>>,
+	names: Option<Names>,
+}
+
+impl Params {
+	/// Parameters for a request with no dynamic segments.
+	pub const fn empty() -> Self {
+		Self {
+			values: None,
+			names: None,
+		}
+	}
+
+	pub fn get(&self, index: usize) -> Option<&str> {
+		self.deref().get(index).map(String::as_str)
+	}
+
+	/// Get the value of a segment registered as [Named](enum.PathSegment.html#variant.Named).
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, Params, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default().register(Method::GET, path![users / {id} / posts / _], handler);
+	/// let (values, node) = router.find_node(&Method::GET, "/users/42/posts/7");
+	/// let params = Params::from(values).with_names(node.unwrap());
+	/// assert_eq!(params.named("id"), Some("42"));
+	/// assert_eq!(params.get(1), Some("7"));
+	/// ```
+	///
+	/// The names belong to the matched route, so the values of a route mounted with
+	/// [mount_shared](struct.Router.html#method.mount_shared) are named from the end of the path.
+	pub fn named(&self, name: &str) -> Option<&str> {
+		let names = self.names.as_deref()?;
+		let offset = self.len().checked_sub(names.len())?;
+		let index = names
+			.iter()
+			.position(|candidate| candidate.as_deref() == Some(name))?;
+		self.get(offset + index)
+	}
+
+	/// Parse the value at an index with `FromStr`.
+	///
+	/// ```
+	/// # use grout::Params;
+	/// let params = Params::from(vec!["42".to_owned()]);
+	/// assert_eq!(params.parse::<u64>(0).unwrap(), 42);
+	/// assert!(params.parse::<u64>(1).is_err());
+	/// ```
+	pub fn parse<T>(&self, index: usize) -> Result<T, ParamError>
+	where
+		T: FromStr,
+		T::Err: Into<Box<dyn Error + Send + Sync>>,
+	{
+		parse_param(index.to_string(), self.get(index))
+	}
+
+	/// Parse the value of a [named](#method.named) segment with `FromStr`.
+	pub fn get_as<T>(&self, name: &str) -> Result<T, ParamError>
+	where
+		T: FromStr,
+		T::Err: Into<Box<dyn Error + Send + Sync>>,
+	{
+		parse_param(name.to_owned(), self.named(name))
+	}
+
+	/// Attach the segment names of the matched route, so values can be looked up by
+	/// [name](#method.named).
+	pub fn with_names<Req, Res>(mut self, node: &RouteNode<'_, Req, Res>) -> Self {
+		self.names = node.names.clone();
+		self
+	}
+
+	/// Take the parameters as a vec, only copying them if they are shared.
+	pub fn into_vec(self) -> Vec<String> {
+		self.values
+			.map(|values| Arc::try_unwrap(values).unwrap_or_else(|values| (*values).clone()))
+			.unwrap_or_default()
+	}
+}
+
+fn parse_param<T>(param: String, value: Option<&str>) -> Result<T, ParamError>
+where
+	T: FromStr,
+	T::Err: Into<Box<dyn Error + Send + Sync>>,
+{
+	let value = value.ok_or_else(|| ParamError::Missing {
+		param: param.clone(),
+	})?;
+	value.parse().map_err(|e: T::Err| ParamError::Invalid {
+		param,
+		value: value.to_owned(),
+		source: e.into(),
+	})
+}
+
+/// A parameter that couldn't be [parsed](struct.Params.html#method.parse). The parameter is
+/// identified by its index or name. When returned from an HTTP handler, this produces a
+/// `400 Bad Request`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ParamError {
+	/// The matched route has no parameter at the index or with the name.
+	Missing { param: String },
+	/// The value of the parameter couldn't be parsed.
+	Invalid {
+		param: String,
+		value: String,
+		source: Box<dyn Error + Send + Sync>,
+	},
+}
+
+impl Display for ParamError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Missing { param } => write!(f, "missing parameter {}", param),
+			Self::Invalid {
+				param,
+				value,
+				source,
+			} => write!(f, "invalid parameter {} {:?}: {}", param, value, source),
+		}
+	}
+}
+
+impl Error for ParamError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Missing { .. } => None,
+			Self::Invalid { source, .. } => Some(&**source),
+		}
+	}
+}
+
+impl Deref for Params {
+	type Target = [String];
+
+	fn deref(&self) -> &Self::Target {
+		self.values.as_deref().map_or(&[], Vec::as_slice)
+	}
+}
+
+impl From<Vec<String>> for Params {
+	fn from(values: Vec<String>) -> Self {
+		values.into_iter().collect()
+	}
+}
+
+impl FromIterator<String> for Params {
+	/// Collect the values without allocating if there are none.
+	fn from_iter<I: IntoIterator<Item = String>>(values: I) -> Self {
+		let values: Vec<String> = values.into_iter().collect();
+		Self {
+			values: Some(values)
+				.filter(|values| !values.is_empty())
+				.map(Arc::new),
+			names: None,
+		}
+	}
 }
 
 /// Represents the route handler type. Although this is typed with a generic return type, this is
 /// only to allow async functions to be used as handlers. T is generally going to be `impl Future<
 /// Output = Response>`, meaning your route handlers are going to look exactly like this:
 /// ```
-/// async fn handler(params: Vec<String>, req: Request) -> Response {}
+/// # use grout::{Request, Response};
+/// async fn handler(params: Vec<String>, req: Request) -> Response {
+/// 	# unimplemented!()
+/// }
 /// ```
+///
+/// Closures with the same arguments can be registered too, so handlers can capture state.
+///
+/// An [HttpRouter](struct.HttpRouter.html) also keeps the parameters in the request extensions, so
+/// a handler taking them as a `Vec<String>` gets its own copy. Handlers registered with
+/// [handle](struct.Router.html#method.handle) or [route](struct.Router.html#method.route) read the
+/// shared parameters instead.
 pub type Route<Req, Res> = fn(Vec<String>, Req) -> Res;
 
 /// Boxed closure for route handlers. Apparently different abstract types don't match, so we need
 /// to box the return type of the user-land route handlers. To keep the API clean, this type is
-/// used internally and created when the user registers a route.
-pub(crate) type DynRoute<Req, Res> =
-	Box<dyn Fn(Vec<String>, Req) -> Pin<Box<dyn Future<Output = Res> + Send>> + Send + Sync>;
+/// created when the user registers a route; it can also be built directly and registered with
+/// [register_boxed](struct.Router.html#method.register_boxed) when the concrete handler type
+/// can't be named.
+pub type DynRoute<Req, Res> =
+	Box<dyn Fn(Params, Req) -> Pin<Box<dyn Future<Output = Res> + Send>> + Send + Sync>;
+
+/// A [DynRoute](type.DynRoute.html) shared between the nodes of route trees, as routers hold their
+/// handlers so they can be cloned.
+pub type SharedRoute<Req, Res> = Arc<DynRoute<Req, Res>>;
+
+/// A predicate a request must pass for a guarded route to match, registered with
+/// [register_guarded](struct.Router.html#method.register_guarded).
+pub type Guard<Req> = Arc<dyn Fn(&Req) -> bool + Send + Sync>;
+
+/// What a route is for, registered with [register_with](struct.Router.html#method.register_with)
+/// and listed by [routes](struct.Router.html#method.routes), such as to document the routes or
+/// label their metrics. The router itself only uses the name, for
+/// [url_for](struct.Router.html#method.url_for).
+///
+/// ```
+/// # use grout::RouteMeta;
+/// let meta = RouteMeta::default()
+/// 	.name("user_detail")
+/// 	.description("Show a user")
+/// 	.tag("users")
+/// 	.auth("bearer")
+/// 	.with("owner", "accounts-team");
+/// assert_eq!(meta.tags, vec!["users"]);
+/// assert_eq!(meta.extra["owner"], "accounts-team");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RouteMeta {
+	/// The unique name of the route.
+	pub name: Option<String>,
+	pub description: Option<String>,
+	pub tags: Vec<String>,
+	/// The authentication schemes the route requires, such as `basic` or `bearer`, any of which
+	/// is enough.
+	pub auth: Vec<String>,
+	/// Any other metadata, by key.
+	pub extra: BTreeMap<String, String>,
+}
+
+impl RouteMeta {
+	pub fn name(mut self, name: impl Into<String>) -> Self {
+		self.name = Some(name.into());
+		self
+	}
+
+	pub fn description(mut self, description: impl Into<String>) -> Self {
+		self.description = Some(description.into());
+		self
+	}
+
+	pub fn tag(mut self, tag: impl Into<String>) -> Self {
+		self.tags.push(tag.into());
+		self
+	}
+
+	pub fn auth(mut self, scheme: impl Into<String>) -> Self {
+		self.auth.push(scheme.into());
+		self
+	}
+
+	pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+		self.extra.insert(key.into(), value.into());
+		self
+	}
+}
+
+/// Box a route handler into a [DynRoute](type.DynRoute.html). Any function or closure taking the
+/// same arguments as a [Route](type.Route.html) can be boxed, including closures that capture
+/// state.
+pub fn boxed<Req, Res, F, T>(route: F) -> DynRoute<Req, Res>
+where
+	Req: 'static,
+	F: Fn(Vec<String>, Req) -> T + Send + Sync + 'static,
+	T: 'static + Future<Output = Res> + Send,
+{
+	Box::new(move |params: Params, req: Req| Box::pin(route(params.into_vec(), req)))
+}
+
+/// Box a route handler that borrows the parameters instead of taking a vec of them, so it only
+/// copies the values it keeps. The future it returns can't borrow them.
+pub fn boxed_borrowed<Req, Res, F, T>(route: F) -> DynRoute<Req, Res>
+where
+	Req: 'static,
+	F: Fn(&Params, Req) -> T + Send + Sync + 'static,
+	T: 'static + Future<Output = Res> + Send,
+{
+	Box::new(move |params: Params, req: Req| Box::pin(route(&params, req)))
+}
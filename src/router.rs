@@ -1,48 +1,889 @@
-use crate::route::{DynRoute, Path, PathSegment, Route};
+use crate::route::{
+	boxed, boxed_borrowed, path_template, segment_names, DynRoute, Guard, Names, Params, Path,
+	PathSegment, RouteMeta, SharedRoute,
+};
 use std::{
+	borrow::Cow,
 	cmp::PartialEq,
-	collections::HashMap,
-	fmt::{self, Debug, Formatter},
+	collections::{hash_map::Entry, HashMap, HashSet},
+	error::Error,
+	fmt::{self, Debug, Display, Formatter},
 	future::Future,
 	hash::Hash,
-	ptr,
+	sync::Arc,
 };
 
 pub struct RouteNode<'path, Req, Res> {
-	pub route: Option<DynRoute<Req, Res>>,
+	pub route: Option<SharedRoute<Req, Res>>,
+	/// Routes that only match requests passing their guard, tried in the order they were
+	/// registered before the unguarded route.
+	pub guarded: Vec<(Guard<Req>, SharedRoute<Req, Res>)>,
+	/// The template of the path this route was registered with, if any.
+	pub template: Option<String>,
+	/// The names of the dynamic segments of the path this route was registered with, if any of
+	/// them are named.
+	pub names: Option<Names>,
 	pub path: Option<RoutePath<'path, Req, Res>>,
+	/// A subtree mounted here with [mount_shared](struct.Router.html#method.mount_shared). It is
+	/// matched after this node's own route and children.
+	pub shared: Option<Arc<RouteNode<'path, Req, Res>>>,
+	/// The highest [priority](struct.Router.html#method.register_with_priority) of the routes at
+	/// or below this node, if there are any.
+	pub priority: Option<i32>,
+	/// The [metadata](struct.RouteMeta.html) the routes of this node were registered with, if any.
+	pub meta: Option<Arc<RouteMeta>>,
 }
 
 impl<'path, Req, Res> Default for RouteNode<'path, Req, Res> {
 	fn default() -> Self {
 		Self {
 			route: None,
+			guarded: vec![],
+			template: None,
+			names: None,
 			path: None,
+			shared: None,
+			priority: None,
+			meta: None,
+		}
+	}
+}
+
+/// Clones share the route handlers, guards and shared subtrees of the node.
+impl<'path, Req, Res> Clone for RouteNode<'path, Req, Res> {
+	fn clone(&self) -> Self {
+		Self {
+			route: self.route.clone(),
+			guarded: self.guarded.clone(),
+			template: self.template.clone(),
+			names: self.names.clone(),
+			path: self.path.clone(),
+			shared: self.shared.clone(),
+			priority: self.priority,
+			meta: self.meta.clone(),
 		}
 	}
 }
 
+/// Nodes are equal when they have the same structure: either both or neither have a route, they
+/// have as many guarded routes, and their children and shared subtrees are equal. Route handlers
+/// and guards can't be compared, so which ones a node holds is not part of equality.
 impl<'path, Req, Res> PartialEq for RouteNode<'path, Req, Res> {
 	fn eq(&self, other: &RouteNode<'path, Req, Res>) -> bool {
-		ptr::eq(&self.route, &other.route) && self.path.eq(&other.path)
+		self.route.is_some() == other.route.is_some()
+			&& self.guarded.len() == other.guarded.len()
+			&& self.path.eq(&other.path)
+			&& self.shared.eq(&other.shared)
 	}
 }
 
 impl<'a, Req, Res> Debug for RouteNode<'a, Req, Res> {
 	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-		write!(f, "{:?}", &self)
+		f.debug_struct("RouteNode")
+			.field("route", &self.route.is_some())
+			.field("guarded", &self.guarded.len())
+			.field("template", &self.template)
+			.field("names", &self.names)
+			.field("path", &self.path)
+			.field("shared", &self.shared)
+			.field("priority", &self.priority)
+			.field("meta", &self.meta)
+			.finish()
+	}
+}
+
+impl<'path, Req, Res> RouteNode<'path, Req, Res> {
+	/// The route of this node that handles a request: the first guarded route whose guard the
+	/// request passes, or else the unguarded route.
+	pub fn route_for(&self, req: &Req) -> Option<&DynRoute<Req, Res>> {
+		self.guarded
+			.iter()
+			.find(|(guard, _)| guard(req))
+			.map(|(_, route)| &**route)
+			.or(self.route.as_deref())
+	}
+
+	/// Whether this node has a route for a request, or for any request if there is none.
+	fn accepts(&self, req: Option<&Req>) -> bool {
+		match req {
+			Some(req) => self.route_for(req).is_some(),
+			None => self.route.is_some() || !self.guarded.is_empty(),
+		}
 	}
 }
 
 type RoutePath<'path, Req, Res> = HashMap<PathSegment<'path>, RouteNode<'path, Req, Res>>;
 pub type Routes<'path, Prefix, Req, Res> = HashMap<Prefix, RouteNode<'path, Req, Res>>;
 
-/// Intended to be used as the main service with hyper.
+/// Which kind of segment a router tries first when both could match a segment of a request path.
+///
+/// Matching always backtracks, so the precedence only decides between routes that would both
+/// match; a path matched by just one of them is routed the same way under either mode.
+///
+/// ```
+/// # use grout::{hyper::Method, path, PathSegment, Precedence, Request, Response, Router};
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+/// let routes = || {
+/// 	Router::default()
+/// 		.register(Method::GET, path![users / me], handler)
+/// 		.register(Method::GET, path![users / _], handler)
+/// };
+///
+/// let router = routes();
+/// let (params, node) = router.find_node(&Method::GET, "/users/me");
+/// assert_eq!(node.unwrap().template.as_deref(), Some("/users/me"));
+/// assert!(params.is_empty());
+///
+/// let router = routes().precedence(Precedence::DynamicFirst);
+/// let (params, node) = router.find_node(&Method::GET, "/users/me");
+/// assert_eq!(node.unwrap().template.as_deref(), Some("/users/_"));
+/// assert_eq!(params, vec!["me"]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precedence {
+	/// Static segments are tried before the dynamic segment. This is the default.
+	#[default]
+	StaticFirst,
+	/// The dynamic segment is tried before static segments, so a dynamic route shadows static
+	/// routes at the same position. Static routes are then only reached when the dynamic branch
+	/// has no route for the rest of the path. Catch-all segments are tried before both, and
+	/// constrained segments between them.
+	DynamicFirst,
+}
+
+/// How a router treats a trailing slash on a request path, such as `/foo/`. The root path `/` has
+/// no trailing slash.
+///
+/// ```
+/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router, TrailingSlash};
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+/// let router = Router::default().register(Method::GET, path![foo], handler);
+/// assert!(router.find_node(&Method::GET, "/foo/").1.is_some());
+///
+/// let router = router.trailing_slash(TrailingSlash::Strict);
+/// assert!(router.find_node(&Method::GET, "/foo/").1.is_none());
+/// assert!(router.find_node(&Method::GET, "/foo").1.is_some());
 /// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingSlash {
+	/// A path with a trailing slash matches the same routes as the path without it. This is the
+	/// default.
+	#[default]
+	Ignore,
+	/// A path with a trailing slash is distinct from the path without it, so it matches no route.
+	Strict,
+	/// A path with a trailing slash matches no route, like [Strict](#variant.Strict), but an
+	/// [HttpRouter](struct.HttpRouter.html) answers requests for it with a
+	/// `308 Permanent Redirect` to the path without it, if that path has a route.
+	RedirectToCanonical,
+}
+
+/// Whether a request path, without its query string, ends in a slash other than the root path.
+pub(crate) fn has_trailing_slash(path: &str) -> bool {
+	let end = path.find(['?', '#']).unwrap_or(path.len());
+	end > 1 && path[..end].ends_with('/')
+}
+
+/// A problem with a registered route that means it can never match a request. Matching always
+/// backtracks, so a route is never shadowed by another that matches some of the same requests;
+/// these are the only ways a route can be unreachable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RouteError {
+	/// A static segment that is empty or contains a `/`, neither of which can appear in a
+	/// segment of a request path.
+	InvalidSegment { path: String, segment: String },
+	/// A segment after a catch-all segment, which has already matched the rest of the path.
+	AfterCatchAll { path: String },
+	/// Two routers being [merged](struct.Router.html#method.merge) both have a route for the
+	/// same path under the same prefix.
+	Conflict { path: String },
+	/// A route registered for a path under a prefix that already had one, which replaced it.
+	/// Paths that only differ in the names of their dynamic segments are the same path.
+	Duplicate { path: String },
+	/// An optional segment before the last segment of a path, where it is matched like a
+	/// required dynamic segment.
+	OptionalNotLast { path: String },
+	/// A route [name](struct.Router.html#method.register_named) used for more than one route.
+	DuplicateName { name: String },
+	/// A route registered with an [extension method](struct.Router.html#method.register_method)
+	/// that isn't a valid method.
+	InvalidMethod { path: String },
+}
+
+impl Display for RouteError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::InvalidSegment { path, segment } => {
+				write!(f, "{}: segment {:?} can never match", path, segment)
+			}
+			Self::AfterCatchAll { path } => {
+				write!(f, "{}: segments after a catch-all can never match", path)
+			}
+			Self::Conflict { path } => write!(f, "{}: registered in both routers", path),
+			Self::Duplicate { path } => write!(f, "{}: registered more than once", path),
+			Self::OptionalNotLast { path } => {
+				write!(f, "{}: only the last segment can be optional", path)
+			}
+			Self::DuplicateName { name } => write!(f, "route name {:?} used more than once", name),
+			Self::InvalidMethod { path } => {
+				write!(f, "{}: registered with an invalid method", path)
+			}
+		}
+	}
+}
+
+/// Every problem found while validating a router.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildError {
+	pub errors: Vec<RouteError>,
+}
+
+impl Display for BuildError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{} invalid route(s)", self.errors.len())?;
+		for error in &self.errors {
+			write!(f, "\n- {}", error)?;
+		}
+		Ok(())
+	}
+}
+
+impl Error for BuildError {}
+
+fn validate_node<Req, Res>(
+	node: &RouteNode<'_, Req, Res>,
+	path: &str,
+	errors: &mut Vec<RouteError>,
+) {
+	for (segment, child) in node.path.iter().flatten() {
+		let child_path = format!("{}/{}", path, segment);
+		if let PathSegment::Static(value) = segment {
+			if value.is_empty() || value.contains('/') {
+				errors.push(RouteError::InvalidSegment {
+					path: child_path.clone(),
+					segment: value.to_string(),
+				});
+			}
+		}
+
+		if *segment == PathSegment::CatchAll && child.path.iter().flatten().next().is_some() {
+			errors.push(RouteError::AfterCatchAll {
+				path: child_path.clone(),
+			});
+		}
+
+		validate_node(child, &child_path, errors);
+	}
+
+	if let Some(shared) = &node.shared {
+		validate_node(shared, path, errors);
+	}
+}
+
+/// Get the node at a path, creating it and its parents if they don't exist yet. Named and optional
+/// segments share the node of the dynamic segment, since they match the same requests.
+/// The nodes on the way are raised to a priority, as routes are put below them.
+fn node_at<'n, 'a, Req, Res>(
+	mut node: &'n mut RouteNode<'a, Req, Res>,
+	path: Path<'a>,
+	priority: Option<i32>,
+) -> &'n mut RouteNode<'a, Req, Res> {
+	raise(&mut node.priority, priority);
+	for segment in path {
+		node = node
+			.path
+			.get_or_insert(RoutePath::default())
+			.entry(node_segment(segment))
+			.or_default();
+		raise(&mut node.priority, priority);
+	}
+	node
+}
+
+/// The segment of the node a path segment is stored under. Named and optional segments share the
+/// node of the dynamic segment.
+fn node_segment(segment: PathSegment<'_>) -> PathSegment<'_> {
+	match segment {
+		PathSegment::Named(_) | PathSegment::Optional => PathSegment::Dynamic,
+		segment => segment,
+	}
+}
+
+/// Take the routes of a path, and of the path without its optional segment if it ends in one.
+fn remove_optional<'a, Req, Res>(
+	node: &mut RouteNode<'a, Req, Res>,
+	path: &[PathSegment<'a>],
+) -> Vec<RouteNode<'a, Req, Res>> {
+	let mut removed: Vec<_> = remove(node, path).into_iter().collect();
+	if let Some((PathSegment::Optional, rest)) = path.split_last() {
+		removed.extend(remove(node, rest));
+	}
+	removed
+}
+
+/// Take the routes of the node at a path, pruning the nodes left without routes or children.
+/// Returns the routes taken, as a node without children, or `None` if the path had no route.
+fn remove<'a, Req, Res>(
+	node: &mut RouteNode<'a, Req, Res>,
+	path: &[PathSegment<'a>],
+) -> Option<RouteNode<'a, Req, Res>> {
+	let (segment, rest) = match path.split_first() {
+		Some((segment, rest)) => (node_segment(segment.clone()), rest),
+		None if node.accepts(None) => {
+			return Some(RouteNode {
+				route: node.route.take(),
+				guarded: std::mem::take(&mut node.guarded),
+				template: node.template.take(),
+				names: node.names.take(),
+				meta: node.meta.take(),
+				..Default::default()
+			})
+		}
+		None => return None,
+	};
+
+	let children = node.path.as_mut()?;
+	let child = children.get_mut(&segment)?;
+	let removed = remove(child, rest)?;
+	if !child.accepts(None) && child.path.is_none() && child.shared.is_none() {
+		children.remove(&segment);
+	}
+	if children.is_empty() {
+		node.path = None;
+	}
+	Some(removed)
+}
+
+fn raise(priority: &mut Option<i32>, to: Option<i32>) {
+	*priority = (*priority).max(to);
+}
+
+/// Register a route at a path, with its static segments lowercased if `fold` is set, for a
+/// [case-insensitive](struct.Router.html#method.case_insensitive) router. The template keeps the
+/// case the path was registered with.
+fn insert<'a, Req: 'static, Res: 'static>(
+	node: &mut RouteNode<'a, Req, Res>,
+	mut path: Path<'a>,
+	fold: bool,
+	priority: i32,
+	guard: Option<Guard<Req>>,
+	meta: Option<Arc<RouteMeta>>,
+	route: DynRoute<Req, Res>,
+) -> Vec<RouteError> {
+	let priority = Some(priority);
+	let template = path_template(&path);
+	if fold {
+		path = fold_path(path);
+	}
+	let mut errors = vec![];
+	if path
+		.iter()
+		.rev()
+		.skip(1)
+		.any(|segment| *segment == PathSegment::Optional)
+	{
+		errors.push(RouteError::OptionalNotLast {
+			path: template.clone(),
+		});
+	}
+	let finish = |replaced| {
+		if replaced {
+			errors.push(RouteError::Duplicate {
+				path: template.clone(),
+			});
+		}
+		errors
+	};
+	if path.last() != Some(&PathSegment::Optional) {
+		let names = segment_names(&path);
+		let node = node_at(node, path, priority);
+		let replaced = set_route(node, guard, Arc::new(route));
+		node.template = Some(template.clone());
+		node.names = names;
+		set_meta(node, meta);
+		return finish(replaced);
+	}
+
+	// An optional segment registers the route both with and without it.
+	let route = Arc::new(route);
+	let names = segment_names(&path);
+	let with = node_at(node, path.clone(), priority);
+	let replaced = set_route(with, guard.clone(), Arc::clone(&route));
+	with.template = Some(template.clone());
+	with.names = names;
+	set_meta(with, meta.clone());
+
+	path.pop();
+	let names = segment_names(&path);
+	let without = node_at(node, path, priority);
+	let replaced = set_route(without, guard, route) || replaced;
+	without.template = Some(template.clone());
+	without.names = names;
+	set_meta(without, meta);
+	finish(replaced)
+}
+
+/// Attach metadata to a node, keeping the metadata of earlier routes if there is none.
+fn set_meta<Req, Res>(node: &mut RouteNode<'_, Req, Res>, meta: Option<Arc<RouteMeta>>) {
+	if meta.is_some() {
+		node.meta = meta;
+	}
+}
+
+/// Set the route of a node, returning whether it replaced one.
+fn set_route<Req, Res>(
+	node: &mut RouteNode<'_, Req, Res>,
+	guard: Option<Guard<Req>>,
+	route: SharedRoute<Req, Res>,
+) -> bool {
+	match guard {
+		Some(guard) => {
+			node.guarded.push((guard, route));
+			false
+		}
+		None => node.route.replace(route).is_some(),
+	}
+}
+
+/// A route registered with a router, as listed by [routes](struct.Router.html#method.routes).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RouteInfo<'r, Prefix> {
+	/// The prefix the route is registered under, or `None` for routes registered with
+	/// [register_any](struct.Router.html#method.register_any).
+	pub prefix: Option<&'r Prefix>,
+	/// The template of the path of the route, in the notation of the [path!](macro.path.html)
+	/// macro, including the paths it is mounted under.
+	pub path: String,
+	/// Whether the route only matches requests passing a
+	/// [guard](struct.Router.html#method.register_guarded).
+	pub guarded: bool,
+	/// The [metadata](struct.RouteMeta.html) of the route, if it was registered with any.
+	pub meta: Option<&'r RouteMeta>,
+}
+
+/// Collect the routes of a node and the nodes below it. `path` is the path of the node in
+/// the notation of the templates, and `base` the path the templates of the node are relative to,
+/// which is only set inside shared subtrees.
+fn collect_routes<'r, Prefix, Req, Res>(
+	node: &'r RouteNode<'r, Req, Res>,
+	prefix: Option<&'r Prefix>,
+	path: &str,
+	base: Option<&str>,
+	routes: &mut Vec<(RouteInfo<'r, Prefix>, Option<usize>)>,
+) {
+	if let Some(template) = &node.template {
+		let path = match (base, template.as_str()) {
+			(None, _) | (Some(""), _) => template.clone(),
+			(Some(base), "/") => base.to_owned(),
+			(Some(base), template) => format!("{}{}", base, template),
+		};
+		let guarded = (0..node.guarded.len()).map(Some);
+		for index in guarded.chain(node.route.as_ref().map(|_| None)) {
+			let route = RouteInfo {
+				prefix,
+				path: path.clone(),
+				guarded: index.is_some(),
+				meta: node.meta.as_deref(),
+			};
+			routes.push((route, index));
+		}
+	}
+
+	for (segment, child) in node.path.iter().flatten() {
+		let child_path = format!("{}/{}", path, segment);
+		collect_routes(child, prefix, &child_path, base, routes);
+	}
+
+	if let Some(shared) = &node.shared {
+		let base = match base {
+			Some(base) => format!("{}{}", base, path),
+			None => path.to_owned(),
+		};
+		collect_routes(shared, prefix, "", Some(&base), routes);
+	}
+}
+
+/// Why a URL couldn't be built with [url_for](struct.Router.html#method.url_for).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UrlError {
+	/// No route is registered with the name.
+	UnknownRoute { name: String },
+	/// The number of parameters doesn't match the dynamic segments of the route.
+	ParamCount {
+		name: String,
+		expected: usize,
+		given: usize,
+	},
+	/// A parameter would never match its segment, because it is empty or rejected by the
+	/// function of a constrained segment.
+	InvalidParam { name: String, index: usize },
+}
+
+impl Display for UrlError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UnknownRoute { name } => write!(f, "no route is named {:?}", name),
+			Self::ParamCount {
+				name,
+				expected,
+				given,
+			} => write!(
+				f,
+				"route {:?} takes {} parameter(s), but {} were given",
+				name, expected, given
+			),
+			Self::InvalidParam { name, index } => write!(
+				f,
+				"parameter {} of route {:?} would never match its segment",
+				index, name
+			),
+		}
+	}
+}
+
+impl Error for UrlError {}
+
+/// The paths of named routes, by name.
+pub(crate) type RouteNames<'a> = Arc<HashMap<String, Path<'a>>>;
+
+/// Build the URL path of a named route from the values of its dynamic segments.
+pub(crate) fn url_for(
+	names: &RouteNames<'_>,
+	name: &str,
+	params: &[&str],
+) -> Result<String, UrlError> {
+	let path = names.get(name).ok_or_else(|| UrlError::UnknownRoute {
+		name: name.to_owned(),
+	})?;
+	let required = path
+		.iter()
+		.filter(|segment| !matches!(segment, PathSegment::Static(_) | PathSegment::Optional))
+		.count();
+	let optional = path.contains(&PathSegment::Optional);
+	if params.len() != required && !(optional && params.len() == required + 1) {
+		return Err(UrlError::ParamCount {
+			name: name.to_owned(),
+			expected: required,
+			given: params.len(),
+		});
+	}
+
+	let invalid = |index| UrlError::InvalidParam {
+		name: name.to_owned(),
+		index,
+	};
+	let mut url = String::new();
+	let params_len = params.len();
+	let mut params = params.iter().enumerate();
+	for segment in path {
+		let value = match segment {
+			PathSegment::Static(value) => {
+				url.push('/');
+				url.push_str(value);
+				continue;
+			}
+			PathSegment::Optional => match params.next() {
+				Some(param) => param,
+				None => break,
+			},
+			// An optional segment before the last one takes the value a later segment needed.
+			// Such paths are only rejected by validate, so they can still be named here.
+			_ => params.next().ok_or_else(|| UrlError::ParamCount {
+				name: name.to_owned(),
+				expected: required,
+				given: params_len,
+			})?,
+		};
+
+		let (index, value) = value;
+		let valid = match segment {
+			PathSegment::Constrained(matches) => matches(value),
+			PathSegment::CatchAll => value.split('/').any(|part| !part.is_empty()),
+			_ => true,
+		};
+		if value.is_empty() || !valid {
+			return Err(invalid(index));
+		}
+
+		url.push('/');
+		match segment {
+			PathSegment::CatchAll => {
+				let parts: Vec<String> = value.split('/').map(encode_segment).collect();
+				url.push_str(&parts.join("/"));
+			}
+			_ => url.push_str(&encode_segment(value)),
+		}
+	}
+
+	if url.is_empty() {
+		url.push('/');
+	}
+	Ok(url)
+}
+
+/// Percent-encode a path segment, keeping the characters that are allowed in one unencoded.
+fn encode_segment(segment: &str) -> String {
+	let mut encoded = String::with_capacity(segment.len());
+	for byte in segment.bytes() {
+		match byte {
+			b'A'..=b'Z'
+			| b'a'..=b'z'
+			| b'0'..=b'9'
+			| b'-'
+			| b'.'
+			| b'_'
+			| b'~'
+			| b'!'
+			| b'$'
+			| b'&'
+			| b'\''
+			| b'('
+			| b')'
+			| b'*'
+			| b'+'
+			| b','
+			| b';'
+			| b'='
+			| b':'
+			| b'@' => encoded.push(char::from(byte)),
+			_ => encoded.push_str(&format!("%{:02X}", byte)),
+		}
+	}
+	encoded
+}
+
+/// Where a router's routes are grafted into another router.
+struct Mount {
+	/// The template of the mount path, empty at the root.
+	template: String,
+	/// The names of the dynamic segments of the mount path, if any of them are named.
+	names: Option<Names>,
+}
+
+impl Mount {
+	fn new(path: &[PathSegment<'_>]) -> Self {
+		Self {
+			template: if path.is_empty() {
+				String::new()
+			} else {
+				path_template(path)
+			},
+			names: segment_names(path),
+		}
+	}
+
+	/// The template of a route at a template relative to the mount point.
+	fn join(&self, template: &str) -> String {
+		match template {
+			"/" if !self.template.is_empty() => self.template.clone(),
+			_ => format!("{}{}", self.template, template),
+		}
+	}
+
+	/// Move the registration errors of a mounted router, with paths that include the mount path.
+	fn move_errors(&self, errors: Vec<RouteError>, into: &mut Vec<RouteError>) {
+		into.extend(errors.into_iter().map(|error| match error {
+			RouteError::Duplicate { path } => RouteError::Duplicate {
+				path: self.join(&path),
+			},
+			RouteError::OptionalNotLast { path } => RouteError::OptionalNotLast {
+				path: self.join(&path),
+			},
+			error => error,
+		}));
+	}
+}
+
+/// Move the routes of a node into another, replacing the routes already there. `path` and
+/// `dynamic` are the path between the mount point and the node and the number of dynamic segments
+/// in it. Every route and shared
+/// subtree that replaces another is reported as a conflict.
+fn graft<'a, Req, Res>(
+	target: &mut RouteNode<'a, Req, Res>,
+	source: RouteNode<'a, Req, Res>,
+	mount: &Mount,
+	path: &str,
+	dynamic: usize,
+	conflicts: &mut Vec<RouteError>,
+) {
+	let replaced = (target.route.is_some() && source.route.is_some())
+		|| (target.shared.is_some() && source.shared.is_some());
+	let routed = source.route.is_some() || !source.guarded.is_empty();
+	raise(&mut target.priority, source.priority);
+	target.guarded.extend(source.guarded);
+	if let Some(route) = source.route {
+		target.route = Some(route);
+	}
+	if source.meta.is_some() {
+		target.meta = source.meta;
+	}
+	if routed {
+		target.template = source.template.map(|template| mount.join(&template));
+		target.names = match &mount.names {
+			None => source.names,
+			Some(mount_names) => {
+				let names = source
+					.names
+					.map_or_else(|| vec![None; dynamic], |names| names.as_ref().clone());
+				Some(Arc::new(mount_names.iter().cloned().chain(names).collect()))
+			}
+		};
+	}
+
+	if source.shared.is_some() {
+		target.shared = source.shared;
+	}
+
+	if replaced {
+		let path = format!("{}{}", mount.template, path);
+		conflicts.push(RouteError::Conflict {
+			path: if path.is_empty() {
+				"/".to_owned()
+			} else {
+				path
+			},
+		});
+	}
+
+	for (segment, child) in source.path.into_iter().flatten() {
+		let child_path = format!("{}/{}", path, segment);
+		let dynamic = match segment {
+			PathSegment::Static(_) => dynamic,
+			_ => dynamic + 1,
+		};
+		let next = target
+			.path
+			.get_or_insert(RoutePath::default())
+			.entry(segment)
+			.or_default();
+		graft(next, child, mount, &child_path, dynamic, conflicts);
+	}
+}
+
+/// Lowercase a value, or return `None` if it has no uppercase characters.
+fn fold_case(value: &str) -> Option<String> {
+	let folded = value.chars().flat_map(char::to_lowercase);
+	if folded.clone().eq(value.chars()) {
+		None
+	} else {
+		Some(folded.collect())
+	}
+}
+
+/// The segment a static segment is stored under in a case-insensitive router.
+fn fold_segment(segment: PathSegment<'_>) -> PathSegment<'_> {
+	match segment {
+		PathSegment::Static(value) => match fold_case(&value) {
+			Some(folded) => PathSegment::Static(Cow::Owned(folded)),
+			None => PathSegment::Static(value),
+		},
+		segment => segment,
+	}
+}
+
+fn fold_path(path: Path<'_>) -> Path<'_> {
+	path.into_iter().map(fold_segment).collect()
+}
+
+/// Whether a node or the nodes below it have static segments that aren't lowercased.
+fn needs_folding<Req, Res>(node: &RouteNode<'_, Req, Res>) -> bool {
+	node.path.iter().flatten().any(|(segment, child)| {
+		matches!(segment, PathSegment::Static(value) if fold_case(value).is_some())
+			|| needs_folding(child)
+	}) || node.shared.iter().any(|shared| needs_folding(shared))
+}
+
+/// Lowercase the static segments of a node and the nodes below it, merging the children that
+/// only differ in case. Routes that end up at the same path are reported as duplicates. Shared
+/// subtrees are copied before they are changed, unless they are already lowercased.
+fn fold_node<'a, Req, Res>(
+	node: &mut RouteNode<'a, Req, Res>,
+	path: &str,
+	errors: &mut Vec<RouteError>,
+) {
+	if let Some(children) = node.path.take() {
+		let mut folded = RoutePath::default();
+		for (segment, mut child) in children {
+			let segment = fold_segment(segment);
+			let child_path = format!("{}/{}", path, segment);
+			fold_node(&mut child, &child_path, errors);
+			match folded.entry(segment) {
+				Entry::Vacant(entry) => {
+					entry.insert(child);
+				}
+				Entry::Occupied(entry) => {
+					let mut conflicts = vec![];
+					graft(
+						entry.into_mut(),
+						child,
+						&Mount::new(&[]),
+						&child_path,
+						0,
+						&mut conflicts,
+					);
+					errors.extend(conflicts.into_iter().map(|error| match error {
+						RouteError::Conflict { path } => RouteError::Duplicate { path },
+						error => error,
+					}));
+				}
+			}
+		}
+		node.path = Some(folded);
+	}
+
+	if let Some(shared) = &mut node.shared {
+		fold_shared(shared, path, errors);
+	}
+}
+
+fn fold_shared<Req, Res>(
+	shared: &mut Arc<RouteNode<'_, Req, Res>>,
+	path: &str,
+	errors: &mut Vec<RouteError>,
+) {
+	if needs_folding(shared) {
+		fold_node(Arc::make_mut(shared), path, errors);
+	}
+}
+
+/// The route trees of a router, frozen so that they can be mounted under several paths of other
+/// routers without copying. Create one with [share](struct.Router.html#method.share).
+pub struct SharedRouter<'a, Prefix, Req, Res> {
+	routes: HashMap<Prefix, Arc<RouteNode<'a, Req, Res>>>,
+	any: Arc<RouteNode<'a, Req, Res>>,
+}
+
+impl<'a, Prefix, Req, Res> Clone for SharedRouter<'a, Prefix, Req, Res>
+where
+	Prefix: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			routes: self.routes.clone(),
+			any: Arc::clone(&self.any),
+		}
+	}
+}
+
+impl<'a, Prefix: Debug, Req, Res> Debug for SharedRouter<'a, Prefix, Req, Res> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("SharedRouter")
+			.field("routes", &self.routes)
+			.field("any", &self.any)
+			.finish()
+	}
+}
+
+/// Intended to be used as the main service with hyper.
+/// ```no_run
+/// # use grout::{hyper::{Method, Server}, HttpRouter, Router};
 /// #[tokio::main]
-/// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 /// 	let addr = ([127, 0, 0, 1], 3000).into();
-/// 	let server = Server::bind(&addr).serve(RouteBuilder::default().build());
+/// 	let server = Server::bind(&addr).serve(HttpRouter::<Method>::from(Router::default()));
 /// 	server.await?;
 /// 	Ok(())
 /// }
@@ -50,72 +891,1289 @@ pub type Routes<'path, Prefix, Req, Res> = HashMap<Prefix, RouteNode<'path, Req,
 #[derive(Debug)]
 pub struct Router<'a, Prefix, Req, Res> {
 	routes: Routes<'a, Prefix, Req, Res>,
+	any: RouteNode<'a, Req, Res>,
+	precedence: Precedence,
+	trailing_slash: TrailingSlash,
+	decode: bool,
+	normalize: bool,
+	case_insensitive: bool,
+	/// Problems found while registering routes, reported by validation.
+	errors: Vec<RouteError>,
+	names: RouteNames<'a>,
+}
+
+/// Clones share the route handlers, so a router can be cloned to change the routes of a running
+/// [HttpRouter](struct.HttpRouter.html) with [update](struct.HttpRouter.html#method.update).
+impl<'a, Prefix: Clone, Req, Res> Clone for Router<'a, Prefix, Req, Res> {
+	fn clone(&self) -> Self {
+		Self {
+			routes: self.routes.clone(),
+			any: self.any.clone(),
+			precedence: self.precedence,
+			trailing_slash: self.trailing_slash,
+			decode: self.decode,
+			normalize: self.normalize,
+			case_insensitive: self.case_insensitive,
+			errors: self.errors.clone(),
+			names: Arc::clone(&self.names),
+		}
+	}
 }
 
 impl<'a, Prefix, Req, Res> Default for Router<'a, Prefix, Req, Res> {
 	fn default() -> Self {
 		Self {
 			routes: Default::default(),
+			any: Default::default(),
+			precedence: Default::default(),
+			trailing_slash: Default::default(),
+			decode: true,
+			normalize: false,
+			case_insensitive: false,
+			errors: vec![],
+			names: Default::default(),
 		}
 	}
 }
 
+/// Prints the route trees of the router, one per prefix, with a line for every segment. Nodes
+/// that have a route are marked `[route]`, with nodes holding guarded routes marked with how many
+/// they have, and subtrees mounted with [mount_shared](#method.mount_shared) are listed under a
+/// `[shared]` line. Named and optional segments are printed as `_`, since they share the node of
+/// the dynamic segment. Prefixes and segments are sorted, so the output is stable.
+///
+/// ```
+/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+/// let router = Router::default()
+/// 	.register(Method::GET, path![users], handler)
+/// 	.register(Method::GET, path![users / {id}], handler)
+/// 	.register(Method::POST, path![users], handler)
+/// 	.register_any(path![health], handler);
+///
+/// assert_eq!(router.to_string(), "\
+/// GET /
+/// 	users [route]
+/// 		_ [route]
+/// POST /
+/// 	users [route]
+/// any /
+/// 	health [route]
+/// ");
+/// ```
+impl<'a, Prefix: Debug, Req, Res> Display for Router<'a, Prefix, Req, Res> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let mut trees: Vec<_> = self
+			.routes
+			.iter()
+			.map(|(prefix, node)| (format!("{:?}", prefix), node))
+			.collect();
+		trees.sort_by(|a, b| a.0.cmp(&b.0));
+		let any =
+			Some(("any".to_owned(), &self.any)).filter(|(_, node)| **node != RouteNode::default());
+		for (prefix, node) in trees
+			.iter()
+			.map(|(prefix, node)| (prefix.clone(), *node))
+			.chain(any)
+		{
+			write!(f, "{} /", prefix)?;
+			write_tree(f, node, 0)?;
+		}
+		Ok(())
+	}
+}
+
+/// Write the marks of a node and the lines of the nodes below it, indented by their depth.
+fn write_tree<Req, Res>(
+	f: &mut Formatter<'_>,
+	node: &RouteNode<'_, Req, Res>,
+	depth: usize,
+) -> fmt::Result {
+	if node.route.is_some() {
+		f.write_str(" [route]")?;
+	}
+	if !node.guarded.is_empty() {
+		write!(f, " [{} guarded]", node.guarded.len())?;
+	}
+	writeln!(f)?;
+
+	let mut children: Vec<_> = node
+		.path
+		.iter()
+		.flatten()
+		.map(|(segment, child)| (segment.to_string(), child))
+		.collect();
+	children.sort_by(|a, b| a.0.cmp(&b.0));
+	for (segment, child) in children {
+		write!(f, "{}{}", "\t".repeat(depth + 1), segment)?;
+		write_tree(f, child, depth + 1)?;
+	}
+
+	if let Some(shared) = &node.shared {
+		write!(f, "{}[shared]", "\t".repeat(depth + 1))?;
+		write_tree(f, shared, depth + 1)?;
+	}
+	Ok(())
+}
+
 impl<'a, Prefix, Req, Res> Router<'a, Prefix, Req, Res>
 where
 	Req: 'static,
+	Res: 'static,
 	Prefix: Eq + Hash,
 {
-	pub fn register<T: 'static + Future<Output = Res> + Send>(
+	/// Register a route handler for a path under a prefix. The handler can be a function or a
+	/// closure, so it can capture state such as a connection pool.
+	///
+	/// ```
+	/// # use grout::{hyper::{Body, Method}, path, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// # use std::sync::Arc;
+	/// let greeting = Arc::new(String::from("hello"));
+	/// let router = Router::default().register(
+	/// 	Method::GET,
+	/// 	path![greet],
+	/// 	move |_params: Vec<String>, _req: Request| {
+	/// 		let greeting = Arc::clone(&greeting);
+	/// 		async move { Ok(ResponseBuilder::default().body(Body::from(greeting.to_string()))?) }
+	/// 	},
+	/// );
+	/// # let _: &grout::Router<'_, Method, Request, Response> = &router;
+	/// ```
+	pub fn register<F, T>(self, prefix: Prefix, path: Path<'a>, route: F) -> Self
+	where
+		F: Fn(Vec<String>, Req) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Res> + Send,
+	{
+		self.register_boxed(prefix, path, boxed(route))
+	}
+
+	/// Register a route handler that borrows the matched parameters, like
+	/// [register](#method.register) but without copying the parameters into a vec for it. A
+	/// synchronous handler can read them and return a ready future.
+	///
+	/// ```
+	/// # use grout::{path, Params, PathSegment, Router};
+	/// # use std::future::ready;
+	/// # #[tokio::main]
+	/// # async fn main() {
+	/// let router: Router<'_, &str, (), String> = Router::default().register_borrowed(
+	/// 	"greet",
+	/// 	path![greet / {name}],
+	/// 	|params: &Params, _req: ()| ready(format!("hello, {}", params.named("name").unwrap_or_default())),
+	/// );
+	///
+	/// let (values, node) = router.find_node(&"greet", "/greet/ferris");
+	/// let node = node.unwrap();
+	/// let route = node.route.as_ref().unwrap();
+	/// assert_eq!(route(Params::from(values).with_names(node), ()).await, "hello, ferris");
+	/// # }
+	/// ```
+	pub fn register_borrowed<F, T>(self, prefix: Prefix, path: Path<'a>, route: F) -> Self
+	where
+		F: Fn(&Params, Req) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Res> + Send,
+	{
+		self.register_boxed(prefix, path, boxed_borrowed(route))
+	}
+
+	/// Register an already boxed route handler, such as one provided by a plugin whose concrete
+	/// handler type isn't known.
+	pub fn register_boxed(
 		mut self,
 		prefix: Prefix,
 		path: Path<'a>,
-		route: Route<Req, T>,
+		route: DynRoute<Req, Res>,
 	) -> Self {
-		let mut node = self.routes.entry(prefix).or_default();
-
-		let path_iter = path.into_iter();
-		for segment in path_iter {
-			node = node
-				.path
-				.get_or_insert(RoutePath::default())
-				.entry(segment)
-				.or_default();
-		}
-		node.route = Some(Box::new(move |params: Vec<String>, req: Req| {
-			Box::pin(route(params, req))
-		}));
+		let duplicate = insert(
+			self.routes.entry(prefix).or_default(),
+			path,
+			self.case_insensitive,
+			0,
+			None,
+			None,
+			route,
+		);
+		self.errors.extend(duplicate);
 		self
 	}
 
-	pub fn find_node<'path>(
-		&self,
+	/// Register a route handler with a name, so the URL of the route can be built from the values
+	/// of its dynamic segments with [url_for](#method.url_for). Names must be unique; registering
+	/// another route with a name is reported by [validate](#method.validate).
+	pub fn register_named<F, T>(self, prefix: Prefix, path: Path<'a>, name: &str, route: F) -> Self
+	where
+		F: Fn(Vec<String>, Req) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Res> + Send,
+	{
+		self.register_with(prefix, path, RouteMeta::default().name(name), route)
+	}
+
+	/// Register a route handler with [metadata](struct.RouteMeta.html) describing it, which is
+	/// listed by [routes](#method.routes) and, if it has a name, makes the route
+	/// [named](#method.register_named).
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, RouteMeta, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let meta = RouteMeta::default().name("user_detail").tag("users").auth("bearer");
+	/// let router = Router::default().register_with(Method::GET, path![users / {id}], meta, handler);
+	///
+	/// let route = router.routes().next().unwrap();
+	/// assert_eq!(route.meta.unwrap().tags, vec!["users"]);
+	/// assert_eq!(router.url_for("user_detail", &["42"]).unwrap(), "/users/42");
+	/// ```
+	pub fn register_with<F, T>(
+		mut self,
+		prefix: Prefix,
+		path: Path<'a>,
+		meta: RouteMeta,
+		route: F,
+	) -> Self
+	where
+		F: Fn(Vec<String>, Req) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Res> + Send,
+	{
+		let name = meta.name.clone();
+		let errors = insert(
+			self.routes.entry(prefix).or_default(),
+			path.clone(),
+			self.case_insensitive,
+			0,
+			None,
+			Some(Arc::new(meta)),
+			boxed(route),
+		);
+		self.errors.extend(errors);
+		if let Some(name) = name {
+			self.add_names(vec![(name, path)].into_iter().collect());
+		}
+		self
+	}
+
+	fn add_names(&mut self, names: HashMap<String, Path<'a>>) {
+		if names.is_empty() {
+			return;
+		}
+
+		let all = Arc::make_mut(&mut self.names);
+		for (name, path) in names {
+			if all.insert(name.clone(), path).is_some() {
+				self.errors.push(RouteError::DuplicateName { name });
+			}
+		}
+	}
+
+	/// Build the URL path of a named route from the values of its dynamic segments, in the order
+	/// they appear in the path, so links and redirects don't repeat the paths of routes. Values are
+	/// percent-encoded, and a value for a catch-all segment can hold several segments separated by
+	/// `/`. A route with an optional segment takes a value for it or not.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router, UrlError};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default()
+	/// 	.register_named(Method::GET, path![users / {id}], "user_detail", handler)
+	/// 	.register_named(Method::GET, path![files / **], "file", handler);
+	///
+	/// assert_eq!(router.url_for("user_detail", &["42"]).unwrap(), "/users/42");
+	/// assert_eq!(router.url_for("file", &["docs/read me.txt"]).unwrap(), "/files/docs/read%20me.txt");
+	/// assert!(matches!(router.url_for("user_detail", &[]), Err(UrlError::ParamCount { .. })));
+	/// ```
+	pub fn url_for(&self, name: &str, params: &[&str]) -> Result<String, UrlError> {
+		url_for(&self.names, name, params)
+	}
+
+	/// The paths of the named routes, shared with requests so handlers can build URLs.
+	#[cfg(feature = "http")]
+	pub(crate) fn names(&self) -> &RouteNames<'a> {
+		&self.names
+	}
+
+	/// Register a route handler with a priority, which decides between routes that could both
+	/// match a request. Routes have a priority of 0 unless registered here, and at every segment
+	/// the branches leading to routes with a higher priority are tried first. Branches with the
+	/// same priority are tried in the order given by the [precedence](enum.Precedence.html).
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// fn numeric(segment: &str) -> bool {
+	/// 	segment.bytes().all(|b| b.is_ascii_digit())
+	/// }
+	///
+	/// fn short(segment: &str) -> bool {
+	/// 	segment.len() <= 3
+	/// }
+	///
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![items / new], handler)
+	/// 	.register_with_priority(Method::GET, path![items / (numeric)], 2, handler)
+	/// 	.register_with_priority(Method::GET, path![items / (short)], 1, handler);
+	/// let priority = |path| router.find_node(&Method::GET, path).1.unwrap().priority;
+	///
+	/// // Both constrained segments accept "123", and the higher priority wins.
+	/// assert_eq!(priority("/items/123"), Some(2));
+	/// assert_eq!(priority("/items/abc"), Some(1));
+	/// // Without priorities the static segment would be tried first.
+	/// assert_eq!(priority("/items/new"), Some(1));
+	/// ```
+	pub fn register_with_priority<F, T>(
+		mut self,
+		prefix: Prefix,
+		path: Path<'a>,
+		priority: i32,
+		route: F,
+	) -> Self
+	where
+		F: Fn(Vec<String>, Req) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Res> + Send,
+	{
+		let duplicate = insert(
+			self.routes.entry(prefix).or_default(),
+			path,
+			self.case_insensitive,
+			priority,
+			None,
+			None,
+			boxed(route),
+		);
+		self.errors.extend(duplicate);
+		self
+	}
+
+	/// Register a route handler that only matches requests passing a guard, such as requests with
+	/// a specific `Content-Type`. Requests the guard rejects fall through to the other routes for
+	/// the path, tried in the order they were registered and then the route registered without a
+	/// guard, and then to other paths that match the request.
+	///
+	/// ```
+	/// # use grout::{hyper::{header::CONTENT_TYPE, service::Service, Body, Method}, path, PathSegment, Request, Response, ResponseBuilder, Router};
+	/// async fn json(_params: Vec<String>, _req: Request) -> Response {
+	/// 	Ok(ResponseBuilder::default().body(Body::from("json"))?)
+	/// }
+	///
+	/// async fn other(_params: Vec<String>, _req: Request) -> Response {
+	/// 	Ok(ResponseBuilder::default().body(Body::from("other"))?)
+	/// }
+	///
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let is_json = |req: &Request| req.headers().get(CONTENT_TYPE).map_or(false, |v| v == "application/json");
+	/// let mut router = Router::default()
+	/// 	.register_guarded(Method::POST, path![uploads], is_json, json)
+	/// 	.register(Method::POST, path![uploads], other)
+	/// 	.build()?;
+	///
+	/// let mut service = router.call(()).await?;
+	/// let req = hyper::Request::post("/uploads")
+	/// 	.header(CONTENT_TYPE, "application/json")
+	/// 	.body(Body::empty())?;
+	/// let res = service.call(req).await?;
+	/// assert_eq!(hyper::body::to_bytes(res.into_body()).await?, "json");
+	///
+	/// let req = hyper::Request::post("/uploads").body(Body::empty())?;
+	/// let res = service.call(req).await?;
+	/// assert_eq!(hyper::body::to_bytes(res.into_body()).await?, "other");
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn register_guarded<G, F, T>(
+		self,
+		prefix: Prefix,
+		path: Path<'a>,
+		guard: G,
+		route: F,
+	) -> Self
+	where
+		G: Fn(&Req) -> bool + Send + Sync + 'static,
+		F: Fn(Vec<String>, Req) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Res> + Send,
+	{
+		self.register_guarded_boxed(prefix, path, Arc::new(guard), boxed(route))
+	}
+
+	/// Register an already boxed route handler behind a guard, like
+	/// [register_guarded](#method.register_guarded).
+	pub fn register_guarded_boxed(
+		mut self,
+		prefix: Prefix,
+		path: Path<'a>,
+		guard: Guard<Req>,
+		route: DynRoute<Req, Res>,
+	) -> Self {
+		let errors = insert(
+			self.routes.entry(prefix).or_default(),
+			path,
+			self.case_insensitive,
+			0,
+			Some(guard),
+			None,
+			route,
+		);
+		self.errors.extend(errors);
+		self
+	}
+
+	/// Register a route that matches requests under any prefix. A route registered for a specific
+	/// prefix always takes precedence over one registered here for the same path.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn health(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// # async fn get_health(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default()
+	/// 	.register_any(path![health], health)
+	/// 	.register(Method::GET, path![health], get_health);
+	/// assert!(router.find_node(&Method::DELETE, "/health").1.is_some());
+	/// ```
+	pub fn register_any<F, T>(mut self, path: Path<'a>, route: F) -> Self
+	where
+		F: Fn(Vec<String>, Req) -> T + Send + Sync + 'static,
+		T: 'static + Future<Output = Res> + Send,
+	{
+		let duplicate = insert(
+			&mut self.any,
+			path,
+			self.case_insensitive,
+			0,
+			None,
+			None,
+			boxed(route),
+		);
+		self.errors.extend(duplicate);
+		self
+	}
+
+	/// Remove the routes registered for a path under a prefix, guarded or not, along with the
+	/// name of the route. A path ending in an optional segment removes the routes both with and
+	/// without it. Paths without a route are left as they are.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![users / {id}], handler)
+	/// 	.register(Method::DELETE, path![users / {id}], handler)
+	/// 	.unregister(&Method::DELETE, path![users / _]);
+	/// assert!(router.find_node(&Method::GET, "/users/1").1.is_some());
+	/// assert!(router.find_node(&Method::DELETE, "/users/1").1.is_none());
+	/// ```
+	pub fn unregister(mut self, prefix: &Prefix, path: Path<'a>) -> Self {
+		let path = self.stored_path(path);
+		let removed = match self.routes.get_mut(prefix) {
+			Some(node) => remove_optional(node, &path),
+			None => vec![],
+		};
+		self.forget_names(removed);
+		self
+	}
+
+	/// Remove the routes registered for a path with [register_any](#method.register_any), like
+	/// [unregister](#method.unregister).
+	pub fn unregister_any(mut self, path: Path<'a>) -> Self {
+		let path = self.stored_path(path);
+		let removed = remove_optional(&mut self.any, &path);
+		self.forget_names(removed);
+		self
+	}
+
+	fn forget_names(&mut self, removed: Vec<RouteNode<'a, Req, Res>>) {
+		for node in removed {
+			if let Some(name) = node.meta.as_ref().and_then(|meta| meta.name.as_ref()) {
+				Arc::make_mut(&mut self.names).remove(name);
+			}
+		}
+	}
+
+	/// Set whether static or dynamic segments are tried first when matching. See
+	/// [Precedence](enum.Precedence.html) for how this changes which routes match.
+	pub fn precedence(mut self, precedence: Precedence) -> Self {
+		self.precedence = precedence;
+		self
+	}
+
+	/// Set how a trailing slash on a request path is treated. See
+	/// [TrailingSlash](enum.TrailingSlash.html) for the policies.
+	///
+	/// ```
+	/// # use grout::{hyper::{header::LOCATION, Method, StatusCode}, path, test::TestClient, PathSegment, Request, Response, ResponseBuilder, Router, TrailingSlash};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	/// # 	Ok(ResponseBuilder::default().body(hyper::Body::empty())?)
+	/// # }
+	/// # #[tokio::main]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![users], handler)
+	/// 	.trailing_slash(TrailingSlash::RedirectToCanonical)
+	/// 	.build()?;
+	/// let client = TestClient::new(router);
+	///
+	/// let res = client.get("/users/?page=2").await?;
+	/// assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+	/// assert_eq!(res.headers()[LOCATION], "/users?page=2");
+	/// assert_eq!(client.get("/posts/").await?.status(), StatusCode::NOT_FOUND);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+		self.trailing_slash = policy;
+		self
+	}
+
+	/// Set whether the segments of request paths are percent-decoded before they are matched, so
+	/// `/caf%C3%A9` matches `path![café]` and dynamic segments capture decoded values. Segments are
+	/// decoded by default. Segments that aren't valid percent-encoded UTF-8 are matched as they
+	/// are, as are the values captured by catch-all segments, since a decoded `%2F` couldn't be
+	/// told apart from the slashes between segments.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![café], handler)
+	/// 	.register(Method::GET, path![tags / _], handler);
+	/// assert!(router.find_node(&Method::GET, "/caf%C3%A9").1.is_some());
+	/// assert_eq!(router.find_node(&Method::GET, "/tags/a%20b").0, vec!["a b"]);
+	///
+	/// let router = router.decode_paths(false);
+	/// assert!(router.find_node(&Method::GET, "/caf%C3%A9").1.is_none());
+	/// assert_eq!(router.find_node(&Method::GET, "/tags/a%20b").0, vec!["a%20b"]);
+	/// ```
+	pub fn decode_paths(mut self, decode: bool) -> Self {
+		self.decode = decode;
+		self
+	}
+
+	/// Set whether `.` and `..` segments of request paths are resolved before they are matched,
+	/// so `/foo//bar/./` matches the same route as `/foo/bar`, and `/public/../admin` is routed
+	/// to `/admin` and its guards instead of to a route under `/public`. Decoded segments are
+	/// resolved too, so `%2E%2E` climbs like `..` does. Paths that climb above the root match no
+	/// route. Empty segments are always ignored.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![foo / bar], handler)
+	/// 	.normalize_paths(true);
+	/// assert!(router.find_node(&Method::GET, "/foo//bar/./").1.is_some());
+	/// assert!(router.find_node(&Method::GET, "/foo/baz/%2e%2e/bar").1.is_some());
+	/// assert!(router.find_node(&Method::GET, "/../foo/bar").1.is_none());
+	/// ```
+	pub fn normalize_paths(mut self, normalize: bool) -> Self {
+		self.normalize = normalize;
+		self
+	}
+
+	/// Set whether static segments are matched regardless of case, so `/API/Users` matches
+	/// `path![api / users]`, such as for clients of a server where they were the same. Values
+	/// captured by dynamic segments keep their case.
+	///
+	/// Static segments are stored lowercased, so they are still found with a single lookup. This
+	/// applies to the routes already registered, and to the routes registered, mounted or merged
+	/// afterwards. Routes that only differ in case are the same route, and are reported as
+	/// duplicates by [validate](#method.validate). The templates of routes keep their case, but
+	/// the segments of the tree don't, even if case-insensitive matching is turned off again.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, RouteError, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![api / users / _], handler)
+	/// 	.case_insensitive(true);
+	/// let (params, node) = router.find_node(&Method::GET, "/API/Users/Alice");
+	/// assert!(node.is_some());
+	/// assert_eq!(params, vec!["Alice"]);
+	///
+	/// let router = router.register(Method::GET, path![API / Users / _], handler);
+	/// let errors = router.validate().unwrap_err().errors;
+	/// assert_eq!(errors, vec![RouteError::Duplicate { path: "/API/Users/_".into() }]);
+	/// ```
+	pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+		self.case_insensitive = case_insensitive;
+		if case_insensitive {
+			for node in self.routes.values_mut().chain(Some(&mut self.any)) {
+				fold_node(node, "", &mut self.errors);
+			}
+		}
+		self
+	}
+
+	/// A path as it is stored in the route trees of this router.
+	fn stored_path(&self, path: Path<'a>) -> Path<'a> {
+		if self.case_insensitive {
+			fold_path(path)
+		} else {
+			path
+		}
+	}
+
+	/// Report a problem found while registering a route when the router is validated.
+	#[cfg(feature = "http")]
+	pub(crate) fn with_error(mut self, error: RouteError) -> Self {
+		self.errors.push(error);
+		self
+	}
+
+	/// The trailing slash policy of the router.
+	#[cfg(feature = "http")]
+	pub(crate) fn trailing_slash_policy(&self) -> TrailingSlash {
+		self.trailing_slash
+	}
+
+	/// Freeze the routes of this router so they can be mounted in several places with
+	/// [mount_shared](#method.mount_shared). The routes of a shared router can no longer be
+	/// changed.
+	pub fn share(self) -> SharedRouter<'a, Prefix, Req, Res> {
+		SharedRouter {
+			routes: self
+				.routes
+				.into_iter()
+				.map(|(prefix, node)| (prefix, Arc::new(node)))
+				.collect(),
+			any: Arc::new(self.any),
+		}
+	}
+
+	/// Move the routes of another router under a path, keeping their prefixes, so routers built
+	/// separately can be composed. The routes replace any already registered at the same paths, as
+	/// if they were registered again, and their templates include the mount path.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let users = Router::default().register(Method::GET, path![users / _], handler);
+	/// let router = Router::default().mount(path![api / v1], users);
+	/// let (_, node) = router.find_node(&Method::GET, "/api/v1/users/42");
+	/// assert_eq!(node.unwrap().template.as_deref(), Some("/api/v1/users/_"));
+	/// ```
+	pub fn mount(mut self, path: Path<'a>, mut router: Router<'a, Prefix, Req, Res>) -> Self {
+		if self.case_insensitive {
+			router = router.case_insensitive(true);
+		}
+		let mount = Mount::new(&path);
+		mount.move_errors(router.errors, &mut self.errors);
+		let names = router.names.iter().map(|(name, route)| {
+			let mut full = path.clone();
+			full.extend(route.iter().cloned());
+			(name.clone(), full)
+		});
+		self.add_names(names.collect());
+		let path = self.stored_path(path);
+		let mut replaced = vec![];
+		for (prefix, node) in router.routes {
+			let root = self.routes.entry(prefix).or_default();
+			let priority = node.priority;
+			graft(
+				node_at(root, path.clone(), priority),
+				node,
+				&mount,
+				"",
+				0,
+				&mut replaced,
+			);
+		}
+
+		let priority = router.any.priority;
+		graft(
+			node_at(&mut self.any, path, priority),
+			router.any,
+			&mount,
+			"",
+			0,
+			&mut replaced,
+		);
+		self
+	}
+
+	/// Combine the routes of two routers, such as routers built by different crates. Every path
+	/// that has a route in both routers under the same prefix is reported as a conflict, or as the
+	/// same path being mounted from a shared router in both.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, RouteError, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let users = Router::default().register(Method::GET, path![users], handler);
+	/// let posts = Router::default().register(Method::GET, path![posts], handler);
+	/// let router = users.merge(posts).unwrap();
+	/// assert!(router.find_node(&Method::GET, "/posts").1.is_some());
+	///
+	/// let other = Router::default().register(Method::GET, path![users], handler);
+	/// let errors = router.merge(other).unwrap_err().errors;
+	/// assert_eq!(errors, vec![RouteError::Conflict { path: "/users".into() }]);
+	/// ```
+	pub fn merge(mut self, mut other: Router<'a, Prefix, Req, Res>) -> Result<Self, BuildError> {
+		if self.case_insensitive {
+			other = other.case_insensitive(true);
+		}
+		let mount = Mount::new(&[]);
+		mount.move_errors(other.errors, &mut self.errors);
+		self.add_names(other.names.as_ref().clone());
+		let mut errors = vec![];
+		for (prefix, node) in other.routes {
+			graft(
+				self.routes.entry(prefix).or_default(),
+				node,
+				&mount,
+				"",
+				0,
+				&mut errors,
+			);
+		}
+
+		graft(&mut self.any, other.any, &mount, "", 0, &mut errors);
+		if errors.is_empty() {
+			Ok(self)
+		} else {
+			Err(BuildError { errors })
+		}
+	}
+
+	/// Register a group of routes under a common path. The closure registers the routes relative
+	/// to the path on an empty router, which is then [mounted](#method.mount) here.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default().scope(path![admin], |admin| {
+	/// 	admin
+	/// 		.register(Method::GET, path![users], handler)
+	/// 		.register(Method::DELETE, path![users / _], handler)
+	/// });
+	/// assert!(router.find_node(&Method::DELETE, "/admin/users/42").1.is_some());
+	/// ```
+	pub fn scope<F>(self, path: Path<'a>, routes: F) -> Self
+	where
+		F: FnOnce(Router<'a, Prefix, Req, Res>) -> Router<'a, Prefix, Req, Res>,
+	{
+		self.mount(path, routes(Router::default()))
+	}
+
+	/// Mount the routes of a shared router under a path, keeping their prefixes. The routes are
+	/// shared with every other place the router is mounted rather than copied.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let shared = Router::default().register(Method::GET, path![users / _], handler).share();
+	/// let router = Router::default()
+	/// 	.mount_shared(path![api], &shared)
+	/// 	.mount_shared(path![internal], &shared);
+	/// assert!(router.find_node(&Method::GET, "/internal/users/42").1.is_some());
+	/// ```
+	///
+	/// Routes registered under the mount point afterwards take precedence over the shared
+	/// routes, which can't be changed through the routers they are mounted in. The templates of
+	/// shared routes are relative to the shared router, since they can be reached through more
+	/// than one path.
+	pub fn mount_shared(
+		mut self,
+		path: Path<'a>,
+		shared: &SharedRouter<'a, Prefix, Req, Res>,
+	) -> Self
+	where
+		Prefix: Clone,
+	{
+		let template = path_template(&path);
+		let path = self.stored_path(path);
+		let fold = self.case_insensitive;
+		let share = |subtree: &Arc<RouteNode<'a, Req, Res>>, errors: &mut Vec<RouteError>| {
+			let mut subtree = Arc::clone(subtree);
+			if fold {
+				fold_shared(&mut subtree, &template, errors);
+			}
+			subtree
+		};
+		for (prefix, subtree) in &shared.routes {
+			let subtree = share(subtree, &mut self.errors);
+			let root = self.routes.entry(prefix.clone()).or_default();
+			let priority = subtree.priority;
+			node_at(root, path.clone(), priority).shared = Some(subtree);
+		}
+
+		let subtree = share(&shared.any, &mut self.errors);
+		let priority = subtree.priority;
+		node_at(&mut self.any, path, priority).shared = Some(subtree);
+		self
+	}
+
+	/// List the registered routes, sorted by path, such as to print the route table at startup.
+	/// Routes with an optional segment are listed once, and shared routes once for every place
+	/// they are mounted.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![users / {id}], handler)
+	/// 	.register(Method::GET, path![users], handler)
+	/// 	.register_any(path![health], handler);
+	///
+	/// for route in router.routes() {
+	/// 	match route.prefix {
+	/// 		Some(method) => println!("{} {}", method, route.path),
+	/// 		None => println!("* {}", route.path),
+	/// 	}
+	/// }
+	///
+	/// let paths: Vec<String> = router.routes().map(|route| route.path).collect();
+	/// assert_eq!(paths, vec!["/health", "/users", "/users/{id}"]);
+	/// ```
+	pub fn routes(&self) -> impl Iterator<Item = RouteInfo<'_, Prefix>> {
+		let mut routes = vec![];
+		for (prefix, node) in &self.routes {
+			collect_routes(node, Some(prefix), "", None, &mut routes);
+		}
+		collect_routes(&self.any, None, "", None, &mut routes);
+
+		// The nodes with and without an optional segment both hold its route, as the same guarded
+		// route or the unguarded one.
+		let mut seen = HashSet::new();
+		routes.retain(|(route, index)| seen.insert((route.prefix, route.path.clone(), *index)));
+		routes.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+		routes.into_iter().map(|(route, _)| route)
+	}
+
+	/// Check every registered route for problems that would otherwise only surface when a request
+	/// fails to match, or when a route that was replaced by another is never called. The route tree
+	/// is walked once and all problems are reported together.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, RouteError, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default().register(Method::GET, vec![PathSegment::Static("a/b".into())], handler);
+	/// assert_eq!(router.validate().unwrap_err().errors.len(), 1);
+	///
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![users / {id}], handler)
+	/// 	.register(Method::GET, path![users / _], handler);
+	/// let errors = router.validate().unwrap_err().errors;
+	/// assert_eq!(errors, vec![RouteError::Duplicate { path: "/users/_".into() }]);
+	/// ```
+	pub fn validate(&self) -> Result<(), BuildError> {
+		let mut errors = self.errors.clone();
+		for node in self.routes.values().chain(Some(&self.any)) {
+			validate_node(node, "", &mut errors);
+		}
+
+		if errors.is_empty() {
+			Ok(())
+		} else {
+			Err(BuildError { errors })
+		}
+	}
+
+	/// Find the node of the route matching a path under the given prefix, falling back to the
+	/// routes registered with [register_any](#method.register_any) if the prefix has no route for
+	/// the path. Only nodes that have a route are returned.
+	///
+	/// The path must begin with a `/`; anything else (such as the `*` of `OPTIONS *`) matches
+	/// nothing. A query string or fragment is ignored, as are empty segments, so `//foo/` matches
+	/// the same route as `/foo` unless the [trailing slash](#method.trailing_slash) policy is strict.
+	pub fn find_node<'r>(
+		&'r self,
 		prefix: &Prefix,
-		path: &'path str,
-	) -> (Vec<String>, Option<&'path RouteNode<Req, Res>>) {
-		path.strip_prefix('/')
-			.unwrap_or_default()
-			.split('/')
-			.filter(|s| !s.is_empty())
-			.try_fold(
-				(vec![], self.routes.get(prefix)),
-				|(mut params, maybe_node), segment| match maybe_node {
-					None => Err((params, maybe_node)),
-					Some(node) => {
-						let new_node = node.path.as_ref().and_then(|routes| {
-							routes.get(&PathSegment::Static(segment)).or_else(|| {
-								let route = routes.get(&PathSegment::Dynamic);
-								if route.is_some() {
-									params.push(segment.to_owned());
-								}
-
-								route
-							})
-						});
-
-						Ok((params, new_node))
+		path: &'r str,
+	) -> (Vec<String>, Option<&'r RouteNode<'r, Req, Res>>) {
+		let (params, node) = match self.find_prefixed(prefix, path, None) {
+			(_, None) => self.find_any(path, None),
+			found => found,
+		};
+		(params.into_owned().collect(), node)
+	}
+
+	/// Collect the prefixes that have a route for a path, in no particular order. Routes
+	/// registered with [register_any](#method.register_any) are not included.
+	///
+	/// ```
+	/// # use grout::{hyper::Method, path, PathSegment, Request, Response, Router};
+	/// # async fn handler(_params: Vec<String>, _req: Request) -> Response { unimplemented!() }
+	/// let router = Router::default()
+	/// 	.register(Method::GET, path![users / _], handler)
+	/// 	.register(Method::DELETE, path![users / _], handler)
+	/// 	.register(Method::POST, path![users], handler);
+	/// let mut methods = router.methods_for("/users/42");
+	/// methods.sort_by_key(|method| method.as_str());
+	/// assert_eq!(methods, vec![&Method::DELETE, &Method::GET]);
+	/// ```
+	pub fn methods_for(&self, path: &str) -> Vec<&Prefix> {
+		self.prefixes_for(path, None)
+	}
+
+	/// Collect the prefixes that have a route for a path, only counting guarded routes whose guard
+	/// a request passes if there is one.
+	pub(crate) fn prefixes_for(&self, path: &str, req: Option<&Req>) -> Vec<&Prefix> {
+		self.routes
+			.iter()
+			.filter(|(_, root)| self.find_in(Some(root), path, req).1.is_some())
+			.map(|(prefix, _)| prefix)
+			.collect()
+	}
+
+	/// Find the node for a path under the given prefix only, checking guards against a request if
+	/// there is one.
+	pub(crate) fn find_prefixed<'r>(
+		&'r self,
+		prefix: &Prefix,
+		path: &'r str,
+		req: Option<&Req>,
+	) -> (Captures<'r>, Option<&'r RouteNode<'r, Req, Res>>) {
+		self.find_in(self.routes.get(prefix), path, req)
+	}
+
+	/// Find the node for a path among the routes registered for any prefix.
+	pub(crate) fn find_any<'r>(
+		&'r self,
+		path: &'r str,
+		req: Option<&Req>,
+	) -> (Captures<'r>, Option<&'r RouteNode<'r, Req, Res>>) {
+		self.find_in(Some(&self.any), path, req)
+	}
+
+	fn find_in<'r>(
+		&self,
+		root: Option<&'r RouteNode<'r, Req, Res>>,
+		path: &'r str,
+		req: Option<&Req>,
+	) -> (Captures<'r>, Option<&'r RouteNode<'r, Req, Res>>) {
+		let mut walker = Walker {
+			precedence: self.precedence,
+			params: Captures::default(),
+			req,
+		};
+		let strict = self.trailing_slash != TrailingSlash::Ignore;
+		let node = root
+			.filter(|_| !strict || !has_trailing_slash(path))
+			.zip(segments(
+				path,
+				self.decode,
+				self.normalize,
+				self.case_insensitive,
+			))
+			.and_then(|(root, segments)| walker.walk(root, &segments));
+		(walker.params, node)
+	}
+}
+
+/// The number of captured values kept inline, which covers the dynamic segments of most paths.
+const INLINE_CAPTURES: usize = 4;
+
+/// The values captured for the dynamic segments of a path while it is matched, borrowed from the
+/// path where they can be. The first few are kept inline, so matching most paths doesn't allocate
+/// for them; longer lists continue on the heap.
+#[derive(Default)]
+pub(crate) struct Captures<'r> {
+	inline: [Cow<'r, str>; INLINE_CAPTURES],
+	len: usize,
+	spilled: Vec<Cow<'r, str>>,
+}
+
+impl<'r> Captures<'r> {
+	fn push(&mut self, value: Cow<'r, str>) {
+		match self.inline.get_mut(self.len) {
+			Some(slot) => *slot = value,
+			None => self.spilled.push(value),
+		}
+		self.len += 1;
+	}
+
+	fn pop(&mut self) {
+		self.len -= 1;
+		match self.inline.get_mut(self.len) {
+			Some(slot) => *slot = Cow::Borrowed(""),
+			None => drop(self.spilled.pop()),
+		}
+	}
+
+	/// The captured values, in order.
+	pub(crate) fn into_owned(self) -> impl Iterator<Item = String> + 'r {
+		let len = self.len.min(INLINE_CAPTURES);
+		IntoIterator::into_iter(self.inline)
+			.take(len)
+			.chain(self.spilled)
+			.map(Cow::into_owned)
+	}
+}
+
+/// Split a request path into the segments used for matching, percent-decoding them if `decode`
+/// is set, resolving dot segments if `normalize` is and looking them up lowercased if `fold` is.
+/// Paths that climb above the root with `..` have no segments.
+fn segments(path: &str, decode: bool, normalize: bool, fold: bool) -> Option<Vec<Segment<'_>>> {
+	let end = path.find(['?', '#']).unwrap_or(path.len());
+	let path = path[..end].strip_prefix('/')?;
+	let segments = path.split('/').filter(|s| !s.is_empty()).map(|raw| {
+		let value = match percent_decode(raw).filter(|value| decode && value != raw) {
+			Some(value) => Cow::Owned(value),
+			None => Cow::Borrowed(raw),
+		};
+		match fold_case(&value).filter(|_| fold) {
+			Some(folded) => Segment {
+				key: PathSegment::Static(Cow::Owned(folded)),
+				cased: Some(value),
+				raw,
+			},
+			None => Segment {
+				key: PathSegment::Static(value),
+				cased: None,
+				raw,
+			},
+		}
+	});
+	if !normalize {
+		return Some(segments.collect());
+	}
+
+	let mut resolved = vec![];
+	for segment in segments {
+		match segment.value() {
+			"." => {}
+			".." => {
+				resolved.pop()?;
+			}
+			_ => resolved.push(segment),
+		}
+	}
+	Some(resolved)
+}
+
+/// A segment of a request path, as it is matched.
+struct Segment<'r> {
+	/// The value of the segment as the static segment it matches, so static children are found
+	/// with a single lookup whether or not it was decoded or lowercased. It borrows from the path
+	/// unless it was either.
+	key: PathSegment<'r>,
+	/// The value of the segment if the key is lowercased, whose case is kept for constrained and
+	/// dynamic segments.
+	cased: Option<Cow<'r, str>>,
+	/// The segment as it appears in the request path, which catch-all segments capture.
+	raw: &'r str,
+}
+
+impl<'r> Segment<'r> {
+	/// The value of the segment, which is matched against static and constrained segments and
+	/// captured by dynamic ones.
+	fn value(&self) -> &str {
+		match (&self.cased, &self.key) {
+			(Some(value), _) | (None, PathSegment::Static(value)) => value,
+			_ => self.raw,
+		}
+	}
+
+	/// The value of the segment as a parameter, borrowed from the path if it wasn't decoded.
+	fn param(&self) -> Cow<'r, str> {
+		match (&self.cased, &self.key) {
+			(Some(Cow::Borrowed(value)), _) | (None, PathSegment::Static(Cow::Borrowed(value))) => {
+				Cow::Borrowed(value)
+			}
+			_ => Cow::Owned(self.value().to_owned()),
+		}
+	}
+}
+
+/// Decode the percent-encoded bytes of a path segment, or return `None` if the encoding or the
+/// UTF-8 it decodes to is invalid.
+pub(crate) fn percent_decode(segment: &str) -> Option<String> {
+	let mut bytes = Vec::with_capacity(segment.len());
+	let mut rest = segment.bytes();
+	while let Some(byte) = rest.next() {
+		if byte == b'%' {
+			let high = (rest.next()? as char).to_digit(16)?;
+			let low = (rest.next()? as char).to_digit(16)?;
+			bytes.push((high * 16 + low) as u8);
+		} else {
+			bytes.push(byte);
+		}
+	}
+
+	String::from_utf8(bytes).ok()
+}
+
+/// The state of a search for the route matching a path.
+struct Walker<'q, 'r, Req> {
+	precedence: Precedence,
+	/// The captured values, borrowed from the path unless they were decoded or joined.
+	params: Captures<'r>,
+	/// The request being routed, whose guards are checked, if there is one.
+	req: Option<&'q Req>,
+}
+
+impl<'q, 'r, Req> Walker<'q, 'r, Req> {
+	/// Depth-first search for the route matching the remaining segments. At every level the
+	/// segments are tried in the order given by the precedence, backtracking if the first branch
+	/// doesn't lead to a route.
+	fn walk<Res>(
+		&mut self,
+		node: &'r RouteNode<'r, Req, Res>,
+		segments: &[Segment<'r>],
+	) -> Option<&'r RouteNode<'r, Req, Res>> {
+		let (segment, rest) = match segments.split_first() {
+			None => {
+				return Some(node)
+					.filter(|node| node.accepts(self.req))
+					.or_else(|| self.walk_shared(node, segments))
+			}
+			Some(split) => split,
+		};
+		self.walk_children(node, segment, rest)
+			.or_else(|| self.walk_shared(node, segments))
+	}
+
+	fn walk_children<Res>(
+		&mut self,
+		node: &'r RouteNode<'r, Req, Res>,
+		segment: &Segment<'r>,
+		rest: &[Segment<'r>],
+	) -> Option<&'r RouteNode<'r, Req, Res>> {
+		let children = node.path.as_ref()?;
+		if children
+			.values()
+			.any(|child| child.priority.unwrap_or(0) != 0)
+		{
+			return self.walk_prioritized(children, segment, rest);
+		}
+
+		match self.precedence {
+			Precedence::StaticFirst => self
+				.walk_static(children, segment, rest)
+				.or_else(|| self.walk_constrained(children, segment, rest))
+				.or_else(|| self.walk_dynamic(children, segment, rest))
+				.or_else(|| self.walk_catch_all(children, segment, rest)),
+			Precedence::DynamicFirst => self
+				.walk_catch_all(children, segment, rest)
+				.or_else(|| self.walk_dynamic(children, segment, rest))
+				.or_else(|| self.walk_constrained(children, segment, rest))
+				.or_else(|| self.walk_static(children, segment, rest)),
+		}
+	}
+
+	/// Try the children that could match a segment by priority, and then by precedence.
+	fn walk_prioritized<Res>(
+		&mut self,
+		children: &'r RoutePath<'r, Req, Res>,
+		segment: &Segment<'r>,
+		rest: &[Segment<'r>],
+	) -> Option<&'r RouteNode<'r, Req, Res>> {
+		let mut candidates: Vec<(&PathSegment<'_>, &RouteNode<'_, Req, Res>)> = children
+			.iter()
+			.filter(|(key, _)| match key {
+				PathSegment::Static(_) => **key == segment.key,
+				PathSegment::Constrained(matches) => matches(segment.value()),
+				_ => true,
+			})
+			.collect();
+		let rank = |key: &PathSegment<'_>| {
+			let rank = match key {
+				PathSegment::Static(_) => 0,
+				PathSegment::Constrained(_) => 1,
+				PathSegment::CatchAll => 3,
+				_ => 2,
+			};
+			match self.precedence {
+				Precedence::StaticFirst => rank,
+				Precedence::DynamicFirst => 3 - rank,
+			}
+		};
+		candidates.sort_by_key(|(key, child)| (std::cmp::Reverse(child.priority), rank(key)));
+
+		for (key, child) in candidates {
+			let found = match key {
+				PathSegment::Static(_) => self.walk(child, rest),
+				PathSegment::CatchAll => self.walk_catch_all(children, segment, rest),
+				_ => {
+					self.params.push(segment.param());
+					let found = self.walk(child, rest);
+					if found.is_none() {
+						self.params.pop();
 					}
-				},
-			)
-			.unwrap_or_else(|e| e)
+					found
+				}
+			};
+			if found.is_some() {
+				return found;
+			}
+		}
+		None
+	}
+
+	fn walk_static<Res>(
+		&mut self,
+		children: &'r RoutePath<'r, Req, Res>,
+		segment: &Segment<'r>,
+		rest: &[Segment<'r>],
+	) -> Option<&'r RouteNode<'r, Req, Res>> {
+		let child = children.get(&segment.key)?;
+		self.walk(child, rest)
+	}
+
+	fn walk_dynamic<Res>(
+		&mut self,
+		children: &'r RoutePath<'r, Req, Res>,
+		segment: &Segment<'r>,
+		rest: &[Segment<'r>],
+	) -> Option<&'r RouteNode<'r, Req, Res>> {
+		let child = children.get(&PathSegment::Dynamic)?;
+		self.params.push(segment.param());
+		let found = self.walk(child, rest);
+		if found.is_none() {
+			self.params.pop();
+		}
+		found
+	}
+
+	fn walk_constrained<Res>(
+		&mut self,
+		children: &'r RoutePath<'r, Req, Res>,
+		segment: &Segment<'r>,
+		rest: &[Segment<'r>],
+	) -> Option<&'r RouteNode<'r, Req, Res>> {
+		for (key, child) in children {
+			let matches = match key {
+				PathSegment::Constrained(matches) => matches,
+				_ => continue,
+			};
+			if !matches(segment.value()) {
+				continue;
+			}
+
+			self.params.push(segment.param());
+			if let Some(found) = self.walk(child, rest) {
+				return Some(found);
+			}
+			self.params.pop();
+		}
+		None
+	}
+
+	fn walk_catch_all<Res>(
+		&mut self,
+		children: &'r RoutePath<'r, Req, Res>,
+		segment: &Segment<'r>,
+		rest: &[Segment<'r>],
+	) -> Option<&'r RouteNode<'r, Req, Res>> {
+		let child = children.get(&PathSegment::CatchAll)?;
+		if !child.accepts(self.req) {
+			return None;
+		}
+		let remainder = match rest {
+			[] => Cow::Borrowed(segment.raw),
+			rest => {
+				let mut remainder = segment.raw.to_owned();
+				for segment in rest {
+					remainder.push('/');
+					remainder.push_str(segment.raw);
+				}
+				Cow::Owned(remainder)
+			}
+		};
+		self.params.push(remainder);
+		Some(child)
+	}
+
+	fn walk_shared<Res>(
+		&mut self,
+		node: &'r RouteNode<'r, Req, Res>,
+		segments: &[Segment<'r>],
+	) -> Option<&'r RouteNode<'r, Req, Res>> {
+		self.walk(node.shared.as_deref()?, segments)
 	}
 }
@@ -0,0 +1,84 @@
+use grout::{path, RouteError, Router};
+
+type TestRouter = Router<'static, (), (), ()>;
+
+async fn noop(_params: Vec<String>, _req: ()) {}
+
+fn template(router: &TestRouter, path: &str) -> Option<String> {
+	router.find_node(&(), path).1?.template.clone()
+}
+
+#[test]
+fn routes_registered_before_and_after() {
+	let router = TestRouter::default()
+		.register((), path![Users / (shouting)], noop)
+		.case_insensitive(true)
+		.register((), path![Posts / _], noop);
+
+	// Constrained segments see the case of the request.
+	assert_eq!(
+		template(&router, "/users/ABC").as_deref(),
+		Some("/Users/(_)")
+	);
+	assert_eq!(template(&router, "/users/abc"), None);
+	assert_eq!(
+		template(&router, "/POSTS/Hello").as_deref(),
+		Some("/Posts/_")
+	);
+	assert_eq!(router.find_node(&(), "/posts/Hello").0, vec!["Hello"]);
+	assert!(router.validate().is_ok());
+
+	let router = router.unregister(&(), path![USERS / (shouting)]);
+	assert_eq!(template(&router, "/users/ABC"), None);
+}
+
+#[test]
+fn routes_differing_in_case_are_duplicates() {
+	let router = TestRouter::default()
+		.register((), path![users], noop)
+		.register((), path![Users], noop)
+		.register((), path![Users / a], noop)
+		.register((), path![users / b], noop)
+		.case_insensitive(true);
+
+	assert_eq!(
+		router.validate().unwrap_err().errors,
+		vec![RouteError::Duplicate {
+			path: "/users".into()
+		}]
+	);
+	assert!(template(&router, "/USERS/A").is_some());
+	assert!(template(&router, "/USERS/B").is_some());
+}
+
+#[test]
+fn mounted_and_merged_routes() {
+	let shared = TestRouter::default()
+		.register((), path![Shared], noop)
+		.share();
+	let router = TestRouter::default()
+		.case_insensitive(true)
+		.mount(
+			path![Api],
+			TestRouter::default().register((), path![Users], noop),
+		)
+		.mount_shared(path![Internal], &shared)
+		.merge(TestRouter::default().register((), path![Health], noop))
+		.unwrap();
+
+	assert_eq!(
+		template(&router, "/api/users").as_deref(),
+		Some("/Api/Users")
+	);
+	assert!(template(&router, "/INTERNAL/SHARED").is_some());
+	assert!(template(&router, "/health").is_some());
+
+	let router = TestRouter::default()
+		.mount_shared(path![Internal], &shared)
+		.case_insensitive(true);
+	assert!(template(&router, "/internal/shared").is_some());
+}
+
+fn shouting(segment: &str) -> bool {
+	segment.chars().all(char::is_uppercase)
+}
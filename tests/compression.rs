@@ -0,0 +1,264 @@
+#![cfg(feature = "compression")]
+
+use grout::{
+	hyper::{
+		header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE},
+		Body, Method,
+	},
+	path,
+	test::TestClient,
+	Compression, Request, Response, ResponseBuilder, Router,
+};
+
+/// Text that compresses about as well as a web page: words from a small vocabulary in an
+/// irregular order.
+fn text(len: usize) -> Vec<u8> {
+	let words = [
+		"route", "segment", "handler", "request", "response", "body", "header",
+	];
+	let mut state = 0x2545_f491_u32;
+	let mut text = Vec::with_capacity(len + 16);
+	while text.len() < len {
+		state ^= state << 13;
+		state ^= state >> 17;
+		state ^= state << 5;
+		text.extend_from_slice(words[state as usize % words.len()].as_bytes());
+		text.push(if state & 0xf == 0 { b'\n' } else { b' ' });
+	}
+	text.truncate(len);
+	text
+}
+
+async fn buffered(params: Vec<String>, _req: Request) -> Response {
+	let len = params[0].parse().unwrap();
+	Ok(ResponseBuilder::default()
+		.header(CONTENT_TYPE, "text/plain")
+		.body(Body::from(text(len)))?)
+}
+
+async fn streamed(params: Vec<String>, _req: Request) -> Response {
+	let len: usize = params[0].parse().unwrap();
+	let (mut sender, body) = Body::channel();
+	tokio::spawn(async move {
+		let text = text(len);
+		for chunk in text.chunks(len / 5 + 1) {
+			sender.send_data(chunk.to_vec().into()).await.unwrap();
+		}
+	});
+	Ok(ResponseBuilder::default()
+		.header(CONTENT_TYPE, "text/plain")
+		.body(body)?)
+}
+
+fn client() -> TestClient<Method> {
+	let router = Router::default()
+		.register(Method::GET, path![buffered / _], buffered)
+		.register(Method::GET, path![streamed / _], streamed)
+		.build()
+		.unwrap()
+		.compression(Compression::default());
+	TestClient::new(router)
+}
+
+async fn get(
+	client: &TestClient<Method>,
+	path: &str,
+	encoding: &str,
+) -> hyper::Response<hyper::body::Bytes> {
+	let req = hyper::Request::get(path)
+		.header(ACCEPT_ENCODING, encoding)
+		.body(Body::empty())
+		.unwrap();
+	client.send(req).await.unwrap()
+}
+
+#[tokio::test]
+async fn gzip_round_trip() {
+	let client = client();
+	for &len in &[4096, 200 * 1024] {
+		let res = get(&client, &format!("/buffered/{}", len), "gzip").await;
+		assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+		assert_eq!(
+			res.headers()[CONTENT_LENGTH],
+			res.body().len().to_string().as_str()
+		);
+		assert!(res.body().len() < len / 2);
+		assert_eq!(gunzip(res.body()), text(len));
+	}
+}
+
+#[tokio::test]
+async fn deflate_round_trip() {
+	let client = client();
+	for &len in &[4096, 200 * 1024] {
+		let res = get(&client, &format!("/buffered/{}", len), "deflate").await;
+		assert_eq!(res.headers()[CONTENT_ENCODING], "deflate");
+		assert_eq!(zlib_decode(res.body()), text(len));
+	}
+}
+
+#[tokio::test]
+async fn streamed_bodies_are_compressed() {
+	let client = client();
+	for &len in &[4096, 400 * 1024] {
+		let res = get(&client, &format!("/streamed/{}", len), "gzip").await;
+		assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+		assert!(res.headers().get(CONTENT_LENGTH).is_none());
+		assert!(res.body().len() < len / 2);
+		assert_eq!(gunzip(res.body()), text(len));
+
+		let res = get(&client, &format!("/streamed/{}", len), "deflate").await;
+		assert_eq!(zlib_decode(res.body()), text(len));
+	}
+}
+
+#[cfg(feature = "fs")]
+#[tokio::test]
+async fn served_files_are_compressed() {
+	let dir = std::env::temp_dir().join(format!("grout-compression-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	std::fs::write(dir.join("page.txt"), text(300 * 1024)).unwrap();
+	let router = Router::default()
+		.register(Method::GET, path![files / **], grout::fs::serve_dir(&dir))
+		.build()
+		.unwrap()
+		.compression(Compression::default());
+
+	let res = get(&TestClient::new(router), "/files/page.txt", "gzip").await;
+	std::fs::remove_dir_all(&dir).unwrap();
+	assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+	assert!(res.headers().get(CONTENT_LENGTH).is_none());
+	assert_eq!(gunzip(res.body()), text(300 * 1024));
+}
+
+#[tokio::test]
+async fn small_bodies_are_not_compressed() {
+	let res = get(&client(), "/buffered/100", "gzip").await;
+	assert!(res.headers().get(CONTENT_ENCODING).is_none());
+	assert_eq!(res.body().as_ref(), &text(100)[..]);
+}
+
+fn gunzip(data: &[u8]) -> Vec<u8> {
+	assert_eq!(data[..3], [0x1f, 0x8b, 8]);
+	let (body, trailer) = data[10..].split_at(data.len() - 18);
+	let out = inflate(body);
+	assert_eq!(trailer[..4], crc32(&out).to_le_bytes());
+	assert_eq!(trailer[4..], (out.len() as u32).to_le_bytes());
+	out
+}
+
+fn zlib_decode(data: &[u8]) -> Vec<u8> {
+	assert_eq!((u16::from(data[0]) << 8 | u16::from(data[1])) % 31, 0);
+	let (body, trailer) = data[2..].split_at(data.len() - 6);
+	let out = inflate(body);
+	assert_eq!(trailer, adler32(&out).to_be_bytes());
+	out
+}
+
+struct Bits<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl Bits<'_> {
+	fn bit(&mut self) -> u32 {
+		let bit = self.data[self.pos / 8] >> (self.pos % 8) & 1;
+		self.pos += 1;
+		u32::from(bit)
+	}
+
+	/// Read a value packed least significant bit first.
+	fn bits(&mut self, count: u32) -> u32 {
+		(0..count).fold(0, |value, i| value | self.bit() << i)
+	}
+
+	/// Read a Huffman code, packed most significant bit first.
+	fn code(&mut self, count: u32) -> u32 {
+		(0..count).fold(0, |code, _| code << 1 | self.bit())
+	}
+
+	fn fixed_symbol(&mut self) -> u32 {
+		let code = self.code(7);
+		if code <= 23 {
+			return code + 256;
+		}
+		let code = code << 1 | self.bit();
+		match code {
+			48..=191 => code - 48,
+			192..=199 => code - 192 + 280,
+			_ => (code << 1 | self.bit()) - 400 + 144,
+		}
+	}
+}
+
+/// Decompress a DEFLATE stream made of stored and fixed Huffman blocks.
+fn inflate(data: &[u8]) -> Vec<u8> {
+	const LENGTH_BASE: [usize; 29] = [
+		3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+		131, 163, 195, 227, 258,
+	];
+	const DISTANCE_BASE: [usize; 30] = [
+		1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+		2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+	];
+	let extra = |index: usize, skip: usize, per: usize| index.saturating_sub(skip) / per;
+
+	let mut bits = Bits { data, pos: 0 };
+	let mut out = vec![];
+	loop {
+		let last = bits.bit() == 1;
+		match bits.bits(2) {
+			0 => {
+				bits.pos = (bits.pos + 7) & !7;
+				let len = bits.bits(16) as usize;
+				assert_eq!(bits.bits(16) as usize, !len & 0xffff);
+				let start = bits.pos / 8;
+				out.extend_from_slice(&data[start..start + len]);
+				bits.pos += len * 8;
+			}
+			1 => loop {
+				let symbol = bits.fixed_symbol() as usize;
+				match symbol {
+					0..=255 => out.push(symbol as u8),
+					256 => break,
+					_ => {
+						let index = symbol - 257;
+						let extra_bits = if index == 28 { 0 } else { extra(index, 4, 4) };
+						let length = LENGTH_BASE[index] + bits.bits(extra_bits as u32) as usize;
+						let index = bits.code(5) as usize;
+						let distance =
+							DISTANCE_BASE[index] + bits.bits(extra(index, 2, 2) as u32) as usize;
+						for _ in 0..length {
+							out.push(out[out.len() - distance]);
+						}
+					}
+				}
+			},
+			kind => panic!("unexpected block type {}", kind),
+		}
+		if last {
+			assert_eq!((bits.pos + 7) >> 3, data.len(), "data after the last block");
+			return out;
+		}
+	}
+}
+
+fn crc32(data: &[u8]) -> u32 {
+	!data.iter().fold(!0u32, |crc, &byte| {
+		(0..8).fold(crc ^ u32::from(byte), |crc, _| {
+			if crc & 1 == 1 {
+				crc >> 1 ^ 0xedb8_8320
+			} else {
+				crc >> 1
+			}
+		})
+	})
+}
+
+fn adler32(data: &[u8]) -> u32 {
+	let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+		let a = (a + u32::from(byte)) % 65521;
+		(a, (b + a) % 65521)
+	});
+	b << 16 | a
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "config")]
+
+use grout::{
+	config::{Handlers, RouteConfig},
+	hyper::{Body, Method, StatusCode},
+	test::TestClient,
+	Request, Response, ResponseBuilder, Router,
+};
+
+async fn version(params: Vec<String>, _req: Request) -> Response {
+	Ok(ResponseBuilder::default().body(Body::from(params[0].clone()))?)
+}
+
+fn load(
+	handlers: &Handlers<Request, Response>,
+	path: &str,
+) -> Router<'static, Method, Request, Response> {
+	// The configuration is dropped on return, so the router has to own its paths.
+	let config = RouteConfig::from_toml(&format!(
+		"[[routes]]\nmethod = \"GET\"\npath = \"{}\"\nhandler = \"version\"\n",
+		path
+	))
+	.unwrap();
+	Router::default().configure(&config, handlers).unwrap()
+}
+
+#[tokio::test]
+async fn reload_from_dropped_configs() {
+	let handlers = Handlers::default().add("version", version);
+	let router = load(&handlers, "/v1/{id}").build().unwrap();
+	let client = TestClient::new(router.clone());
+	assert_eq!(client.get("/v1/a").await.unwrap().body(), "a");
+
+	router.reload(load(&handlers, "/v2/{id}")).unwrap();
+	assert_eq!(client.get("/v2/b").await.unwrap().body(), "b");
+	assert_eq!(
+		client.get("/v1/a").await.unwrap().status(),
+		StatusCode::NOT_FOUND
+	);
+}
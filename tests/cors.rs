@@ -0,0 +1,79 @@
+#![cfg(feature = "http")]
+
+use grout::{
+	hyper::{
+		header::{ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_ORIGIN, VARY},
+		Body, Method, StatusCode,
+	},
+	path,
+	test::TestClient,
+	Cors, HttpRouter, Request, Response, ResponseBuilder, Router,
+};
+
+async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	Ok(ResponseBuilder::default().body(Body::empty())?)
+}
+
+fn router() -> HttpRouter<Method> {
+	Router::default()
+		.register(Method::GET, path![users], handler)
+		.build()
+		.unwrap()
+}
+
+async fn get(
+	client: &TestClient<Method>,
+	origin: Option<&str>,
+) -> hyper::Response<hyper::body::Bytes> {
+	let req = hyper::Request::get("/users");
+	let req = match origin {
+		Some(origin) => req.header("origin", origin),
+		None => req,
+	};
+	client.send(req.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+#[test]
+#[should_panic(expected = "credentials")]
+fn credentials_from_any_origin_are_rejected() {
+	router().cors(Cors::default().allow_any_origin().allow_credentials(true));
+}
+
+#[tokio::test]
+async fn responses_vary_by_origin() {
+	let cors = Cors::default()
+		.allow_origin("https://example.com")
+		.allow_credentials(true);
+	let client = TestClient::new(router().cors(cors));
+
+	let res = get(&client, Some("https://example.com")).await;
+	assert_eq!(
+		res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+		"https://example.com"
+	);
+	assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+	assert_eq!(res.headers()[VARY], "origin");
+
+	for origin in &[Some("https://evil.example"), None] {
+		let res = get(&client, *origin).await;
+		assert_eq!(res.status(), StatusCode::OK);
+		assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+		assert_eq!(res.headers()[VARY], "origin");
+	}
+
+	// A preflight from a disallowed origin falls through to routing.
+	let req = hyper::Request::options("/users")
+		.header("origin", "https://evil.example")
+		.header("access-control-request-method", "GET")
+		.body(Body::empty())
+		.unwrap();
+	assert_eq!(client.send(req).await.unwrap().headers()[VARY], "origin");
+}
+
+#[tokio::test]
+async fn any_origin_does_not_vary() {
+	let client = TestClient::new(router().cors(Cors::default().allow_any_origin()));
+	let res = get(&client, Some("https://example.com")).await;
+	assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+	assert!(res.headers().get(VARY).is_none());
+}
@@ -0,0 +1,251 @@
+#![cfg(feature = "fs")]
+
+use grout::{
+	fs::serve_dir,
+	hyper::{
+		header::{
+			HeaderName, CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+			IF_RANGE, LAST_MODIFIED, RANGE,
+		},
+		Body, Method, StatusCode,
+	},
+	path,
+	test::TestClient,
+	Router,
+};
+use std::path::{Path, PathBuf};
+
+/// A directory of files to serve, removed when dropped.
+struct Site(PathBuf);
+
+impl Site {
+	/// Create a site with a file outside of its root, which must never be served.
+	fn new(name: &str) -> Self {
+		let dir = std::env::temp_dir().join(format!("grout-fs-{}-{}", name, std::process::id()));
+		std::fs::create_dir_all(dir.join("root/docs")).unwrap();
+		std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+		std::fs::write(dir.join("root/hello.txt"), "hello, world").unwrap();
+		std::fs::write(dir.join("root/index.html"), "home").unwrap();
+		std::fs::write(dir.join("root/docs/index.html"), "docs").unwrap();
+		Self(dir)
+	}
+
+	fn root(&self) -> PathBuf {
+		self.0.join("root")
+	}
+
+	fn client(&self) -> TestClient {
+		let router = Router::default()
+			.register(Method::GET, path![files / **], serve_dir(self.root()))
+			.build()
+			.unwrap();
+		TestClient::new(router)
+	}
+}
+
+impl Drop for Site {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_dir_all(&self.0);
+	}
+}
+
+async fn get(client: &TestClient, uri: &str) -> hyper::Response<hyper::body::Bytes> {
+	get_with(client, uri, &[]).await
+}
+
+async fn get_with(
+	client: &TestClient,
+	uri: &str,
+	headers: &[(HeaderName, &str)],
+) -> hyper::Response<hyper::body::Bytes> {
+	let mut req = hyper::Request::get(uri);
+	for (name, value) in headers {
+		req = req.header(name, *value);
+	}
+	client.send(req.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+#[tokio::test]
+async fn paths_outside_the_root_are_not_found() {
+	let site = Site::new("traversal");
+	assert!(Path::new(&site.0.join("secret.txt")).is_file());
+	let client = site.client();
+
+	for uri in &[
+		"/files/../secret.txt",
+		"/files/docs/../../secret.txt",
+		"/files/%2e%2e/secret.txt",
+		"/files/%2E%2E/secret.txt",
+		"/files/docs%2F..%2F..%2Fsecret.txt",
+		"/files/..%2Fsecret.txt",
+		"/files/..%5Csecret.txt",
+		"/files/docs%5C..%5C..%5Csecret.txt",
+		"/files/hello.txt%00",
+		"/files/hello.txt%00.png",
+		"/files/C:/secret.txt",
+		"/files/C:%5Csecret.txt",
+		"/files/%2e%2e%00/secret.txt",
+	] {
+		let res = get(&client, uri).await;
+		assert_eq!(res.status(), StatusCode::NOT_FOUND, "{}", uri);
+		assert_ne!(res.body().as_ref(), b"secret", "{}", uri);
+	}
+}
+
+#[tokio::test]
+async fn files_are_served() {
+	let site = Site::new("files");
+	let client = site.client();
+
+	let res = get(&client, "/files/hello.txt").await;
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+	assert_eq!(res.body().as_ref(), b"hello, world");
+
+	let res = get(&client, "/files/./docs/%69ndex.html").await;
+	assert_eq!(res.body().as_ref(), b"docs");
+
+	assert_eq!(
+		get(&client, "/files/missing.txt").await.status(),
+		StatusCode::NOT_FOUND
+	);
+}
+
+#[tokio::test]
+async fn directories_serve_their_index() {
+	let site = Site::new("index");
+	let client = site.client();
+
+	for uri in &["/files/docs", "/files/docs/"] {
+		let res = get(&client, uri).await;
+		assert_eq!(res.status(), StatusCode::OK, "{}", uri);
+		assert_eq!(res.headers()["content-type"], "text/html; charset=utf-8");
+		assert_eq!(res.body().as_ref(), b"docs", "{}", uri);
+	}
+	assert_eq!(get(&client, "/files/.").await.body().as_ref(), b"home");
+}
+
+/// Get `/files/hello.txt` with a `Range` header, returning the status, `Content-Range` and body.
+async fn range(client: &TestClient, range: &str) -> (StatusCode, Option<String>, String) {
+	let res = get_with(client, "/files/hello.txt", &[(RANGE, range)]).await;
+	let content_range = res
+		.headers()
+		.get(CONTENT_RANGE)
+		.map(|range| range.to_str().unwrap().to_owned());
+	if let Some(len) = res.headers().get(CONTENT_LENGTH) {
+		assert_eq!(len.to_str().unwrap(), res.body().len().to_string());
+	}
+	let body = String::from_utf8(res.body().to_vec()).unwrap();
+	(res.status(), content_range, body)
+}
+
+#[tokio::test]
+async fn byte_ranges() {
+	let site = Site::new("ranges");
+	let client = site.client();
+	let partial = |range: &str, body: &str| {
+		(
+			StatusCode::PARTIAL_CONTENT,
+			Some(format!("bytes {}/12", range)),
+			body.to_owned(),
+		)
+	};
+	let unsatisfiable = (
+		StatusCode::RANGE_NOT_SATISFIABLE,
+		Some("bytes */12".to_owned()),
+		String::new(),
+	);
+	let whole = (StatusCode::OK, None, "hello, world".to_owned());
+
+	assert_eq!(range(&client, "bytes=0-0").await, partial("0-0", "h"));
+	assert_eq!(range(&client, "bytes=0-4").await, partial("0-4", "hello"));
+	assert_eq!(
+		range(&client, "bytes=7-100").await,
+		partial("7-11", "world")
+	);
+	assert_eq!(range(&client, "bytes=5-").await, partial("5-11", ", world"));
+	assert_eq!(range(&client, "bytes=-5").await, partial("7-11", "world"));
+	assert_eq!(
+		range(&client, "bytes=-100").await,
+		partial("0-11", "hello, world")
+	);
+
+	assert_eq!(range(&client, "bytes=12-").await, unsatisfiable);
+	assert_eq!(range(&client, "bytes=50-60").await, unsatisfiable);
+	assert_eq!(range(&client, "bytes=-0").await, unsatisfiable);
+
+	assert_eq!(range(&client, "bytes=0-1,5-6").await, whole);
+	assert_eq!(range(&client, "bytes=4-2").await, whole);
+	assert_eq!(range(&client, "lines=0-1").await, whole);
+	assert_eq!(range(&client, "bytes=a-b").await, whole);
+}
+
+#[tokio::test]
+async fn conditional_requests() {
+	let site = Site::new("conditional");
+	let client = site.client();
+	let res = get(&client, "/files/hello.txt").await;
+	let etag = res.headers()[ETAG].to_str().unwrap().to_owned();
+	let modified = res.headers()[LAST_MODIFIED].to_str().unwrap().to_owned();
+	assert!(etag.starts_with("W/\""));
+	let status = |headers: Vec<(HeaderName, String)>| {
+		let client = client.clone();
+		let etag = etag.clone();
+		async move {
+			let headers: Vec<_> = headers
+				.iter()
+				.map(|(name, value)| (name.clone(), value.as_str()))
+				.collect();
+			let res = get_with(&client, "/files/hello.txt", &headers).await;
+			if res.status() == StatusCode::NOT_MODIFIED {
+				assert!(res.body().is_empty());
+				assert_eq!(res.headers()[ETAG], etag.as_str());
+			}
+			res.status()
+		}
+	};
+	let epoch = "Thu, 01 Jan 1970 00:00:00 GMT".to_owned();
+
+	// If-None-Match compares weakly, so the strong form of the tag matches too.
+	let strong = etag.trim_start_matches("W/").to_owned();
+	for tags in &[
+		etag.clone(),
+		strong.clone(),
+		format!("\"other\", {}", etag),
+		"*".to_owned(),
+	] {
+		let status = status(vec![(IF_NONE_MATCH, tags.clone())]).await;
+		assert_eq!(status, StatusCode::NOT_MODIFIED, "{}", tags);
+	}
+	assert_eq!(
+		status(vec![(IF_NONE_MATCH, "\"other\"".to_owned())]).await,
+		StatusCode::OK
+	);
+
+	assert_eq!(
+		status(vec![(IF_MODIFIED_SINCE, modified.clone())]).await,
+		StatusCode::NOT_MODIFIED
+	);
+	assert_eq!(
+		status(vec![(IF_MODIFIED_SINCE, epoch.clone())]).await,
+		StatusCode::OK
+	);
+	// If-Modified-Since is ignored when If-None-Match is sent.
+	assert_eq!(
+		status(vec![
+			(IF_NONE_MATCH, "\"other\"".to_owned()),
+			(IF_MODIFIED_SINCE, modified.clone()),
+		])
+		.await,
+		StatusCode::OK
+	);
+
+	// A range is only honored if If-Range names the current version of the file, and weak tags
+	// never do.
+	let ranged =
+		|if_range: String| status(vec![(RANGE, "bytes=0-4".to_owned()), (IF_RANGE, if_range)]);
+	assert_eq!(ranged(modified.clone()).await, StatusCode::PARTIAL_CONTENT);
+	assert_eq!(ranged(epoch).await, StatusCode::OK);
+	assert_eq!(ranged(etag.clone()).await, StatusCode::OK);
+	assert_eq!(ranged(strong).await, StatusCode::OK);
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "http")]
+
+use grout::{
+	hyper::{header::CONTENT_LENGTH, Body, Method, StatusCode},
+	path,
+	test::TestClient,
+	HttpRouter, Next, Request, Response, ResponseBuilder, Router,
+};
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc,
+};
+
+async fn handler(_params: Vec<String>, _req: Request) -> Response {
+	Ok(ResponseBuilder::default().body(Body::empty())?)
+}
+
+/// A router with middleware that buffers the whole request body, like csrf does for forms, and
+/// records whether it ran.
+fn router(ran: &Arc<AtomicBool>) -> HttpRouter<Method> {
+	let ran = Arc::clone(ran);
+	Router::default()
+		.register(Method::POST, path![upload], handler)
+		.build()
+		.unwrap()
+		.max_body_size(8)
+		.middleware(move |mut req: Request, next: Next| {
+			ran.store(true, Ordering::SeqCst);
+			async move {
+				let body = hyper::body::to_bytes(std::mem::take(req.body_mut())).await?;
+				*req.body_mut() = Body::from(body);
+				next.run(req).await
+			}
+		})
+}
+
+fn upload(body: &'static str) -> hyper::Request<Body> {
+	hyper::Request::post("/upload")
+		.body(Body::from(body))
+		.unwrap()
+}
+
+#[tokio::test]
+async fn declared_bodies_over_the_limit_are_rejected_before_middleware() {
+	let ran = Arc::new(AtomicBool::new(false));
+	let client = TestClient::new(router(&ran));
+
+	let mut req = upload("much too long");
+	req.headers_mut()
+		.insert(CONTENT_LENGTH, "13".parse().unwrap());
+	let res = client.send(req).await.unwrap();
+	assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+	assert!(!ran.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn middleware_reads_limited_bodies() {
+	let ran = Arc::new(AtomicBool::new(false));
+	let client = TestClient::new(router(&ran));
+
+	let res = client.send(upload("much too long")).await.unwrap();
+	assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+	assert!(ran.load(Ordering::SeqCst));
+
+	let res = client.send(upload("short")).await.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn long_uris_are_rejected_before_middleware() {
+	let ran = Arc::new(AtomicBool::new(false));
+	let client = TestClient::new(router(&ran).max_path_segments(2));
+
+	let req = hyper::Request::post("/upload/a/b")
+		.body(Body::empty())
+		.unwrap();
+	let res = client.send(req).await.unwrap();
+	assert_eq!(res.status(), StatusCode::URI_TOO_LONG);
+	assert!(!ran.load(Ordering::SeqCst));
+}
@@ -0,0 +1,153 @@
+#![cfg(feature = "multipart")]
+
+use grout::{
+	body,
+	hyper::{header::CONTENT_TYPE, Body, StatusCode},
+	Error,
+};
+
+const BOUNDARY: &str = "XyZ";
+
+/// A request with a multipart body, sent in chunks of a size.
+fn request(body: &'static [u8], chunk_size: usize) -> grout::Request {
+	let (mut sender, stream) = Body::channel();
+	tokio::spawn(async move {
+		for chunk in body.chunks(chunk_size) {
+			if sender.send_data(chunk.into()).await.is_err() {
+				return;
+			}
+		}
+	});
+	hyper::Request::post("/")
+		.header(
+			CONTENT_TYPE,
+			format!("multipart/form-data; boundary={}", BOUNDARY),
+		)
+		.body(stream)
+		.unwrap()
+}
+
+/// Read every field of a body with the names of the fields and their contents, unless their name
+/// is `skip`. The body is sent in chunks of every size, so every delimiter, including the close
+/// delimiter, is split across chunks at every position, and the fields must be the same each time.
+async fn fields(
+	body: &'static [u8],
+	limit: Option<u64>,
+) -> Result<Vec<(Option<String>, Vec<u8>)>, Error> {
+	let mut all = None;
+	for chunk_size in 1..=body.len() {
+		let mut multipart = body::multipart(request(body, chunk_size))?;
+		if let Some(limit) = limit {
+			multipart = multipart.field_limit(limit);
+		}
+
+		let mut fields = vec![];
+		while let Some(field) = multipart.next_field().await? {
+			let name = field.name().map(str::to_owned);
+			let contents = match name.as_deref() {
+				Some("skip") => b"<skipped>".to_vec(),
+				_ => field.bytes().await?.to_vec(),
+			};
+			fields.push((name, contents));
+		}
+		match &all {
+			None => all = Some(fields),
+			Some(all) => assert_eq!(*all, fields, "with chunks of {} bytes", chunk_size),
+		}
+	}
+	Ok(all.unwrap())
+}
+
+fn field(name: &str, contents: &[u8]) -> (Option<String>, Vec<u8>) {
+	(Some(name.to_owned()), contents.to_vec())
+}
+
+#[tokio::test]
+async fn preamble_and_epilogue_are_ignored() {
+	let body = b"This is the preamble, where --XyZ\r\n-XyZ\r\n--XyY\r\n\
+		--XyZ\r\n\
+		Content-Disposition: form-data; name=\"a\"\r\n\
+		\r\n\
+		one\r\n\
+		--XyZ--\r\n\
+		This is the epilogue.";
+	assert_eq!(fields(body, None).await.unwrap(), vec![field("a", b"one")]);
+}
+
+#[tokio::test]
+async fn part_without_headers() {
+	let body = b"--XyZ\r\n\
+		\r\n\
+		anonymous\r\n\
+		--XyZ\r\n\
+		Content-Disposition: form-data; name=\"b\"\r\n\
+		\r\n\
+		two\r\n\
+		--XyZ--";
+	assert_eq!(
+		fields(body, None).await.unwrap(),
+		vec![(None, b"anonymous".to_vec()), field("b", b"two")]
+	);
+}
+
+#[tokio::test]
+async fn near_miss_delimiters_are_contents() {
+	let body = b"--XyZ\r\n\
+		Content-Disposition: form-data; name=\"a\"\r\n\
+		\r\n\
+		--XyZ\r\n-XyZ\r\n--XyY\r\n--Xy\r\r\n\r\n--\r\n--XyZ\r\n\
+		Content-Disposition: form-data; name=\"b\"\r\n\
+		\r\n\
+		\r\n--XyZ--";
+	assert_eq!(
+		fields(body, None).await.unwrap(),
+		vec![
+			field("a", b"--XyZ\r\n-XyZ\r\n--XyY\r\n--Xy\r\r\n\r\n--"),
+			field("b", b""),
+		]
+	);
+}
+
+#[tokio::test]
+async fn unread_fields_are_skipped() {
+	let body = b"--XyZ\r\n\
+		Content-Disposition: form-data; name=\"skip\"\r\n\
+		\r\n\
+		not read at all\r\n\
+		--XyZ\r\n\
+		Content-Disposition: form-data; name=\"c\"\r\n\
+		\r\n\
+		three\r\n\
+		--XyZ--";
+	assert_eq!(
+		fields(body, None).await.unwrap(),
+		vec![field("skip", b"<skipped>"), field("c", b"three")]
+	);
+}
+
+#[tokio::test]
+async fn field_limit() {
+	let body = b"--XyZ\r\n\
+		Content-Disposition: form-data; name=\"a\"\r\n\
+		\r\n\
+		0123456789\r\n\
+		--XyZ--";
+	assert_eq!(
+		fields(body, Some(10)).await.unwrap(),
+		vec![field("a", b"0123456789")]
+	);
+
+	let error = fields(body, Some(9)).await.unwrap_err();
+	assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn truncated_body() {
+	let body = b"--XyZ\r\n\
+		Content-Disposition: form-data; name=\"a\"\r\n\
+		\r\n\
+		one\r\n\
+		--Xy";
+	let error = fields(body, None).await.unwrap_err();
+	assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+}
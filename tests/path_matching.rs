@@ -0,0 +1,141 @@
+use grout::{path_template, Path, PathSegment, Precedence, Router};
+use proptest::prelude::*;
+
+type TestRouter = Router<'static, (), (), ()>;
+
+async fn noop(_params: Vec<String>, _req: ()) {}
+
+fn route_segment() -> impl Strategy<Value = PathSegment<'static>> {
+	prop_oneof![
+		Just(PathSegment::Static("a".into())),
+		Just(PathSegment::Static("b".into())),
+		Just(PathSegment::Static("c".into())),
+		Just(PathSegment::Dynamic),
+	]
+}
+
+fn route() -> impl Strategy<Value = Path<'static>> {
+	(prop::collection::vec(route_segment(), 0..4), any::<bool>()).prop_map(
+		|(mut route, catch_all)| {
+			if catch_all {
+				route.push(PathSegment::CatchAll);
+			}
+			route
+		},
+	)
+}
+
+fn request_path() -> impl Strategy<Value = String> {
+	let segment = prop::sample::select(vec!["a", "b", "c", "d", "%62", ""]);
+	(
+		prop::sample::select(vec!["/", "//", ""]),
+		prop::collection::vec(segment, 0..5),
+		prop::sample::select(vec!["", "/", "?a/b", "#c"]),
+	)
+		.prop_map(|(lead, segments, tail)| format!("{}{}{}", lead, segments.join("/"), tail))
+}
+
+/// Tokenize a request path the way the router documents it.
+fn reference_segments(path: &str) -> Option<Vec<&str>> {
+	let path = path.split(['?', '#']).next().unwrap();
+	if !path.starts_with('/') {
+		return None;
+	}
+
+	Some(path.split('/').filter(|s| !s.is_empty()).collect())
+}
+
+/// Decode a segment of a request path, which only ever encodes `b`.
+fn reference_decode(segment: &str) -> String {
+	segment.replace("%62", "b")
+}
+
+/// Whether a route matches the segments of a request, segment by segment.
+fn reference_matches(route: &[PathSegment<'static>], segments: &[&str]) -> bool {
+	match route.split_last() {
+		Some((PathSegment::CatchAll, init)) => {
+			segments.len() > init.len() && reference_matches(init, &segments[..init.len()])
+		}
+		_ => {
+			route.len() == segments.len()
+				&& route
+					.iter()
+					.zip(segments)
+					.all(|(expected, actual)| match expected {
+						PathSegment::Static(s) => *s == reference_decode(actual),
+						_ => true,
+					})
+		}
+	}
+}
+
+/// How early a kind of segment is tried at each position.
+fn reference_rank(segment: &PathSegment<'static>, precedence: Precedence) -> u8 {
+	let rank = match segment {
+		PathSegment::Static(_) => 2,
+		PathSegment::CatchAll => 0,
+		_ => 1,
+	};
+	match precedence {
+		Precedence::StaticFirst => rank,
+		Precedence::DynamicFirst => 2 - rank,
+	}
+}
+
+/// Match a request against a flat list of routes: among the routes that match, prefer the one
+/// whose segments are of the kinds tried first, comparing position by position.
+fn reference_match(
+	routes: &[Path<'static>],
+	path: &str,
+	precedence: Precedence,
+) -> Option<(String, Vec<String>)> {
+	let segments = reference_segments(path)?;
+	routes
+		.iter()
+		.filter(|route| reference_matches(route, &segments))
+		.max_by_key(|route| {
+			route
+				.iter()
+				.map(|segment| reference_rank(segment, precedence))
+				.collect::<Vec<_>>()
+		})
+		.map(|route| {
+			let mut params: Vec<String> = route
+				.iter()
+				.zip(&segments)
+				.filter(|(expected, _)| **expected == PathSegment::Dynamic)
+				.map(|(_, actual)| reference_decode(actual))
+				.collect();
+			if route.last() == Some(&PathSegment::CatchAll) {
+				params.push(segments[route.len() - 1..].join("/"));
+			}
+			(path_template(route), params)
+		})
+}
+
+proptest! {
+	#[test]
+	fn arbitrary_paths_do_not_panic(routes in prop::collection::vec(route(), 0..8), path in ".*") {
+		let router = routes
+			.into_iter()
+			.fold(TestRouter::default(), |router, route| router.register((), route, noop));
+		router.find_node(&(), &path);
+	}
+
+	#[test]
+	fn matches_reference(
+		routes in prop::collection::vec(route(), 0..8),
+		path in request_path(),
+		precedence in prop_oneof![Just(Precedence::StaticFirst), Just(Precedence::DynamicFirst)],
+	) {
+		let router = routes
+			.iter()
+			.cloned()
+			.fold(TestRouter::default(), |router, route| router.register((), route, noop))
+			.precedence(precedence);
+
+		let (params, node) = router.find_node(&(), &path);
+		let found = node.map(|node| (node.template.clone().unwrap(), params));
+		prop_assert_eq!(found, reference_match(&routes, &path, precedence));
+	}
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "session")]
+
+use grout::{
+	hyper::{
+		header::{COOKIE, SET_COOKIE},
+		Body, Method, StatusCode,
+	},
+	path,
+	session::{MemoryStore, Sessions},
+	test::TestClient,
+	Error, Request, RequestExt, Response, ResponseBuilder, Router,
+};
+
+fn respond(body: String) -> Response {
+	Ok(ResponseBuilder::default().body(Body::from(body))?)
+}
+
+fn failed() -> Response {
+	Err(Error::new(
+		StatusCode::INTERNAL_SERVER_ERROR,
+		"handler failed",
+	))
+}
+
+async fn login(_params: Vec<String>, req: Request) -> Response {
+	let session = req.session().unwrap();
+	session.insert("user", "ferris");
+	session.regenerate();
+	respond(String::new())
+}
+
+async fn whoami(_params: Vec<String>, req: Request) -> Response {
+	respond(req.session().unwrap().get("user").unwrap_or_default())
+}
+
+/// Change the session the way the action in the path would, then fail.
+async fn fail(params: Vec<String>, req: Request) -> Response {
+	let session = req.session().unwrap();
+	match params[0].as_str() {
+		"destroy" => session.destroy(),
+		"regenerate" => session.regenerate(),
+		_ => session.insert("user", "mallory"),
+	}
+	failed()
+}
+
+fn client() -> TestClient {
+	let sessions = Sessions::new(MemoryStore::default(), b"a secret of at least 32 bytes....");
+	let router = Router::default()
+		.register(Method::GET, path![login], login)
+		.register(Method::GET, path![whoami], whoami)
+		.register(Method::GET, path![fail / _], fail)
+		.build()
+		.unwrap()
+		.middleware(sessions.middleware());
+	TestClient::new(router)
+}
+
+async fn get(client: &TestClient, uri: &str, cookie: &str) -> hyper::Response<hyper::body::Bytes> {
+	let req = hyper::Request::get(uri)
+		.header(COOKIE, cookie)
+		.body(Body::empty())
+		.unwrap();
+	client.send(req).await.unwrap()
+}
+
+/// Log in, returning the session cookie to send back.
+async fn log_in(client: &TestClient) -> String {
+	let res = client.get("/login").await.unwrap();
+	let cookie = res.headers()[SET_COOKIE].to_str().unwrap();
+	let cookie = cookie.split(';').next().unwrap().to_owned();
+	assert_eq!(get(client, "/whoami", &cookie).await.body(), "ferris");
+	cookie
+}
+
+#[tokio::test]
+async fn destroyed_sessions_are_removed_when_the_handler_fails() {
+	let client = client();
+	let cookie = log_in(&client).await;
+
+	let res = get(&client, "/fail/destroy", &cookie).await;
+	assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+	assert_eq!(get(&client, "/whoami", &cookie).await.body(), "");
+}
+
+#[tokio::test]
+async fn regenerated_sessions_retire_their_id_when_the_handler_fails() {
+	let client = client();
+	let cookie = log_in(&client).await;
+
+	let res = get(&client, "/fail/regenerate", &cookie).await;
+	assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+	assert_eq!(get(&client, "/whoami", &cookie).await.body(), "");
+}
+
+#[tokio::test]
+async fn changes_are_not_saved_when_the_handler_fails() {
+	let client = client();
+	let cookie = log_in(&client).await;
+
+	let res = get(&client, "/fail/rename", &cookie).await;
+	assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+	assert_eq!(get(&client, "/whoami", &cookie).await.body(), "ferris");
+}
@@ -0,0 +1,22 @@
+use grout::{PathSegment::*, RouteMeta, Router, UrlError};
+
+async fn noop(_params: Vec<String>, _req: ()) {}
+
+#[test]
+fn optional_before_dynamic_runs_out_of_values() {
+	let router = Router::<'static, (), (), ()>::default().register_with(
+		(),
+		vec![Static("a".into()), Optional, Dynamic],
+		RouteMeta::default().name("x"),
+		noop,
+	);
+	assert_eq!(
+		router.url_for("x", &["1"]),
+		Err(UrlError::ParamCount {
+			name: "x".to_owned(),
+			expected: 1,
+			given: 1,
+		})
+	);
+	assert_eq!(router.url_for("x", &["1", "2"]).unwrap(), "/a/1/2");
+}
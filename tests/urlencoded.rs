@@ -0,0 +1,23 @@
+#![cfg(feature = "query")]
+
+use grout::Query;
+use std::collections::HashMap;
+
+#[test]
+fn many_distinct_keys() {
+	// Grouping the values by a linear search of the keys made this take tens of seconds.
+	let query: Vec<String> = (0..200_000).map(|i| format!("k{}=", i)).collect();
+	let query = Query::parse(&query.join("&")).unwrap();
+	let fields: HashMap<String, String> = query.deserialize().unwrap();
+	assert_eq!(fields.len(), 200_000);
+	assert_eq!(fields["k199999"], "");
+}
+
+#[test]
+fn repeated_keys_are_grouped() {
+	let query = Query::parse("a=1&b=2&a=3&c=4&a=5").unwrap();
+	let fields: HashMap<String, Vec<u32>> = query.deserialize().unwrap();
+	assert_eq!(fields["a"], [1, 3, 5]);
+	assert_eq!(fields["b"], [2]);
+	assert_eq!(fields["c"], [4]);
+}